@@ -0,0 +1,174 @@
+//! Per-block timings of the DSP hot paths at the jammers' block size.
+//!
+//! Run with `cargo bench`, optionally followed by `-- <filter>` to only run
+//! benchmarks whose name contains the filter. Each benchmark reports the
+//! mean and fastest time per block and the mean as a fraction of the
+//! block's real-time duration.
+
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use echo_nlms::test_util::kernels::{self, scalar};
+use echo_nlms::test_util::{chord, to_i16, white_noise, white_noise_i16};
+use echo_nlms::{NlmsCanceller, UpdateRule};
+use myjammer::synth::Synthesizer;
+use pitch_detect::PitchDetector;
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+const MIN_FREQ: f32 = 60.0;
+const MAX_FREQ: f32 = 1000.0;
+const KERNEL_TAPS: usize = 2048;
+/// Peak of the white noise render and near-end signals, -18 dBFS.
+const NOISE_PEAK: f32 = 4096.0;
+/// A major triad for the pitch detection benchmarks.
+const CHORD: [f32; 3] = [220.0, 277.2, 329.6];
+const WARMUP: Duration = Duration::from_millis(200);
+const MEASUREMENT: Duration = Duration::from_secs(1);
+
+fn main() {
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let selected = |name: &str| filter.as_deref().is_none_or(|f| name.contains(f));
+
+    let render = white_noise_i16(BLOCK, NOISE_PEAK, 0x1234_5678);
+    let capture: Vec<i16> = render
+        .iter()
+        .zip(white_noise_i16(BLOCK, NOISE_PEAK, 0x8765_4321))
+        .map(|(&echo, near)| echo / 2 + near / 8)
+        .collect();
+    for taps in [512, 1024, 2048] {
+        let name = format!("nlms_process_block/{taps}_taps");
+        if selected(&name) {
+            let mut canceller = NlmsCanceller::new(taps, 0.5);
+            let mut output = vec![0i16; BLOCK];
+            bench(&name, BLOCK, || {
+                canceller.process_block(&render, &capture, &mut output, true);
+                black_box(&output);
+            });
+        }
+    }
+
+    let name = "nlms_process_block/1024_taps_proportionate";
+    if selected(name) {
+        let mut canceller = NlmsCanceller::new(1024, 0.5);
+        canceller.set_proportionate(true);
+        let mut output = vec![0i16; BLOCK];
+        bench(name, BLOCK, || {
+            canceller.process_block(&render, &capture, &mut output, true);
+            black_box(&output);
+        });
+    }
+
+    for (suffix, rule) in [
+        ("sign_error", UpdateRule::SignError),
+        ("sign_sign", UpdateRule::SignSign),
+    ] {
+        let name = format!("nlms_process_block/1024_taps_{suffix}");
+        if selected(&name) {
+            let mut canceller = NlmsCanceller::new(1024, 0.5);
+            canceller.set_update_rule(rule);
+            let mut output = vec![0i16; BLOCK];
+            bench(&name, BLOCK, || {
+                canceller.process_block(&render, &capture, &mut output, true);
+                black_box(&output);
+            });
+        }
+    }
+
+    // The NLMS kernels alone, one call per sample of a block as the canceller
+    // makes them, vectorized and scalar.
+    let taps = white_noise(KERNEL_TAPS, 0.5, 0x1234_5678);
+    let history = white_noise(KERNEL_TAPS, 8000.0, 0x8765_4321);
+    for (name, dot_rev) in [
+        (
+            "dot_rev/2048_taps/simd",
+            kernels::dot_rev as fn(&[f32], &[f32]) -> f32,
+        ),
+        ("dot_rev/2048_taps/scalar", scalar::dot_rev),
+    ] {
+        if selected(name) {
+            bench(name, BLOCK, || {
+                for _ in 0..BLOCK {
+                    black_box(dot_rev(black_box(&taps), black_box(&history)));
+                }
+            });
+        }
+    }
+    for (name, leaky_axpy_rev) in [
+        (
+            "leaky_axpy_rev/2048_taps/simd",
+            kernels::leaky_axpy_rev as fn(&mut [f32], &[f32], f32, f32),
+        ),
+        ("leaky_axpy_rev/2048_taps/scalar", scalar::leaky_axpy_rev),
+    ] {
+        if selected(name) {
+            let mut taps = taps.clone();
+            bench(name, BLOCK, || {
+                for _ in 0..BLOCK {
+                    leaky_axpy_rev(&mut taps, black_box(&history), 1.0, 1e-9);
+                }
+                black_box(&taps);
+            });
+        }
+    }
+
+    let chord = to_i16(&chord(
+        BLOCK,
+        &CHORD,
+        SAMPLE_RATE,
+        8000.0 / CHORD.len() as f32,
+    ));
+    for (name, coarse) in [
+        ("detect_pitches/full_search", None),
+        ("detect_pitches/coarse_search", Some(4)),
+    ] {
+        if selected(name) {
+            let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, MIN_FREQ, MAX_FREQ);
+            detector.set_max_results(3);
+            detector.set_coarse_search(coarse);
+            bench(name, BLOCK, || {
+                black_box(detector.detect(&chord));
+            });
+        }
+    }
+
+    let name = "synthesize_chunk/3_voices_stereo";
+    if selected(name) {
+        let mut synth = Synthesizer::new(3, 2, SAMPLE_RATE);
+        synth.set_target_gain(0.5);
+        let freqs = [311.1, 392.0, 466.2];
+        let mut output = vec![0i16; BLOCK * 2];
+        bench(name, BLOCK, || {
+            synth.render(&freqs, &mut output);
+            black_box(&output);
+        });
+    }
+}
+
+/// Runs `block` repeatedly for a warm-up period and then for the measurement
+/// period, printing the timing of one call processing `frames` frames.
+fn bench(name: &str, frames: usize, mut block: impl FnMut()) {
+    let start = Instant::now();
+    while start.elapsed() < WARMUP {
+        block();
+    }
+
+    let mut iterations = 0u32;
+    let mut fastest = Duration::MAX;
+    let start = Instant::now();
+    while start.elapsed() < MEASUREMENT {
+        let call = Instant::now();
+        block();
+        fastest = fastest.min(call.elapsed());
+        iterations += 1;
+    }
+    let mean = start.elapsed() / iterations;
+    let real_time = Duration::from_secs_f64(frames as f64 / SAMPLE_RATE as f64);
+    println!(
+        "{name:<36} mean {:>9.1} us/block, fastest {:>9.1} us/block, {:5.1}% of real time",
+        mean.as_secs_f64() * 1e6,
+        fastest.as_secs_f64() * 1e6,
+        100.0 * mean.as_secs_f64() / real_time.as_secs_f64(),
+    );
+}
@@ -0,0 +1,197 @@
+//! Affine projection algorithm (APA) echo canceller.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{DEFAULT_EPSILON, EchoCanceller, check_block_lengths};
+
+/// Echo canceller adapting with an order-`P` affine projection algorithm.
+///
+/// Each update projects onto the last `P` render vectors instead of just the
+/// newest one, which decorrelates colored (speech-like) render signals and
+/// converges much faster than NLMS at the cost of `O(P² · tap_len)` work per
+/// sample. With `P = 1` it performs exactly the NLMS update.
+pub struct ApaCanceller {
+    taps: Vec<f32>,
+    order: usize,
+    /// Render samples newest first, written twice `span` apart so that every
+    /// input vector is a contiguous slice.
+    buffer: Vec<f32>,
+    span: usize,
+    pos: usize,
+    captures: Vec<f32>,
+    errors: Vec<f32>,
+    gram: Vec<f32>,
+    mu: f32,
+    epsilon: f32,
+}
+
+impl ApaCanceller {
+    /// Creates a canceller that tracks `tap_len` samples of the render path and
+    /// projects over the last `order` input vectors.
+    pub fn new(tap_len: usize, order: usize, mu: f32) -> Self {
+        assert!(tap_len > 0, "tap_len must be positive");
+        assert!(order > 0, "projection order must be positive");
+        crate::assert_valid_step_size(mu);
+
+        let span = tap_len + order - 1;
+        Self {
+            taps: vec![0.0; tap_len],
+            order,
+            buffer: vec![0.0; span * 2],
+            span,
+            pos: 0,
+            captures: vec![0.0; order],
+            errors: vec![0.0; order],
+            gram: vec![0.0; order * order],
+            mu,
+            epsilon: DEFAULT_EPSILON,
+        }
+    }
+
+    /// Returns the projection order.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Processes a capture block using the provided render block, writing the
+    /// residual echo-reduced samples into `output`.
+    pub fn process_block(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        adapt: bool,
+    ) {
+        check_block_lengths(render.len(), capture.len(), output.len());
+
+        for idx in 0..render.len() {
+            let error = self.step(render[idx] as f32, capture[idx] as f32, adapt);
+            output[idx] = error.clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+
+    /// Clears the adapted taps and render history.
+    pub fn reset(&mut self) {
+        self.taps.fill(0.0);
+        self.buffer.fill(0.0);
+        self.pos = 0;
+        self.captures.fill(0.0);
+    }
+
+    /// Returns the render vector `lag` samples ago, newest sample first.
+    fn input(&self, lag: usize) -> &[f32] {
+        let start = self.pos + lag;
+        &self.buffer[start..start + self.taps.len()]
+    }
+
+    fn step(&mut self, render: f32, capture: f32, adapt: bool) -> f32 {
+        self.pos = if self.pos == 0 {
+            self.span - 1
+        } else {
+            self.pos - 1
+        };
+        self.buffer[self.pos] = render;
+        self.buffer[self.pos + self.span] = render;
+        self.captures.rotate_right(1);
+        self.captures[0] = capture;
+
+        for lag in 0..self.order {
+            let estimate = dot(&self.taps, self.input(lag));
+            self.errors[lag] = self.captures[lag] - estimate;
+        }
+        let error = self.errors[0];
+
+        if adapt && error.is_finite() {
+            self.update_taps();
+        }
+
+        error
+    }
+
+    fn update_taps(&mut self) {
+        let order = self.order;
+        for i in 0..order {
+            for j in i..order {
+                let mut value = dot(self.input(i), self.input(j));
+                if i == j {
+                    value += self.epsilon;
+                }
+                self.gram[i * order + j] = value;
+                self.gram[j * order + i] = value;
+            }
+        }
+
+        // Solves `gram * a = errors` in place; `errors` then holds `a`.
+        if !solve_in_place(&mut self.gram, &mut self.errors, order) {
+            return;
+        }
+
+        for lag in 0..order {
+            let scale = self.mu * self.errors[lag];
+            if !scale.is_finite() {
+                continue;
+            }
+            let start = self.pos + lag;
+            let input = &self.buffer[start..start + self.taps.len()];
+            for (w, &x) in self.taps.iter_mut().zip(input) {
+                *w += scale * x;
+            }
+        }
+    }
+}
+
+impl EchoCanceller for ApaCanceller {
+    fn process_block(&mut self, render: &[i16], capture: &[i16], output: &mut [i16], adapt: bool) {
+        ApaCanceller::process_block(self, render, capture, output, adapt);
+    }
+
+    fn reset(&mut self) {
+        ApaCanceller::reset(self);
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Gaussian elimination with partial pivoting on a row-major `n`×`n` matrix.
+/// Returns `false` if the system is numerically singular.
+fn solve_in_place(matrix: &mut [f32], rhs: &mut [f32], n: usize) -> bool {
+    for col in 0..n {
+        let pivot_row = (col..n)
+            .max_by(|&a, &b| {
+                matrix[a * n + col]
+                    .abs()
+                    .total_cmp(&matrix[b * n + col].abs())
+            })
+            .unwrap_or(col);
+        let pivot = matrix[pivot_row * n + col];
+        if !pivot.is_normal() {
+            return false;
+        }
+        if pivot_row != col {
+            for k in 0..n {
+                matrix.swap(col * n + k, pivot_row * n + k);
+            }
+            rhs.swap(col, pivot_row);
+        }
+
+        for row in col + 1..n {
+            let factor = matrix[row * n + col] / pivot;
+            for k in col..n {
+                matrix[row * n + k] -= factor * matrix[col * n + k];
+            }
+            rhs[row] -= factor * rhs[col];
+        }
+    }
+
+    for row in (0..n).rev() {
+        let mut value = rhs[row];
+        for k in row + 1..n {
+            value -= matrix[row * n + k] * rhs[k];
+        }
+        rhs[row] = value / matrix[row * n + row];
+    }
+    true
+}
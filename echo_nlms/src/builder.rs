@@ -0,0 +1,280 @@
+//! Validated construction of [`NlmsCanceller`].
+
+use core::fmt;
+
+use crate::{
+    ClipMode, DEFAULT_EPSILON, DEFAULT_ERLE_WINDOW, DivergenceGuard, DoubleTalkScaling,
+    NlmsCanceller, UpdateRule,
+};
+
+/// A configuration value rejected by [`NlmsCancellerBuilder::build`] or by one
+/// of the canceller's setters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigError {
+    /// The tap length was zero.
+    TapLen,
+    /// The step size was not positive and finite.
+    StepSize(f32),
+    /// The regularization term was not positive and finite.
+    Epsilon(f32),
+    /// The leakage factor was outside `[0.0, 1.0)`.
+    Leakage(f32),
+    /// The divergence ratio was not positive and finite.
+    DivergenceRatio(f32),
+    /// The divergence guard spanned zero blocks.
+    DivergenceBlocks,
+    /// The energy refresh interval was zero.
+    EnergyRefreshInterval,
+    /// The ERLE window held zero blocks.
+    ErleWindow,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TapLen => write!(f, "tap_len must be positive"),
+            Self::StepSize(mu) => write!(f, "mu must be positive and finite, got {mu}"),
+            Self::Epsilon(epsilon) => {
+                write!(f, "epsilon must be positive and finite, got {epsilon}")
+            }
+            Self::Leakage(leak) => write!(f, "leak must be within [0.0, 1.0), got {leak}"),
+            Self::DivergenceRatio(ratio) => {
+                write!(
+                    f,
+                    "divergence ratio must be positive and finite, got {ratio}"
+                )
+            }
+            Self::DivergenceBlocks => write!(f, "divergence guard must span at least one block"),
+            Self::EnergyRefreshInterval => write!(f, "energy refresh interval must be positive"),
+            Self::ErleWindow => write!(f, "ERLE window must hold at least one block"),
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+/// Builder collecting the tunables of an [`NlmsCanceller`].
+///
+/// Values are only checked by [`build`](Self::build), so a configuration read
+/// from the command line or a file can be reported as a single error.
+#[derive(Debug, Clone)]
+pub struct NlmsCancellerBuilder {
+    tap_len: usize,
+    mu: f32,
+    epsilon: f32,
+    leak: f32,
+    proportionate: bool,
+    variable_step: bool,
+    trim: bool,
+    update_rule: UpdateRule,
+    bulk_delay: usize,
+    clip_mode: ClipMode,
+    dither: bool,
+    whitening: usize,
+    double_talk_scaling: Option<DoubleTalkScaling>,
+    divergence_guard: Option<(f32, usize)>,
+    energy_refresh_interval: Option<usize>,
+    erle_window: usize,
+}
+
+impl NlmsCancellerBuilder {
+    /// Starts a configuration for a `tap_len`-tap filter with step size `mu`.
+    pub fn new(tap_len: usize, mu: f32) -> Self {
+        Self {
+            tap_len,
+            mu,
+            epsilon: DEFAULT_EPSILON,
+            leak: 0.0,
+            proportionate: false,
+            variable_step: false,
+            trim: false,
+            update_rule: UpdateRule::Nlms,
+            bulk_delay: 0,
+            clip_mode: ClipMode::Hard,
+            dither: false,
+            whitening: 0,
+            double_talk_scaling: None,
+            divergence_guard: None,
+            energy_refresh_interval: None,
+            erle_window: DEFAULT_ERLE_WINDOW,
+        }
+    }
+
+    /// See [`NlmsCanceller::with_epsilon`].
+    pub fn epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_leakage`].
+    pub fn leakage(mut self, leak: f32) -> Self {
+        self.leak = leak;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_proportionate`].
+    pub fn proportionate(mut self, enabled: bool) -> Self {
+        self.proportionate = enabled;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_variable_step`].
+    pub fn variable_step(mut self, enabled: bool) -> Self {
+        self.variable_step = enabled;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_trim`].
+    pub fn trim(mut self, enabled: bool) -> Self {
+        self.trim = enabled;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_update_rule`].
+    pub fn update_rule(mut self, rule: UpdateRule) -> Self {
+        self.update_rule = rule;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_bulk_delay`].
+    pub fn bulk_delay(mut self, samples: usize) -> Self {
+        self.bulk_delay = samples;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_output_clip`].
+    pub fn output_clip(mut self, mode: ClipMode) -> Self {
+        self.clip_mode = mode;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_dither`].
+    pub fn dither(mut self, enabled: bool) -> Self {
+        self.dither = enabled;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_whitening`].
+    pub fn whitening(mut self, order: usize) -> Self {
+        self.whitening = order;
+        self
+    }
+
+    /// See [`NlmsCanceller::set_double_talk_scaling`].
+    pub fn double_talk_scaling(mut self, scaling: DoubleTalkScaling) -> Self {
+        self.double_talk_scaling = Some(scaling);
+        self
+    }
+
+    /// See [`NlmsCanceller::set_divergence_guard`].
+    pub fn divergence_guard(mut self, ratio: f32, blocks: usize) -> Self {
+        self.divergence_guard = Some((ratio, blocks));
+        self
+    }
+
+    /// See [`NlmsCanceller::set_energy_refresh_interval`].
+    pub fn energy_refresh_interval(mut self, samples: usize) -> Self {
+        self.energy_refresh_interval = Some(samples);
+        self
+    }
+
+    /// See [`NlmsCanceller::set_erle_window`].
+    pub fn erle_window(mut self, blocks: usize) -> Self {
+        self.erle_window = blocks;
+        self
+    }
+
+    /// Validates the configuration and creates the canceller.
+    pub fn build(self) -> Result<NlmsCanceller, ConfigError> {
+        check_tap_len(self.tap_len)?;
+        check_step_size(self.mu)?;
+        check_epsilon(self.epsilon)?;
+        check_leakage(self.leak)?;
+        check_erle_window(self.erle_window)?;
+        let divergence_guard = match self.divergence_guard {
+            Some((ratio, blocks)) => {
+                check_divergence_guard(ratio, blocks)?;
+                Some(DivergenceGuard { ratio, blocks })
+            }
+            None => None,
+        };
+        let energy_refresh_interval = self.energy_refresh_interval.unwrap_or(self.tap_len);
+        check_energy_refresh_interval(energy_refresh_interval)?;
+
+        let mut canceller = NlmsCanceller::unchecked(self.tap_len, self.mu, self.epsilon);
+        canceller.leak = self.leak;
+        canceller.proportionate = self.proportionate;
+        canceller.variable_step = self.variable_step;
+        canceller.trim = self.trim;
+        canceller.update_rule = self.update_rule;
+        canceller.clip_mode = self.clip_mode;
+        canceller.set_dither(self.dither);
+        canceller.set_whitening(self.whitening);
+        canceller.set_double_talk_scaling(self.double_talk_scaling);
+        if self.bulk_delay > 0 {
+            canceller.set_bulk_delay(self.bulk_delay);
+        }
+        canceller.divergence_guard = divergence_guard;
+        canceller.energy_refresh_interval = energy_refresh_interval;
+        canceller.set_erle_window(self.erle_window);
+        Ok(canceller)
+    }
+}
+
+pub(crate) fn check_tap_len(tap_len: usize) -> Result<(), ConfigError> {
+    if tap_len > 0 {
+        Ok(())
+    } else {
+        Err(ConfigError::TapLen)
+    }
+}
+
+pub(crate) fn check_step_size(mu: f32) -> Result<(), ConfigError> {
+    if mu.is_finite() && mu > 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigError::StepSize(mu))
+    }
+}
+
+pub(crate) fn check_epsilon(epsilon: f32) -> Result<(), ConfigError> {
+    if epsilon.is_finite() && epsilon > 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigError::Epsilon(epsilon))
+    }
+}
+
+pub(crate) fn check_leakage(leak: f32) -> Result<(), ConfigError> {
+    if (0.0..1.0).contains(&leak) {
+        Ok(())
+    } else {
+        Err(ConfigError::Leakage(leak))
+    }
+}
+
+pub(crate) fn check_divergence_guard(ratio: f32, blocks: usize) -> Result<(), ConfigError> {
+    if !(ratio.is_finite() && ratio > 0.0) {
+        return Err(ConfigError::DivergenceRatio(ratio));
+    }
+    if blocks == 0 {
+        return Err(ConfigError::DivergenceBlocks);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_energy_refresh_interval(samples: usize) -> Result<(), ConfigError> {
+    if samples > 0 {
+        Ok(())
+    } else {
+        Err(ConfigError::EnergyRefreshInterval)
+    }
+}
+
+pub(crate) fn check_erle_window(blocks: usize) -> Result<(), ConfigError> {
+    if blocks > 0 {
+        Ok(())
+    } else {
+        Err(ConfigError::ErleWindow)
+    }
+}
@@ -0,0 +1,48 @@
+//! Bulk delay estimation between render and capture signals.
+
+#[cfg(all(feature = "no_std", not(test)))]
+use crate::math::F64Ext as _;
+
+/// Normalized correlation below which no delay is reported.
+const MIN_CORRELATION: f32 = 0.1;
+
+/// Estimates the fixed delay, in samples, from `render` to its echo in
+/// `capture` by normalized cross-correlation over lags `0..=max_lag`.
+///
+/// Both slices should cover the same time span and be considerably longer
+/// than `max_lag`. Returns `None` if either signal is silent or no lag
+/// correlates clearly. The result can be passed to
+/// [`NlmsCanceller::set_bulk_delay`](crate::NlmsCanceller::set_bulk_delay).
+pub fn estimate_bulk_delay(render: &[i16], capture: &[i16], max_lag: usize) -> Option<usize> {
+    let len = render.len().min(capture.len());
+    if max_lag >= len {
+        return None;
+    }
+
+    let mut best: Option<(usize, f32)> = None;
+    for lag in 0..=max_lag {
+        let overlap = len - lag;
+        let mut cross = 0.0f64;
+        let mut render_energy = 0.0f64;
+        let mut capture_energy = 0.0f64;
+        for (&r, &c) in render[..overlap].iter().zip(&capture[lag..len]) {
+            let (r, c) = (f64::from(r), f64::from(c));
+            cross += r * c;
+            render_energy += r * r;
+            capture_energy += c * c;
+        }
+
+        let denom = (render_energy * capture_energy).sqrt();
+        if denom <= 1e-9 {
+            continue;
+        }
+
+        let correlation = (cross / denom).abs() as f32;
+        if best.is_none_or(|(_, value)| correlation > value) {
+            best = Some((lag, correlation));
+        }
+    }
+
+    best.filter(|&(_, correlation)| correlation >= MIN_CORRELATION)
+        .map(|(lag, _)| lag)
+}
@@ -0,0 +1,105 @@
+//! Comfort noise generation for suppressed output.
+
+#[cfg(all(feature = "no_std", not(test)))]
+use crate::math::F32Ext as _;
+
+/// Per-block growth factor of the noise floor estimate while the input stays
+/// above it, roughly 0.1 dB per block.
+const FLOOR_RISE: f32 = 1.025;
+/// Blocks at most this many times above the floor count as background noise
+/// for spectral-shape estimation.
+const QUIET_MARGIN: f32 = 2.0;
+/// Smoothing applied to the spectral-shape estimate on quiet blocks.
+const SHAPE_SMOOTHING: f32 = 0.1;
+
+/// Generates low-level noise matched to the background of a signal.
+///
+/// The background is modeled as first-order autoregressive noise: its power
+/// is tracked with minimum statistics and its spectral tilt with the lag-one
+/// autocorrelation of quiet blocks. Filling gated gaps with such noise keeps
+/// suppressed output from sounding unnaturally dead.
+pub struct ComfortNoise {
+    floor_power: Option<f32>,
+    pole: f32,
+    state: f32,
+    rng: u32,
+}
+
+impl ComfortNoise {
+    /// Creates a generator with no background estimate yet.
+    pub fn new() -> Self {
+        Self {
+            floor_power: None,
+            pole: 0.0,
+            state: 0.0,
+            rng: 0x9E37_79B9,
+        }
+    }
+
+    /// Returns the estimated background noise RMS in `i16` units.
+    pub fn noise_level(&self) -> f32 {
+        self.floor_power.unwrap_or(0.0).sqrt()
+    }
+
+    /// Updates the background estimate from a block of (unsuppressed) signal.
+    pub fn update(&mut self, samples: &[i16]) {
+        if samples.len() < 2 {
+            return;
+        }
+
+        let mut power = 0.0;
+        let mut lag_one = 0.0;
+        let mut prev = samples[0] as f32;
+        for &sample in samples {
+            let v = sample as f32;
+            power += v * v;
+            lag_one += v * prev;
+            prev = v;
+        }
+        power /= samples.len() as f32;
+        lag_one /= samples.len() as f32;
+
+        let floor = match self.floor_power {
+            Some(floor) if power >= floor => floor * FLOOR_RISE,
+            _ => power,
+        };
+        self.floor_power = Some(floor);
+
+        if power <= floor * QUIET_MARGIN && power > 0.0 {
+            let pole = (lag_one / power).clamp(0.0, 0.95);
+            self.pole += (pole - self.pole) * SHAPE_SMOOTHING;
+        }
+    }
+
+    /// Adds matched noise to `out`. `level` in `[0.0, 1.0]` selects how much of
+    /// the background to restore, typically one minus the suppression gain.
+    pub fn fill(&mut self, out: &mut [i16], level: f32) {
+        let level = level.clamp(0.0, 1.0);
+        if level == 0.0 {
+            return;
+        }
+
+        // Scale the excitation so the AR(1) output has the tracked power.
+        let excitation = self.noise_level() * level * (1.0 - self.pole * self.pole).sqrt();
+        for sample in out.iter_mut() {
+            self.state = self.pole * self.state + excitation * self.white();
+            let mixed = *sample as f32 + self.state;
+            *sample = mixed.clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+
+    /// Unit-variance uniform white noise from a xorshift generator.
+    fn white(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let uniform = self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        uniform * 3.0f32.sqrt()
+    }
+}
+
+impl Default for ComfortNoise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
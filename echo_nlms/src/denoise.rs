@@ -0,0 +1,181 @@
+//! Spectral-subtraction noise reduction.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+
+use crate::fft::{Complex, Fft};
+#[cfg(all(feature = "no_std", not(test)))]
+use crate::math::F32Ext as _;
+
+/// Frames whose energy stays within this factor of the noise estimate count
+/// as background and update it (about 6 dB).
+const NOISE_MARGIN: f32 = 4.0;
+/// Smoothing of the per-bin noise estimate on background frames.
+const NOISE_SMOOTHING: f32 = 0.1;
+/// Per-frame growth of the noise estimate while frames stay above it, so a
+/// louder background is eventually picked up.
+const NOISE_RISE: f32 = 1.001;
+
+/// Removes stationary background noise by spectral subtraction.
+///
+/// The signal is cut into half-overlapping frames under a square-root Hann
+/// window. Frames whose energy stays close to the current noise estimate are
+/// treated as background and refine the per-bin noise spectrum; the first
+/// frame seeds it. Every frame then has `over_subtraction` times the noise
+/// magnitude removed from each bin, keeping at least `floor` of the original
+/// magnitude, and is resynthesized with its original phase by overlap-add.
+///
+/// Output lags the input by [`latency`](Self::latency) samples.
+pub struct SpectralDenoiser {
+    frame_len: usize,
+    over_subtraction: f32,
+    floor: f32,
+    fft: Fft,
+    window: Vec<f32>,
+    input: Vec<f32>,
+    overlap: Vec<f32>,
+    pending: usize,
+    spectrum: Vec<Complex>,
+    noise: Option<Vec<f32>>,
+}
+
+impl SpectralDenoiser {
+    /// Creates a denoiser working on frames of `frame_len` samples.
+    ///
+    /// `over_subtraction` scales the noise estimate before it is subtracted
+    /// (values above `1.0` remove more noise but also more signal) and `floor`
+    /// bounds the per-bin attenuation to limit musical noise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame_len` is not a power of two of at least 4, if
+    /// `over_subtraction` is negative or not finite, or if `floor` is outside
+    /// `[0.0, 1.0]`.
+    pub fn new(frame_len: usize, over_subtraction: f32, floor: f32) -> Self {
+        assert!(
+            frame_len >= 4 && frame_len.is_power_of_two(),
+            "frame_len must be a power of two of at least 4"
+        );
+        let mut denoiser = Self {
+            frame_len,
+            over_subtraction: 0.0,
+            floor: 0.0,
+            fft: Fft::new(frame_len),
+            window: (0..frame_len)
+                .map(|n| (PI * n as f32 / frame_len as f32).sin())
+                .collect(),
+            input: vec![0.0; frame_len],
+            overlap: vec![0.0; frame_len],
+            pending: 0,
+            spectrum: vec![Complex::ZERO; frame_len],
+            noise: None,
+        };
+        denoiser.set_over_subtraction(over_subtraction);
+        denoiser.set_floor(floor);
+        denoiser
+    }
+
+    /// Changes how many times the noise estimate is subtracted.
+    pub fn set_over_subtraction(&mut self, over_subtraction: f32) {
+        assert!(
+            over_subtraction.is_finite() && over_subtraction >= 0.0,
+            "over_subtraction must be non-negative and finite"
+        );
+        self.over_subtraction = over_subtraction;
+    }
+
+    /// Changes the smallest fraction of each bin's magnitude that is kept.
+    pub fn set_floor(&mut self, floor: f32) {
+        assert!(
+            (0.0..=1.0).contains(&floor),
+            "floor must be within [0.0, 1.0]"
+        );
+        self.floor = floor;
+    }
+
+    /// Returns the delay between input and output in samples.
+    pub fn latency(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Returns the estimated background noise RMS in `i16` units, or zero
+    /// before the first frame.
+    pub fn noise_level(&self) -> f32 {
+        let Some(noise) = self.noise.as_ref() else {
+            return 0.0;
+        };
+        // Parseval, undoing the window's mean power of one half.
+        let energy: f32 = noise.iter().sum();
+        (2.0 * energy / (self.frame_len * self.frame_len) as f32).sqrt()
+    }
+
+    /// Denoises `samples` in place. Blocks may have any length.
+    pub fn process(&mut self, samples: &mut [i16]) {
+        let hop = self.frame_len / 2;
+        for sample in samples.iter_mut() {
+            self.input[hop + self.pending] = *sample as f32;
+            *sample = self.overlap[self.pending].clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            self.pending += 1;
+            if self.pending == hop {
+                self.process_frame();
+                self.pending = 0;
+            }
+        }
+    }
+
+    /// Forgets the noise estimate and any buffered audio.
+    pub fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.overlap.fill(0.0);
+        self.pending = 0;
+        self.noise = None;
+    }
+
+    fn process_frame(&mut self) {
+        let hop = self.frame_len / 2;
+        for ((bin, &sample), &weight) in self.spectrum.iter_mut().zip(&self.input).zip(&self.window)
+        {
+            *bin = Complex::new(sample * weight, 0.0);
+        }
+        self.fft.forward(&mut self.spectrum);
+
+        let noise = self
+            .noise
+            .get_or_insert_with(|| self.spectrum.iter().map(|bin| bin.norm_sqr()).collect());
+        let frame_energy: f32 = self.spectrum.iter().map(|bin| bin.norm_sqr()).sum();
+        let noise_energy: f32 = noise.iter().sum();
+        if frame_energy <= noise_energy * NOISE_MARGIN {
+            for (estimate, bin) in noise.iter_mut().zip(&self.spectrum) {
+                *estimate += (bin.norm_sqr() - *estimate) * NOISE_SMOOTHING;
+            }
+        } else {
+            for estimate in noise.iter_mut() {
+                *estimate *= NOISE_RISE;
+            }
+        }
+
+        for (bin, &noise_power) in self.spectrum.iter_mut().zip(noise.iter()) {
+            let power = bin.norm_sqr();
+            let gain = if power > 0.0 {
+                (1.0 - self.over_subtraction * (noise_power / power).sqrt()).max(self.floor)
+            } else {
+                self.floor
+            };
+            *bin = bin.scale(gain);
+        }
+        self.fft.inverse(&mut self.spectrum);
+
+        self.overlap.copy_within(hop.., 0);
+        self.overlap[hop..].fill(0.0);
+        for ((out, bin), &weight) in self
+            .overlap
+            .iter_mut()
+            .zip(&self.spectrum)
+            .zip(&self.window)
+        {
+            *out += bin.re * weight;
+        }
+        self.input.copy_within(hop.., 0);
+    }
+}
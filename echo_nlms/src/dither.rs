@@ -0,0 +1,31 @@
+//! Triangular dither for the final `i16` conversion.
+
+/// Generator of triangular-PDF (TPDF) dither spanning ±1 LSB.
+///
+/// Adding it before rounding decorrelates the quantization error from the
+/// signal, turning the distortion of quiet passages into a constant, benign
+/// noise floor.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct TpdfDither {
+    state: u32,
+}
+
+impl TpdfDither {
+    pub fn new() -> Self {
+        Self { state: 0x2545_F491 }
+    }
+
+    /// Returns the next dither value in `(-1.0, 1.0)`, in LSBs.
+    pub fn next(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+
+    /// Uniform value in `[0.0, 1.0)` from a xorshift generator.
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
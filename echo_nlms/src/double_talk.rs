@@ -0,0 +1,147 @@
+//! Geigel double-talk detection.
+
+use alloc::collections::VecDeque;
+
+/// Geigel double-talk detector.
+///
+/// Declares double-talk when the magnitude of a capture sample exceeds
+/// `threshold` times the peak render magnitude seen over the last `window`
+/// samples, i.e. when the capture is louder than the echo path could explain.
+/// Once triggered, the detector keeps reporting double-talk for `hold_blocks`
+/// further blocks so adaptation does not resume on a speech tail.
+pub struct GeigelDetector {
+    window: usize,
+    threshold: f32,
+    hold_blocks: usize,
+    hold_remaining: usize,
+    peaks: VecDeque<(u64, f32)>,
+    sample_idx: u64,
+}
+
+impl GeigelDetector {
+    /// Creates a detector looking back over `window` render samples.
+    pub fn new(window: usize, threshold: f32, hold_blocks: usize) -> Self {
+        assert!(window > 0, "window must be positive");
+        assert!(
+            threshold.is_finite() && threshold > 0.0,
+            "threshold must be positive and finite"
+        );
+        Self {
+            window,
+            threshold,
+            hold_blocks,
+            hold_remaining: 0,
+            peaks: VecDeque::new(),
+            sample_idx: 0,
+        }
+    }
+
+    /// Consumes a render/capture block pair and reports whether adaptation
+    /// should be frozen for it.
+    pub fn is_double_talk(&mut self, render: &[i16], capture: &[i16]) -> bool {
+        assert_eq!(
+            render.len(),
+            capture.len(),
+            "render and capture chunks must match"
+        );
+
+        let mut detected = false;
+        for (&r, &c) in render.iter().zip(capture) {
+            let render_peak = self.push_render(r.unsigned_abs() as f32);
+            if c.unsigned_abs() as f32 > self.threshold * render_peak {
+                detected = true;
+            }
+        }
+
+        if detected {
+            self.hold_remaining = self.hold_blocks;
+            true
+        } else if self.hold_remaining > 0 {
+            self.hold_remaining -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Clears the render window and any pending hangover.
+    pub fn reset(&mut self) {
+        self.peaks.clear();
+        self.hold_remaining = 0;
+    }
+
+    /// Adds a render magnitude to the sliding window and returns the window
+    /// maximum.
+    fn push_render(&mut self, magnitude: f32) -> f32 {
+        while self.peaks.back().is_some_and(|&(_, v)| v <= magnitude) {
+            self.peaks.pop_back();
+        }
+        self.peaks.push_back((self.sample_idx, magnitude));
+
+        let oldest = self.sample_idx.saturating_sub(self.window as u64 - 1);
+        while self.peaks.front().is_some_and(|&(idx, _)| idx < oldest) {
+            self.peaks.pop_front();
+        }
+
+        self.sample_idx += 1;
+        self.peaks.front().map_or(0.0, |&(_, v)| v)
+    }
+}
+
+/// Mapping from the capture-to-render level ratio to a step size scale, for
+/// slowing adaptation down gradually during double-talk instead of freezing
+/// it.
+///
+/// Used by
+/// [`NlmsCanceller::set_double_talk_scaling`](crate::NlmsCanceller::set_double_talk_scaling).
+///
+/// Up to a ratio of `onset` the full step size is used. Between `onset` and
+/// `cutoff` the scale falls along a smoothstep curve, reaching zero at
+/// `cutoff`, so the step size has no corner at either end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DoubleTalkScaling {
+    onset: f32,
+    cutoff: f32,
+}
+
+impl DoubleTalkScaling {
+    /// Creates a mapping that starts reducing the step size once the RMS
+    /// capture level exceeds `onset` times the RMS render level and stops
+    /// adaptation at `cutoff` times.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 < onset < cutoff` and both are finite.
+    pub fn new(onset: f32, cutoff: f32) -> Self {
+        assert!(
+            onset.is_finite() && onset > 0.0,
+            "onset must be positive and finite"
+        );
+        assert!(
+            cutoff.is_finite() && cutoff > onset,
+            "cutoff must be finite and greater than onset"
+        );
+        Self { onset, cutoff }
+    }
+
+    /// Returns the level ratio above which the step size is reduced.
+    pub fn onset(&self) -> f32 {
+        self.onset
+    }
+
+    /// Returns the level ratio at which adaptation stops.
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    /// Returns the step size scale, from 0.0 to 1.0, for a capture-to-render
+    /// level ratio.
+    pub fn scale(&self, ratio: f32) -> f32 {
+        if ratio.is_nan() || ratio >= self.cutoff {
+            return 0.0;
+        }
+        let t = ((ratio - self.onset) / (self.cutoff - self.onset)).max(0.0);
+        1.0 - t * t * (3.0 - 2.0 * t)
+    }
+}
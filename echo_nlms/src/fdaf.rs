@@ -0,0 +1,193 @@
+//! Partitioned-block frequency-domain adaptive filter.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::fft::{Complex, Fft};
+use crate::{EchoCanceller, check_block_lengths};
+
+/// Echo canceller built on a partitioned-block frequency-domain adaptive
+/// filter (PBFDAF) using overlap-save convolution.
+///
+/// The filter is split into partitions of `partition_len` taps, each adapted
+/// in the frequency domain once per partition-sized sub-block. For long echo
+/// paths this is far cheaper than the sample-by-sample [`NlmsCanceller`],
+/// at the cost of only updating the taps once per sub-block.
+///
+/// [`NlmsCanceller`]: crate::NlmsCanceller
+pub struct FdafCanceller {
+    partition_len: usize,
+    fft: Fft,
+    weights: Vec<Vec<Complex>>,
+    spectra: Vec<Vec<Complex>>,
+    spectra_pos: usize,
+    render_frame: Vec<f32>,
+    power: Vec<f32>,
+    scratch: Vec<Complex>,
+    error_spectrum: Vec<Complex>,
+    mu: f32,
+}
+
+impl FdafCanceller {
+    /// Creates a canceller covering at least `tap_len` samples of the render
+    /// path, processed in partitions of `partition_len` samples.
+    ///
+    /// `partition_len` must be a power of two. Blocks handed to
+    /// [`process_block`](Self::process_block) must be a multiple of it.
+    pub fn new(tap_len: usize, partition_len: usize, mu: f32) -> Self {
+        assert!(tap_len > 0, "tap_len must be positive");
+        assert!(
+            partition_len.is_power_of_two(),
+            "partition_len must be a power of two"
+        );
+        crate::assert_valid_step_size(mu);
+
+        let partitions = tap_len.div_ceil(partition_len);
+        let fft_len = partition_len * 2;
+        Self {
+            partition_len,
+            fft: Fft::new(fft_len),
+            weights: vec![vec![Complex::ZERO; fft_len]; partitions],
+            spectra: vec![vec![Complex::ZERO; fft_len]; partitions],
+            spectra_pos: 0,
+            render_frame: vec![0.0; fft_len],
+            power: vec![0.0; fft_len],
+            scratch: vec![Complex::ZERO; fft_len],
+            error_spectrum: vec![Complex::ZERO; fft_len],
+            mu,
+        }
+    }
+
+    /// Returns the number of taps covered by all partitions together.
+    pub fn tap_len(&self) -> usize {
+        self.weights.len() * self.partition_len
+    }
+
+    /// Processes a capture block using the provided render block, writing the
+    /// residual echo-reduced samples into `output`.
+    ///
+    /// Each slice must share the same length, which must be a multiple of the
+    /// partition length.
+    pub fn process_block(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        adapt: bool,
+    ) {
+        check_block_lengths(render.len(), capture.len(), output.len());
+        assert!(
+            render.len().is_multiple_of(self.partition_len),
+            "block length must be a multiple of the partition length"
+        );
+
+        let len = self.partition_len;
+        for start in (0..render.len()).step_by(len) {
+            let end = start + len;
+            self.process_partition(
+                &render[start..end],
+                &capture[start..end],
+                &mut output[start..end],
+                adapt,
+            );
+        }
+    }
+
+    /// Clears the adapted weights and render history.
+    pub fn reset(&mut self) {
+        for partition in self.weights.iter_mut().chain(self.spectra.iter_mut()) {
+            partition.fill(Complex::ZERO);
+        }
+        self.spectra_pos = 0;
+        self.render_frame.fill(0.0);
+    }
+
+    fn process_partition(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        adapt: bool,
+    ) {
+        let len = self.partition_len;
+        let partitions = self.weights.len();
+
+        // Overlap-save: transform the previous and current render blocks.
+        self.render_frame.copy_within(len.., 0);
+        for (dst, &src) in self.render_frame[len..].iter_mut().zip(render) {
+            *dst = src as f32;
+        }
+        self.spectra_pos = (self.spectra_pos + partitions - 1) % partitions;
+        let newest = &mut self.spectra[self.spectra_pos];
+        for (dst, &src) in newest.iter_mut().zip(&self.render_frame) {
+            *dst = Complex::new(src, 0.0);
+        }
+        self.fft.forward(newest);
+
+        self.scratch.fill(Complex::ZERO);
+        for (p, weights) in self.weights.iter().enumerate() {
+            let spectrum = &self.spectra[(self.spectra_pos + p) % partitions];
+            for ((acc, &w), &x) in self.scratch.iter_mut().zip(weights).zip(spectrum) {
+                *acc += w * x;
+            }
+        }
+        self.fft.inverse(&mut self.scratch);
+
+        let limit_min = i16::MIN as f32;
+        let limit_max = i16::MAX as f32;
+        self.error_spectrum[..len].fill(Complex::ZERO);
+        for idx in 0..len {
+            let error = capture[idx] as f32 - self.scratch[len + idx].re;
+            output[idx] = error.clamp(limit_min, limit_max) as i16;
+            self.error_spectrum[len + idx] = Complex::new(error, 0.0);
+        }
+
+        if adapt {
+            self.update_weights();
+        }
+    }
+
+    fn update_weights(&mut self) {
+        let len = self.partition_len;
+        let partitions = self.weights.len();
+        self.fft.forward(&mut self.error_spectrum);
+
+        // Normalize each bin by the render power across all partitions, the
+        // frequency-domain analogue of the NLMS energy term.
+        let floor = self.fft.len() as f32;
+        self.power.fill(floor);
+        for spectrum in &self.spectra {
+            for (power, x) in self.power.iter_mut().zip(spectrum) {
+                *power += x.norm_sqr();
+            }
+        }
+
+        for (p, weights) in self.weights.iter_mut().enumerate() {
+            let spectrum = &self.spectra[(self.spectra_pos + p) % partitions];
+            for (k, gradient) in self.scratch.iter_mut().enumerate() {
+                *gradient =
+                    (spectrum[k].conj() * self.error_spectrum[k]).scale(1.0 / self.power[k]);
+            }
+
+            // Gradient constraint: keep only the causal half so each partition
+            // stays a linear (not circular) convolution.
+            self.fft.inverse(&mut self.scratch);
+            self.scratch[len..].fill(Complex::ZERO);
+            self.fft.forward(&mut self.scratch);
+
+            for (w, &g) in weights.iter_mut().zip(&self.scratch) {
+                *w += g.scale(self.mu);
+            }
+        }
+    }
+}
+
+impl EchoCanceller for FdafCanceller {
+    fn process_block(&mut self, render: &[i16], capture: &[i16], output: &mut [i16], adapt: bool) {
+        FdafCanceller::process_block(self, render, capture, output, adapt);
+    }
+
+    fn reset(&mut self) {
+        FdafCanceller::reset(self);
+    }
+}
@@ -0,0 +1,163 @@
+//! Minimal in-place radix-2 FFT used by the frequency-domain filters, and
+//! shared with the spectral pitch detectors of `pitch_detect`.
+
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use core::ops::{Add, AddAssign, Mul, Sub};
+
+#[cfg(all(feature = "no_std", not(test)))]
+use crate::math::F32Ext as _;
+
+/// A complex number with `f32` parts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    /// Real part.
+    pub re: f32,
+    /// Imaginary part.
+    pub im: f32,
+}
+
+impl Complex {
+    pub const ZERO: Self = Self { re: 0.0, im: 0.0 };
+
+    /// Creates `re + im·i`.
+    pub fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    /// Returns the complex conjugate.
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    /// Returns the squared magnitude.
+    pub fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Returns the number scaled by a real `factor`.
+    pub fn scale(self, factor: f32) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, rhs: Self) {
+        self.re += rhs.re;
+        self.im += rhs.im;
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+/// Precomputed twiddles and bit-reversal table for one transform size.
+pub struct Fft {
+    twiddles: Vec<Complex>,
+    bitrev: Vec<usize>,
+}
+
+impl Fft {
+    /// Prepares transforms of `len` points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is not a power of two.
+    pub fn new(len: usize) -> Self {
+        assert!(len.is_power_of_two(), "FFT length must be a power of two");
+
+        let bits = len.trailing_zeros();
+        let bitrev = (0..len)
+            .map(|i| {
+                if bits == 0 {
+                    0
+                } else {
+                    i.reverse_bits() >> (usize::BITS - bits)
+                }
+            })
+            .collect();
+        let twiddles = (0..len / 2)
+            .map(|k| {
+                let angle = -2.0 * PI * k as f32 / len as f32;
+                Complex::new(angle.cos(), angle.sin())
+            })
+            .collect();
+
+        Self { twiddles, bitrev }
+    }
+
+    /// Returns the number of points per transform.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.bitrev.len()
+    }
+
+    /// Unnormalized forward transform.
+    pub fn forward(&self, data: &mut [Complex]) {
+        self.transform(data, false);
+    }
+
+    /// Inverse transform, scaled by `1 / len` so it undoes [`forward`](Self::forward).
+    pub fn inverse(&self, data: &mut [Complex]) {
+        self.transform(data, true);
+        let scale = 1.0 / self.len() as f32;
+        for value in data.iter_mut() {
+            *value = value.scale(scale);
+        }
+    }
+
+    fn transform(&self, data: &mut [Complex], inverse: bool) {
+        let len = self.len();
+        assert_eq!(data.len(), len, "FFT buffer length mismatch");
+
+        for i in 0..len {
+            let j = self.bitrev[i];
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+
+        let mut size = 2;
+        while size <= len {
+            let half = size / 2;
+            let stride = len / size;
+            for start in (0..len).step_by(size) {
+                for k in 0..half {
+                    let mut twiddle = self.twiddles[k * stride];
+                    if inverse {
+                        twiddle = twiddle.conj();
+                    }
+                    let odd = data[start + k + half] * twiddle;
+                    let even = data[start + k];
+                    data[start + k] = even + odd;
+                    data[start + k + half] = even - odd;
+                }
+            }
+            size *= 2;
+        }
+    }
+}
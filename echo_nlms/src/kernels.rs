@@ -0,0 +1,147 @@
+//! Inner loops of the time-domain adaptive filters.
+//!
+//! Taps are ordered newest render sample first while the history ring stores
+//! samples oldest first, so every kernel pairs a forward run of taps with a
+//! backward run of history. Callers store the ring twice back to back, so the
+//! window of history under the taps is always one contiguous run.
+//!
+//! The vectorized paths index `history` by the length of `taps`, so every
+//! entry point checks that the two match.
+
+/// Returns `sum(taps[k] * history[len - 1 - k])`.
+pub fn dot_rev(taps: &[f32], history: &[f32]) -> f32 {
+    assert_eq!(
+        taps.len(),
+        history.len(),
+        "taps and history must have the same length"
+    );
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        sse::dot_rev(taps, history)
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        scalar::dot_rev(taps, history)
+    }
+}
+
+/// Performs `taps[k] = retain * taps[k] + scale * history[len - 1 - k]`.
+pub fn leaky_axpy_rev(taps: &mut [f32], history: &[f32], retain: f32, scale: f32) {
+    assert_eq!(
+        taps.len(),
+        history.len(),
+        "taps and history must have the same length"
+    );
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        sse::leaky_axpy_rev(taps, history, retain, scale);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        scalar::leaky_axpy_rev(taps, history, retain, scale);
+    }
+}
+
+/// Performs `taps[k] = retain * taps[k] + scale * sign(history[len - 1 - k])`,
+/// where the sign of zero is zero.
+pub fn leaky_sign_axpy_rev(taps: &mut [f32], history: &[f32], retain: f32, scale: f32) {
+    assert_eq!(
+        taps.len(),
+        history.len(),
+        "taps and history must have the same length"
+    );
+
+    for (w, &h) in taps.iter_mut().zip(history.iter().rev()) {
+        *w *= retain;
+        if h > 0.0 {
+            *w += scale;
+        } else if h < 0.0 {
+            *w -= scale;
+        }
+    }
+}
+
+/// Portable fallbacks, also the reference the vectorized paths are tested
+/// against.
+#[cfg_attr(
+    all(target_arch = "x86_64", not(feature = "test-util")),
+    allow(dead_code)
+)]
+pub mod scalar {
+    pub fn dot_rev(taps: &[f32], history: &[f32]) -> f32 {
+        taps.iter()
+            .zip(history.iter().rev())
+            .map(|(w, h)| w * h)
+            .sum()
+    }
+
+    pub fn leaky_axpy_rev(taps: &mut [f32], history: &[f32], retain: f32, scale: f32) {
+        for (w, h) in taps.iter_mut().zip(history.iter().rev()) {
+            *w = retain * *w + scale * h;
+        }
+    }
+}
+
+/// SSE is part of the x86_64 baseline, so no runtime detection is needed.
+#[cfg(target_arch = "x86_64")]
+mod sse {
+    use core::arch::x86_64::*;
+
+    const LANES: usize = 4;
+    /// `_MM_SHUFFLE(0, 1, 2, 3)`: reverses the four lanes.
+    const REVERSE: i32 = 0b00_01_10_11;
+
+    pub fn dot_rev(taps: &[f32], history: &[f32]) -> f32 {
+        let len = taps.len();
+        let vector_len = len - len % LANES;
+
+        let mut lanes = [0.0f32; LANES];
+        // SAFETY: SSE is always available on x86_64. Every load reads four
+        // floats starting at most `LANES` before the end of a `len`-long
+        // slice, both slices being `len` long as the entry point asserted,
+        // and the store writes exactly the four floats of `lanes`.
+        unsafe {
+            let mut acc = _mm_setzero_ps();
+            for k in (0..vector_len).step_by(LANES) {
+                let w = _mm_loadu_ps(taps.as_ptr().add(k));
+                let h = _mm_loadu_ps(history.as_ptr().add(len - k - LANES));
+                let h = _mm_shuffle_ps::<REVERSE>(h, h);
+                acc = _mm_add_ps(acc, _mm_mul_ps(w, h));
+            }
+            _mm_storeu_ps(lanes.as_mut_ptr(), acc);
+        }
+
+        let tail = super::scalar::dot_rev(&taps[vector_len..], &history[..len - vector_len]);
+        lanes.iter().sum::<f32>() + tail
+    }
+
+    pub fn leaky_axpy_rev(taps: &mut [f32], history: &[f32], retain: f32, scale: f32) {
+        let len = taps.len();
+        let vector_len = len - len % LANES;
+
+        // SAFETY: SSE is always available on x86_64. Every load and store
+        // touches four floats starting at most `LANES` before the end of a
+        // `len`-long slice, both slices being `len` long as the entry point
+        // asserted.
+        unsafe {
+            let retain_v = _mm_set1_ps(retain);
+            let scale_v = _mm_set1_ps(scale);
+            for k in (0..vector_len).step_by(LANES) {
+                let w = _mm_loadu_ps(taps.as_ptr().add(k));
+                let h = _mm_loadu_ps(history.as_ptr().add(len - k - LANES));
+                let h = _mm_shuffle_ps::<REVERSE>(h, h);
+                let updated = _mm_add_ps(_mm_mul_ps(retain_v, w), _mm_mul_ps(scale_v, h));
+                _mm_storeu_ps(taps.as_mut_ptr().add(k), updated);
+            }
+        }
+
+        super::scalar::leaky_axpy_rev(
+            &mut taps[vector_len..],
+            &history[..len - vector_len],
+            retain,
+            scale,
+        );
+    }
+}
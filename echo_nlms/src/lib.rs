@@ -1,28 +1,626 @@
 //! Simple NLMS-based acoustic echo canceller.
+//!
+//! With the `no_std` feature the crate only depends on `core` and `alloc`,
+//! for use in firmware.
+
+#![cfg_attr(feature = "no_std", no_std)]
+
+extern crate alloc;
+
+mod apa;
+mod builder;
+mod bulk_delay;
+mod comfort_noise;
+mod denoise;
+mod dither;
+mod double_talk;
+mod fdaf;
+pub mod fft;
+mod kernels;
+#[cfg(all(feature = "no_std", not(test)))]
+mod math;
+mod multichannel;
+mod nonlinear;
+mod suppressor;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod whitening;
+
+pub use apa::ApaCanceller;
+pub use builder::{ConfigError, NlmsCancellerBuilder};
+pub use bulk_delay::estimate_bulk_delay;
+pub use comfort_noise::ComfortNoise;
+pub use denoise::SpectralDenoiser;
+pub use double_talk::{DoubleTalkScaling, GeigelDetector};
+pub use fdaf::FdafCanceller;
+pub use multichannel::{MultiChannelCanceller, RenderReference};
+pub use nonlinear::NonlinearCanceller;
+pub use suppressor::ResidualSuppressor;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+#[cfg(all(feature = "no_std", not(test)))]
+use crate::math::F32Ext as _;
+use dither::TpdfDither;
+use whitening::Whitener;
 
 const DEFAULT_EPSILON: f32 = 1e-3;
+const DEFAULT_ERLE_WINDOW: usize = 16;
+const INITIAL_ENERGY: f32 = 1e-6;
+/// Fraction of full scale up to which [`ClipMode::SoftTanh`] stays linear.
+const SOFT_CLIP_KNEE: f32 = 0.5;
+/// Fraction of the largest tap that every PNLMS gain is floored to.
+const PNLMS_RHO: f32 = 0.01;
+/// Gain floor used by PNLMS while all taps are still near zero.
+const PNLMS_DELTA: f32 = 0.01;
+/// Per-sample smoothing of the error statistics driving the variable step
+/// size.
+const VSS_SMOOTHING: f32 = 0.995;
+/// Smallest fraction of `mu` the variable step size scales down to.
+const VSS_MIN_SCALE: f32 = 0.05;
+/// Per-sample smoothing of the capture power compared against the render
+/// power for double-talk scaling: it follows rises within about 32 samples,
+/// so little adaptation happens at the onset of near-end speech, and decays
+/// over about 512.
+const CAPTURE_POWER_ATTACK: f32 = 1.0 - 1.0 / 32.0;
+const CAPTURE_POWER_RELEASE: f32 = 1.0 - 1.0 / 512.0;
+/// Tap magnitude, relative to the largest tap, below which the tail of the
+/// filter counts as negligible (-40 dB).
+const EFFECTIVE_LENGTH_THRESHOLD: f32 = 0.01;
+/// Taps kept beyond the effective length while trimming, so a lengthening
+/// echo path can still grow the active region.
+const TRIM_MIN_MARGIN: usize = 32;
+
+/// Common interface of the echo cancellers in this crate, so callers can pick
+/// an implementation at runtime.
+pub trait EchoCanceller {
+    /// Processes a capture block using the provided render block, writing the
+    /// residual echo-reduced samples into `output`.
+    fn process_block(&mut self, render: &[i16], capture: &[i16], output: &mut [i16], adapt: bool);
+
+    /// Clears all adaptation state.
+    fn reset(&mut self);
+}
+
+/// Coefficient update rule used while adapting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UpdateRule {
+    /// Normalized LMS: steps along `error * render / energy`.
+    #[default]
+    Nlms,
+    /// Uses only the sign of the error, normalized by the render RMS norm.
+    /// Cheaper and more robust to outliers, but converges less accurately.
+    SignError,
+    /// Uses only the signs of the error and the render samples; every adapted
+    /// tap moves by exactly `mu / tap_len`. The cheapest rule, avoiding the
+    /// per-tap multiply by the render signal.
+    SignSign,
+}
+
+/// How residual samples outside the `i16` range are limited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClipMode {
+    /// Clamp to the `i16` range.
+    #[default]
+    Hard,
+    /// Pass samples through linearly up to half of full scale and saturate
+    /// smoothly along a `tanh` curve above that.
+    SoftTanh,
+}
+
+impl ClipMode {
+    fn apply(self, value: f32) -> i16 {
+        let limit = i16::MAX as f32;
+        match self {
+            Self::Hard => value.clamp(i16::MIN as f32, limit) as i16,
+            Self::SoftTanh => {
+                let knee = SOFT_CLIP_KNEE * limit;
+                let magnitude = value.abs();
+                if magnitude <= knee {
+                    return value as i16;
+                }
+                let headroom = limit - knee;
+                let shaped = knee + headroom * ((magnitude - knee) / headroom).tanh();
+                shaped.copysign(value) as i16
+            }
+        }
+    }
+}
+
+/// Per-block step size callback; see
+/// [`NlmsCanceller::set_step_size_schedule`].
+type StepSizeSchedule = Box<dyn FnMut(&NlmsStats) -> f32 + Send>;
+
+/// Figures describing one processed block, passed to a
+/// [step size schedule](NlmsCanceller::set_step_size_schedule).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NlmsStats {
+    /// Zero-based index of the block since creation or the last reset.
+    pub block: u64,
+    /// Render energy within the filter window at the end of the block.
+    pub render_energy: f32,
+    /// RMS of the residual over the block.
+    pub error_rms: f32,
+}
 
 /// Adaptive filter implementing a Normalized Least Mean Squares echo canceller.
+///
+/// With the `serde` feature the complete filter state can be serialized, so a
+/// restored instance continues exactly where the original left off. A step
+/// size schedule is code rather than state and has to be set again.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NlmsCanceller {
     taps: Vec<f32>,
+    previous_taps: Vec<f32>,
+    coefficient_delta: f32,
+    /// Render history ring of `tap_len + bulk_delay` samples, stored twice
+    /// back to back so the filter window is always one contiguous slice.
     history: Vec<f32>,
     history_pos: usize,
+    bulk_delay: usize,
     energy: f32,
+    energy_refresh_interval: usize,
+    samples_since_refresh: usize,
     mu: f32,
     epsilon: f32,
+    leak: f32,
+    proportionate: bool,
+    update_rule: UpdateRule,
+    clip_mode: ClipMode,
+    dither: Option<TpdfDither>,
+    erle: ErleTracker,
+    divergence_guard: Option<DivergenceGuard>,
+    diverging_blocks: usize,
+    diverged: u64,
+    variable_step: bool,
+    error_stats: ErrorCorrelation,
+    double_talk_scaling: Option<DoubleTalkScaling>,
+    capture_power: f32,
+    double_talk_scale: f32,
+    trim: bool,
+    active_len: usize,
+    whitener: Option<Whitener>,
+    /// Render history passed through the whitener, laid out like `history`;
+    /// empty while whitening is off.
+    whitened_history: Vec<f32>,
+    whitened_energy: f32,
+    blocks: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    step_size_schedule: Option<StepSizeSchedule>,
 }
 
 impl NlmsCanceller {
     /// Creates a new canceller that tracks `tap_len` samples of the render path.
     pub fn new(tap_len: usize, mu: f32) -> Self {
-        assert!(tap_len > 0, "tap_len must be positive");
+        Self::with_epsilon(tap_len, mu, DEFAULT_EPSILON)
+    }
+
+    /// Creates a new canceller with an explicit regularization term.
+    ///
+    /// `epsilon` serves both as the floor of the render energy estimate and as
+    /// the regularizer added to it during adaptation. Smaller values let the
+    /// filter keep adapting on very quiet render signals.
+    pub fn with_epsilon(tap_len: usize, mu: f32, epsilon: f32) -> Self {
+        expect_valid(Self::builder(tap_len, mu).epsilon(epsilon).build())
+    }
+
+    /// Creates a canceller that adapts in caller-allocated buffers rather
+    /// than allocating its own, so firmware can reserve the two largest
+    /// pieces of state up front.
+    ///
+    /// The tap length is the length of `taps`. The render history is stored
+    /// twice so the filter window is always contiguous, so `history` must be
+    /// twice as long. Both are cleared and used in place. The remaining
+    /// state, including a tap-length copy of the previous taps, is still
+    /// allocated, and a later [`set_bulk_delay`](Self::set_bulk_delay)
+    /// reallocates the history to fit the delay.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `taps` is empty, if `history` is not twice as long as
+    /// `taps`, or if `mu` is not positive and finite.
+    pub fn from_buffers(taps: Vec<f32>, history: Vec<f32>, mu: f32) -> Self {
+        assert_eq!(
+            history.len(),
+            2 * taps.len(),
+            "history must be twice as long as taps"
+        );
+        expect_valid(builder::check_tap_len(taps.len()));
+        assert_valid_step_size(mu);
+        Self::from_parts(taps, history, mu, DEFAULT_EPSILON)
+    }
+
+    /// Starts a validated configuration; see [`NlmsCancellerBuilder`].
+    pub fn builder(tap_len: usize, mu: f32) -> NlmsCancellerBuilder {
+        NlmsCancellerBuilder::new(tap_len, mu)
+    }
+
+    /// Allocates a canceller with default tunables, trusting the caller to
+    /// have validated the arguments.
+    fn unchecked(tap_len: usize, mu: f32, epsilon: f32) -> Self {
+        Self::from_parts(vec![0.0; tap_len], vec![0.0; 2 * tap_len], mu, epsilon)
+    }
+
+    /// Builds a canceller with default tunables around the given tap and
+    /// mirrored history buffers, clearing them.
+    fn from_parts(mut taps: Vec<f32>, mut history: Vec<f32>, mu: f32, epsilon: f32) -> Self {
+        let tap_len = taps.len();
+        debug_assert_eq!(history.len(), 2 * tap_len);
+        taps.fill(0.0);
+        history.fill(0.0);
         Self {
-            taps: vec![0.0; tap_len],
-            history: vec![0.0; tap_len],
+            taps,
+            previous_taps: vec![0.0; tap_len],
+            coefficient_delta: 0.0,
+            history,
             history_pos: 0,
-            energy: 1e-6,
+            bulk_delay: 0,
+            energy: INITIAL_ENERGY,
+            energy_refresh_interval: tap_len,
+            samples_since_refresh: 0,
             mu,
-            epsilon: DEFAULT_EPSILON,
+            epsilon,
+            leak: 0.0,
+            proportionate: false,
+            update_rule: UpdateRule::Nlms,
+            clip_mode: ClipMode::Hard,
+            dither: None,
+            erle: ErleTracker::new(DEFAULT_ERLE_WINDOW),
+            divergence_guard: None,
+            diverging_blocks: 0,
+            diverged: 0,
+            variable_step: false,
+            error_stats: ErrorCorrelation::default(),
+            double_talk_scaling: None,
+            capture_power: 0.0,
+            double_talk_scale: 1.0,
+            trim: false,
+            active_len: tap_len,
+            whitener: None,
+            whitened_history: Vec::new(),
+            whitened_energy: INITIAL_ENERGY,
+            blocks: 0,
+            step_size_schedule: None,
+        }
+    }
+
+    /// Clears the adapted taps and render history so the filter starts over.
+    ///
+    /// The tap length and tuning parameters are preserved, and no memory is
+    /// reallocated.
+    pub fn reset(&mut self) {
+        self.taps.fill(0.0);
+        self.previous_taps.fill(0.0);
+        self.coefficient_delta = 0.0;
+        self.history.fill(0.0);
+        self.history_pos = 0;
+        self.energy = INITIAL_ENERGY;
+        self.samples_since_refresh = 0;
+        self.erle.clear();
+        self.diverging_blocks = 0;
+        self.error_stats = ErrorCorrelation::default();
+        self.capture_power = 0.0;
+        self.double_talk_scale = 1.0;
+        self.active_len = self.taps.len();
+        if let Some(whitener) = &mut self.whitener {
+            whitener.reset();
+        }
+        self.whitened_history.fill(0.0);
+        self.whitened_energy = INITIAL_ENERGY;
+        self.blocks = 0;
+    }
+
+    /// Returns the number of taps in the adaptive filter.
+    pub fn tap_len(&self) -> usize {
+        self.taps.len()
+    }
+
+    /// Returns the length of the significant part of the converged impulse
+    /// response: one past the last tap whose magnitude is at least 1% (-40 dB)
+    /// of the largest tap. An untrained filter has an effective length of
+    /// zero.
+    pub fn effective_length(&self) -> usize {
+        let peak = self.taps.iter().fold(0.0f32, |acc, w| acc.max(w.abs()));
+        if peak == 0.0 {
+            return 0;
+        }
+        let threshold = peak * EFFECTIVE_LENGTH_THRESHOLD;
+        self.taps
+            .iter()
+            .rposition(|w| w.abs() >= threshold)
+            .map_or(0, |idx| idx + 1)
+    }
+
+    /// Returns how many leading taps are currently filtered and adapted.
+    /// Equals [`tap_len`](Self::tap_len) unless trimming is enabled.
+    pub fn active_length(&self) -> usize {
+        self.active_len
+    }
+
+    /// Enables restricting the computation to the significant part of the
+    /// filter.
+    ///
+    /// After every block the active region is set to the
+    /// [`effective_length`](Self::effective_length) plus a quarter and a
+    /// small margin, saving work when the real echo tail is much shorter
+    /// than the tap length. Taps beyond the region are ignored, but it
+    /// grows again if the echo path lengthens into the margin, and an
+    /// untrained filter always uses every tap.
+    pub fn set_trim(&mut self, enabled: bool) {
+        self.trim = enabled;
+        self.active_len = self.taps.len();
+    }
+
+    /// Returns the number of render samples skipped before the filtered region.
+    pub fn bulk_delay(&self) -> usize {
+        self.bulk_delay
+    }
+
+    /// Skips `samples` of render history before the first tap, so the taps
+    /// only need to cover the echo tail rather than the whole loudspeaker to
+    /// microphone delay. See [`estimate_bulk_delay`].
+    ///
+    /// This reallocates the render history and clears it; the taps are kept.
+    pub fn set_bulk_delay(&mut self, samples: usize) {
+        self.bulk_delay = samples;
+        self.history = vec![0.0; 2 * (self.taps.len() + samples)];
+        self.history_pos = 0;
+        self.energy = INITIAL_ENERGY;
+        if self.whitener.is_some() {
+            self.whitened_history = vec![0.0; self.history.len()];
+            self.whitened_energy = INITIAL_ENERGY;
+        }
+        self.samples_since_refresh = 0;
+    }
+
+    /// Returns the current filter coefficients, newest render sample first.
+    pub fn coefficients(&self) -> &[f32] {
+        &self.taps
+    }
+
+    /// Returns the L2 norm of the filter coefficients.
+    pub fn coefficient_norm(&self) -> f32 {
+        self.taps.iter().map(|w| w * w).sum::<f32>().sqrt()
+    }
+
+    /// Returns the L2 norm of the change in coefficients over the last
+    /// processed block. A settled filter shows a small delta.
+    pub fn coefficient_delta(&self) -> f32 {
+        self.coefficient_delta
+    }
+
+    /// Replaces the filter coefficients, e.g. with a snapshot taken from a
+    /// previously converged filter via [`coefficients`](Self::coefficients).
+    ///
+    /// The new taps take effect from the next processed sample.
+    pub fn load_coefficients(&mut self, taps: &[f32]) -> Result<(), LengthMismatch> {
+        if taps.len() != self.taps.len() {
+            return Err(LengthMismatch {
+                expected: self.taps.len(),
+                actual: taps.len(),
+            });
+        }
+
+        self.taps.copy_from_slice(taps);
+        Ok(())
+    }
+
+    /// Returns the current adaptation step size.
+    pub fn step_size(&self) -> f32 {
+        self.mu
+    }
+
+    /// Changes the adaptation step size used by subsequent blocks.
+    ///
+    /// Useful for converging aggressively at first and backing off once the
+    /// filter has settled.
+    pub fn set_step_size(&mut self, mu: f32) {
+        expect_valid(builder::check_step_size(mu));
+        self.mu = mu;
+    }
+
+    /// Hands control of the step size to `schedule`, called after every
+    /// processed block with that block's [`NlmsStats`]. The step size it
+    /// returns is used from the next block on, as if passed to
+    /// [`set_step_size`](Self::set_step_size); the first block uses the
+    /// current step size.
+    ///
+    /// Returning a fixed value reproduces the behaviour of a canceller
+    /// created with that step size. With
+    /// [`set_variable_step`](Self::set_variable_step) the scheduled step is
+    /// scaled as the fixed one would be.
+    ///
+    /// # Panics
+    ///
+    /// Processing panics if `schedule` returns a step size that is not
+    /// positive and finite.
+    pub fn set_step_size_schedule(
+        &mut self,
+        schedule: impl FnMut(&NlmsStats) -> f32 + Send + 'static,
+    ) {
+        self.step_size_schedule = Some(Box::new(schedule));
+    }
+
+    /// Removes the step size schedule, keeping the step size it last
+    /// returned.
+    pub fn clear_step_size_schedule(&mut self) {
+        self.step_size_schedule = None;
+    }
+
+    /// Returns the coefficient leakage factor.
+    pub fn leakage(&self) -> f32 {
+        self.leak
+    }
+
+    /// Sets the coefficient leakage factor in `[0.0, 1.0)`.
+    ///
+    /// Every adaptation step shrinks the taps by `1.0 - leak`, which keeps them
+    /// from drifting while the render path is silent. `0.0` disables leakage.
+    pub fn set_leakage(&mut self, leak: f32) {
+        expect_valid(builder::check_leakage(leak));
+        self.leak = leak;
+    }
+
+    /// Returns the echo return loss enhancement in dB, averaged over the last
+    /// processed blocks.
+    ///
+    /// This is the ratio of capture energy to residual energy; a converging
+    /// filter shows a rising figure. Returns `0.0` before any block has been
+    /// processed.
+    pub fn erle_db(&self) -> f32 {
+        self.erle.db()
+    }
+
+    /// Sets how many recent blocks [`erle_db`](Self::erle_db) averages over,
+    /// discarding the figures gathered so far.
+    pub fn set_erle_window(&mut self, blocks: usize) {
+        expect_valid(builder::check_erle_window(blocks));
+        self.erle = ErleTracker::new(blocks);
+    }
+
+    /// Switches between plain NLMS and proportionate NLMS (PNLMS) updates.
+    ///
+    /// PNLMS scales each tap's step by its relative magnitude, which converges
+    /// much faster on sparse echo paths such as a long bulk delay followed by
+    /// a short impulse response.
+    pub fn set_proportionate(&mut self, enabled: bool) {
+        self.proportionate = enabled;
+    }
+
+    /// Enables variable step-size NLMS (VSS-NLMS).
+    ///
+    /// The step size is scaled by the normalized correlation of successive
+    /// residual samples, tracked over a few hundred samples. Far from
+    /// convergence the residual is dominated by structured echo and the full
+    /// `mu` is used; once only uncorrelated noise remains the step shrinks
+    /// towards a twentieth of `mu`, lowering the steady-state error without
+    /// slowing the initial convergence.
+    pub fn set_variable_step(&mut self, enabled: bool) {
+        self.variable_step = enabled;
+        self.error_stats = ErrorCorrelation::default();
+    }
+
+    /// Enables scaling the step size down smoothly as the capture grows
+    /// louder than the render could explain, instead of relying on the
+    /// caller to freeze adaptation outright during double-talk (`None`
+    /// disables it).
+    ///
+    /// The capture level, tracked with a fast attack and a slow release, is
+    /// compared with the RMS render level across the filter; `scaling` maps their
+    /// ratio to the factor applied to the step size of every update. Far-end
+    /// single talk keeps the full step size, near-end speech slows adaptation
+    /// progressively, and it stops altogether past the cutoff. The `adapt`
+    /// argument of the process methods still freezes adaptation on top.
+    pub fn set_double_talk_scaling(&mut self, scaling: Option<DoubleTalkScaling>) {
+        self.double_talk_scaling = scaling;
+        self.capture_power = 0.0;
+        self.double_talk_scale = 1.0;
+    }
+
+    /// Returns the double-talk step size mapping, if enabled.
+    pub fn double_talk_scaling(&self) -> Option<DoubleTalkScaling> {
+        self.double_talk_scaling
+    }
+
+    /// Enables adapting on prewhitened signals, with a linear predictor of
+    /// `order` taps (0 disables it).
+    ///
+    /// Strongly colored render such as speech spreads the eigenvalues of its
+    /// autocorrelation and slows NLMS down along the weak directions. With
+    /// whitening, the taps adapt on render and capture passed through a
+    /// prediction-error filter refitted to the render after every block,
+    /// which flattens their spectra, at the cost of a second filtering pass
+    /// per sample. The echo estimate still filters the original render, so
+    /// the output residual is not whitened and needs no inverse filter.
+    pub fn set_whitening(&mut self, order: usize) {
+        if order == 0 {
+            self.whitener = None;
+            self.whitened_history = Vec::new();
+        } else {
+            self.whitener = Some(Whitener::new(order));
+            self.whitened_history = vec![0.0; self.history.len()];
+        }
+        self.whitened_energy = INITIAL_ENERGY;
+    }
+
+    /// Returns the order of the whitening predictor, or 0 if whitening is
+    /// off.
+    pub fn whitening(&self) -> usize {
+        self.whitener.as_ref().map_or(0, Whitener::order)
+    }
+
+    /// Returns the step size the last adaptation used, which differs from
+    /// [`step_size`](Self::step_size) only with
+    /// [`set_variable_step`](Self::set_variable_step) or
+    /// [`set_double_talk_scaling`](Self::set_double_talk_scaling) enabled.
+    pub fn effective_step_size(&self) -> f32 {
+        let mu = self.mu * self.double_talk_scale;
+        if self.variable_step {
+            mu * self.error_stats.scale()
+        } else {
+            mu
+        }
+    }
+
+    /// Selects the coefficient update rule. Proportionate updates only apply
+    /// to [`UpdateRule::Nlms`].
+    pub fn set_update_rule(&mut self, rule: UpdateRule) {
+        self.update_rule = rule;
+    }
+
+    /// Selects how [`process_block`](Self::process_block) limits residual
+    /// samples to the `i16` range.
+    pub fn set_output_clip(&mut self, mode: ClipMode) {
+        self.clip_mode = mode;
+    }
+
+    /// Enables adding TPDF dither before [`process_block`](Self::process_block)
+    /// rounds residual samples to `i16`, trading quantization distortion on
+    /// quiet residuals for a flat noise floor of about one LSB. Without it
+    /// samples are truncated towards zero.
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.dither = enabled.then(TpdfDither::new);
+    }
+
+    /// Sets how many samples pass between exact recomputations of the render
+    /// energy.
+    ///
+    /// The energy is tracked incrementally per sample, which slowly accumulates
+    /// rounding error; it is periodically rebuilt from the render history to
+    /// stop that drift. Defaults to the tap length.
+    pub fn set_energy_refresh_interval(&mut self, samples: usize) {
+        expect_valid(builder::check_energy_refresh_interval(samples));
+        self.energy_refresh_interval = samples;
+    }
+
+    /// Enables automatic recovery from filter divergence.
+    ///
+    /// When the residual energy of a block exceeds `ratio` times the capture
+    /// energy for `blocks` consecutive blocks, the taps are cleared and
+    /// adaptation starts over. The guard is off until this is called.
+    pub fn set_divergence_guard(&mut self, ratio: f32, blocks: usize) {
+        expect_valid(builder::check_divergence_guard(ratio, blocks));
+        self.divergence_guard = Some(DivergenceGuard { ratio, blocks });
+        self.diverging_blocks = 0;
+    }
+
+    /// Returns how many times the divergence guard has reset the taps.
+    pub fn diverged_count(&self) -> u64 {
+        self.diverged
+    }
+
+    /// Loads render samples into the history without producing output or
+    /// adapting, so the first processed block already has render context
+    /// instead of a zero-filled history.
+    pub fn prime_history(&mut self, render: &[i16]) {
+        for &sample in render {
+            self.push_render(sample as f32);
         }
     }
 
@@ -37,71 +635,545 @@ impl NlmsCanceller {
         capture: &[i16],
         output: &mut [i16],
         adapt: bool,
+    ) {
+        self.process_block_with(render, capture, output, None, |_| adapt);
+    }
+
+    /// Like [`process_block`](Self::process_block), but also writes the echo
+    /// estimate subtracted from each capture sample into `estimate`, for
+    /// comparing it with the true echo when validating the filter offline.
+    ///
+    /// The estimate is clamped to the `i16` range but otherwise unaffected by
+    /// the output clip mode and dither.
+    pub fn process_block_with_estimate(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        residual: &mut [i16],
+        estimate: &mut [i16],
+        adapt: bool,
     ) {
         assert_eq!(
-            render.len(),
             capture.len(),
-            "render and capture chunks must match"
+            estimate.len(),
+            "estimate buffer length must match capture chunk"
         );
+        self.process_block_with(render, capture, residual, Some(estimate), |_| adapt);
+    }
+
+    /// Like [`process_block`](Self::process_block), but decides per sample
+    /// whether to adapt, so adaptation can be frozen exactly where double-talk
+    /// starts instead of for the whole block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `adapt_mask` does not have one entry per sample.
+    pub fn process_block_masked(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        adapt_mask: &[bool],
+    ) {
         assert_eq!(
-            capture.len(),
-            output.len(),
-            "output buffer length must match capture chunk"
+            adapt_mask.len(),
+            render.len(),
+            "adapt mask must match the block length"
         );
+        self.process_block_with(render, capture, output, None, |idx| adapt_mask[idx]);
+    }
 
-        let limit_min = i16::MIN as f32;
-        let limit_max = i16::MAX as f32;
+    fn process_block_with(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        mut estimate: Option<&mut [i16]>,
+        adapt: impl Fn(usize) -> bool,
+    ) {
+        check_block_lengths(render.len(), capture.len(), output.len());
 
+        let mut stats = BlockStats::default();
         for idx in 0..render.len() {
-            let new_sample = render[idx] as f32;
-            let old_sample = self.history[self.history_pos];
-
-            self.history[self.history_pos] = new_sample;
-            self.energy += new_sample * new_sample - old_sample * old_sample;
-            if self.energy < self.epsilon {
-                self.energy = self.epsilon;
+            let (echo, error) = self.step(
+                render[idx] as f32,
+                capture[idx] as f32,
+                adapt(idx),
+                &mut stats,
+            );
+            let error = match self.dither.as_mut() {
+                Some(dither) => (error + dither.next()).round(),
+                None => error,
+            };
+            output[idx] = self.clip_mode.apply(error);
+            if let Some(estimate) = estimate.as_mut() {
+                estimate[idx] = ClipMode::Hard.apply(echo);
             }
+        }
 
-            self.history_pos = (self.history_pos + 1) % self.history.len();
+        self.finish_block(stats);
+    }
 
-            let estimate = self.estimate_echo();
-            let error = capture[idx] as f32 - estimate;
-            output[idx] = error.clamp(limit_min, limit_max) as i16;
+    /// Like [`process_block`](Self::process_block), but writes the residual as
+    /// unclamped `f32` so callers can apply their own limiting or dithering.
+    pub fn process_block_into_f32(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        residual: &mut [f32],
+        adapt: bool,
+    ) {
+        check_block_lengths(render.len(), capture.len(), residual.len());
+
+        let mut stats = BlockStats::default();
+        for idx in 0..render.len() {
+            residual[idx] = self
+                .step(render[idx] as f32, capture[idx] as f32, adapt, &mut stats)
+                .1;
+        }
 
-            if adapt {
-                self.update_taps(error);
+        self.finish_block(stats);
+    }
+
+    /// Floating-point counterpart of [`process_block`](Self::process_block).
+    ///
+    /// Samples are used as-is, without conversion or clamping, so the residual
+    /// may exceed the range of the inputs. `epsilon` is interpreted in the same
+    /// units as the render signal, so normalized input usually calls for a
+    /// smaller value than the default.
+    pub fn process_block_f32(
+        &mut self,
+        render: &[f32],
+        capture: &[f32],
+        output: &mut [f32],
+        adapt: bool,
+    ) {
+        check_block_lengths(render.len(), capture.len(), output.len());
+
+        let mut stats = BlockStats::default();
+        for idx in 0..render.len() {
+            output[idx] = self.step(render[idx], capture[idx], adapt, &mut stats).1;
+        }
+
+        self.finish_block(stats);
+    }
+
+    /// Feeds one render/capture sample pair through the filter and returns the
+    /// echo estimate and the residual.
+    fn step(
+        &mut self,
+        render: f32,
+        capture: f32,
+        adapt: bool,
+        stats: &mut BlockStats,
+    ) -> (f32, f32) {
+        // A non-finite capture sample would otherwise poison every tap.
+        let capture = if capture.is_finite() { capture } else { 0.0 };
+        self.push_render(render);
+
+        let estimate = self.estimate_echo();
+        let error = capture - estimate;
+        stats.capture_energy += capture * capture;
+        stats.error_energy += error * error;
+        stats.samples += 1;
+        if let Some(scaling) = self.double_talk_scaling {
+            let power = capture * capture;
+            let smoothing = if power > self.capture_power {
+                CAPTURE_POWER_ATTACK
+            } else {
+                CAPTURE_POWER_RELEASE
+            };
+            self.capture_power = smoothing * self.capture_power + (1.0 - smoothing) * power;
+            let render_power = self.energy / self.taps.len() as f32;
+            self.double_talk_scale = scaling.scale((self.capture_power / render_power).sqrt());
+        }
+        let whitened_capture = self
+            .whitener
+            .as_mut()
+            .map(|whitener| whitener.whiten_capture(capture));
+
+        if adapt {
+            if self.variable_step {
+                self.error_stats.push(error);
             }
+            let adaptation_error = match whitened_capture {
+                Some(whitened) => whitened - self.filter(&self.whitened_history),
+                None => error,
+            };
+            self.update_taps(adaptation_error);
+        }
+
+        (estimate, error)
+    }
+
+    /// Appends one render sample to the history and updates the energy.
+    fn push_render(&mut self, render: f32) {
+        // Likewise, keep non-finite render samples out of the history.
+        let render = if render.is_finite() { render } else { 0.0 };
+        let len = self.history.len() / 2;
+        let entering = (self.history_pos + len - self.bulk_delay) % len;
+        self.energy += push_history(&mut self.history, self.history_pos, entering, render);
+        if let Some(whitener) = &mut self.whitener {
+            let whitened = whitener.whiten_render(render);
+            self.whitened_energy += push_history(
+                &mut self.whitened_history,
+                self.history_pos,
+                entering,
+                whitened,
+            );
         }
+        self.history_pos = (self.history_pos + 1) % len;
+
+        self.samples_since_refresh += 1;
+        if self.samples_since_refresh >= self.energy_refresh_interval
+            || !self.energy.is_finite()
+            || !self.whitened_energy.is_finite()
+        {
+            self.refresh_energy();
+        }
+        self.energy = self.energy.max(self.epsilon);
+        self.whitened_energy = self.whitened_energy.max(self.epsilon);
+    }
+
+    fn refresh_energy(&mut self) {
+        let window = self.window_of(self.taps.len());
+        let exact = |history: &[f32]| {
+            history[window.clone()]
+                .iter()
+                .map(|&s| f64::from(s) * f64::from(s))
+                .sum::<f64>() as f32
+        };
+        self.energy = exact(&self.history);
+        if self.whitener.is_some() {
+            self.whitened_energy = exact(&self.whitened_history);
+        }
+        self.samples_since_refresh = 0;
+    }
+
+    fn finish_block(&mut self, stats: BlockStats) {
+        self.erle.push(stats.capture_energy, stats.error_energy);
+        self.check_divergence(stats.capture_energy, stats.error_energy);
+        if let Some(whitener) = &mut self.whitener {
+            whitener.refit();
+        }
+        if self.trim {
+            let effective = self.effective_length();
+            self.active_len = if effective == 0 {
+                self.taps.len()
+            } else {
+                (effective + effective / 4 + TRIM_MIN_MARGIN).min(self.taps.len())
+            };
+        }
+
+        self.coefficient_delta = self
+            .taps
+            .iter()
+            .zip(&self.previous_taps)
+            .map(|(w, p)| (w - p) * (w - p))
+            .sum::<f32>()
+            .sqrt();
+        self.previous_taps.copy_from_slice(&self.taps);
+
+        if let Some(schedule) = self.step_size_schedule.as_mut() {
+            let mu = schedule(&NlmsStats {
+                block: self.blocks,
+                render_energy: self.energy,
+                error_rms: if stats.samples > 0 {
+                    (stats.error_energy / stats.samples as f32).sqrt()
+                } else {
+                    0.0
+                },
+            });
+            self.set_step_size(mu);
+        }
+        self.blocks += 1;
+    }
+
+    fn check_divergence(&mut self, capture_energy: f32, error_energy: f32) {
+        let Some(guard) = self.divergence_guard else {
+            return;
+        };
+
+        if error_energy > capture_energy * guard.ratio + self.epsilon {
+            self.diverging_blocks += 1;
+        } else {
+            self.diverging_blocks = 0;
+        }
+
+        if self.diverging_blocks >= guard.blocks {
+            self.taps.fill(0.0);
+            self.diverging_blocks = 0;
+            self.diverged += 1;
+        }
+    }
+
+    /// Returns the history range feeding the active taps, read backwards from
+    /// its end.
+    fn window(&self) -> Range<usize> {
+        self.window_of(self.active_len)
+    }
+
+    /// Like [`window`](Self::window), for the first `tap_len` taps.
+    fn window_of(&self, tap_len: usize) -> Range<usize> {
+        // Ending the window in the second copy of the ring keeps it in
+        // bounds without wrapping.
+        let len = self.history.len() / 2;
+        let end = (self.history_pos + len - self.bulk_delay) % len + len;
+        end - tap_len..end
+    }
+
+    fn active_fraction(&self) -> f32 {
+        self.active_len as f32 / self.taps.len() as f32
     }
 
     fn estimate_echo(&self) -> f32 {
-        let len = self.history.len();
-        let mut idx = self.history_pos;
-        let mut acc = 0.0;
-        for weight in &self.taps {
-            idx = dec_idx(len, idx);
-            acc += weight * self.history[idx];
+        self.filter(&self.history)
+    }
+
+    /// Applies the active taps to the current window of `history`, which is
+    /// laid out like the render history.
+    fn filter(&self, history: &[f32]) -> f32 {
+        let window = self.window();
+        // Summing in the two runs the ring wraps the window into keeps the
+        // estimate rounding exactly as it did before the ring was mirrored.
+        let recent_len = (window.end - self.history.len() / 2).min(window.len());
+        let (older, recent) = history[window].split_at(self.active_len - recent_len);
+        let (recent_taps, older_taps) = self.taps[..self.active_len].split_at(recent_len);
+        kernels::dot_rev(recent_taps, recent) + kernels::dot_rev(older_taps, older)
+    }
+
+    /// Returns the energy over the filter window of the render the taps
+    /// adapt on, the whitened render if whitening is on.
+    fn adaptation_energy(&self) -> f32 {
+        if self.whitener.is_some() {
+            self.whitened_energy
+        } else {
+            self.energy
         }
-        acc
     }
 
     fn update_taps(&mut self, error: f32) {
-        let norm = self.energy + self.epsilon;
-        let scale = self.mu * error / norm;
+        // The tracked energy covers every tap; scale it to the active ones.
+        let norm = self.adaptation_energy() * self.active_fraction() + self.epsilon;
+        let mu = self.effective_step_size();
+        let scale = match self.update_rule {
+            UpdateRule::Nlms if self.proportionate => {
+                self.update_taps_proportionate(error);
+                return;
+            }
+            UpdateRule::Nlms => mu * error / norm,
+            UpdateRule::SignError => mu * sign(error) / norm.sqrt(),
+            UpdateRule::SignSign => mu * sign(error) / self.active_len as f32,
+        };
+        if !scale.is_finite() {
+            return;
+        }
+        let retain = 1.0 - self.leak;
+
+        let window = self.window();
+        let history = if self.whitener.is_some() {
+            &self.whitened_history[window]
+        } else {
+            &self.history[window]
+        };
+        let taps = &mut self.taps[..self.active_len];
+        if self.update_rule == UpdateRule::SignSign {
+            kernels::leaky_sign_axpy_rev(taps, history, retain, scale);
+        } else {
+            kernels::leaky_axpy_rev(taps, history, retain, scale);
+        }
+    }
+
+    fn update_taps_proportionate(&mut self, error: f32) {
+        let max_tap = self.taps.iter().fold(0.0f32, |acc, w| acc.max(w.abs()));
+        let floor = PNLMS_RHO * max_tap.max(PNLMS_DELTA);
+
+        let window = self.window();
+        let history = if self.whitener.is_some() {
+            &self.whitened_history[window]
+        } else {
+            &self.history[window]
+        };
+        let window = || history.iter().rev();
+
+        let mut gain_sum = 0.0;
+        let mut weighted_energy = 0.0;
+        for (weight, &sample) in self.taps.iter().zip(window()) {
+            let gain = weight.abs().max(floor);
+            gain_sum += gain;
+            weighted_energy += gain * sample * sample;
+        }
+
+        // Gains are normalized to average one so `mu` keeps its NLMS meaning.
+        let mean_gain = gain_sum / self.active_len as f32;
+        let norm = weighted_energy / mean_gain + self.epsilon;
+        let scale = self.effective_step_size() * error / (norm * mean_gain);
+        if !scale.is_finite() {
+            return;
+        }
+        let retain = 1.0 - self.leak;
+
+        for (weight, &sample) in self.taps.iter_mut().zip(window()) {
+            let gain = weight.abs().max(floor);
+            *weight = retain * *weight + scale * gain * sample;
+        }
+    }
+}
+
+impl EchoCanceller for NlmsCanceller {
+    fn process_block(&mut self, render: &[i16], capture: &[i16], output: &mut [i16], adapt: bool) {
+        NlmsCanceller::process_block(self, render, capture, output, adapt);
+    }
+
+    fn reset(&mut self) {
+        NlmsCanceller::reset(self);
+    }
+}
+
+/// Smoothed lag-one autocorrelation and power of the residual, driving
+/// VSS-NLMS.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ErrorCorrelation {
+    correlation: f32,
+    power: f32,
+    previous: f32,
+}
+
+impl ErrorCorrelation {
+    fn push(&mut self, error: f32) {
+        self.correlation =
+            VSS_SMOOTHING * self.correlation + (1.0 - VSS_SMOOTHING) * error * self.previous;
+        self.power = VSS_SMOOTHING * self.power + (1.0 - VSS_SMOOTHING) * error * error;
+        self.previous = error;
+    }
+
+    /// Returns the normalized correlation, which is near one for a strongly
+    /// structured residual and near zero for white noise, as a step scale.
+    /// Before any error has been seen the full step is used.
+    fn scale(&self) -> f32 {
+        if self.power <= f32::MIN_POSITIVE {
+            return 1.0;
+        }
+        (self.correlation.abs() / self.power).clamp(VSS_MIN_SCALE, 1.0)
+    }
+}
+
+/// Energies accumulated over one processed block.
+#[derive(Default)]
+struct BlockStats {
+    capture_energy: f32,
+    error_energy: f32,
+    samples: usize,
+}
+
+/// Error returned when a coefficient slice does not match the tap length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} coefficients, got {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl core::error::Error for LengthMismatch {}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct DivergenceGuard {
+    ratio: f32,
+    blocks: usize,
+}
+
+/// Ring of per-block capture/residual energies backing the ERLE figure.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ErleTracker {
+    blocks: Vec<(f32, f32)>,
+    pos: usize,
+    filled: usize,
+}
 
-        let len = self.history.len();
-        let mut idx = self.history_pos;
-        for weight in &mut self.taps {
-            idx = dec_idx(len, idx);
-            *weight += scale * self.history[idx];
+impl ErleTracker {
+    fn new(window: usize) -> Self {
+        Self {
+            blocks: vec![(0.0, 0.0); window],
+            pos: 0,
+            filled: 0,
         }
     }
+
+    fn push(&mut self, capture_energy: f32, error_energy: f32) {
+        self.blocks[self.pos] = (capture_energy, error_energy);
+        self.pos = (self.pos + 1) % self.blocks.len();
+        self.filled = (self.filled + 1).min(self.blocks.len());
+    }
+
+    fn clear(&mut self) {
+        self.blocks.fill((0.0, 0.0));
+        self.pos = 0;
+        self.filled = 0;
+    }
+
+    fn db(&self) -> f32 {
+        if self.filled == 0 {
+            return 0.0;
+        }
+
+        let (capture, error) = self
+            .blocks
+            .iter()
+            .fold((0.0f32, 0.0f32), |(c, e), &(bc, be)| (c + bc, e + be));
+        10.0 * ((capture + 1e-9) / (error + 1e-9)).log10()
+    }
+}
+
+/// Writes `sample` at `pos` of both copies of a mirrored filter history ring
+/// and returns the change in energy of the samples within the filter window,
+/// the window's newest sample sitting at `entering`.
+fn push_history(history: &mut [f32], pos: usize, entering: usize, sample: f32) -> f32 {
+    // The ring is exactly `tap_len + bulk_delay` long, so the sample leaving
+    // the filter window is the one about to be overwritten.
+    let len = history.len() / 2;
+    let leaving = history[pos];
+    history[pos] = sample;
+    history[pos + len] = sample;
+    let entering = history[entering];
+    entering * entering - leaving * leaving
+}
+
+pub(crate) fn check_block_lengths(render: usize, capture: usize, output: usize) {
+    assert_eq!(render, capture, "render and capture chunks must match");
+    assert_eq!(
+        capture, output,
+        "output buffer length must match capture chunk"
+    );
+}
+
+pub(crate) fn assert_valid_step_size(mu: f32) {
+    expect_valid(builder::check_step_size(mu));
+}
+
+/// Unwraps a validation result, panicking with the error's message like the
+/// plain constructors and setters always have.
+fn expect_valid<T>(result: Result<T, ConfigError>) -> T {
+    result.unwrap_or_else(|err| panic!("{err}"))
 }
 
-fn dec_idx(len: usize, idx: usize) -> usize {
-    if idx == 0 {
-        len - 1
+/// Like `f32::signum`, but maps zero to zero so silence does not adapt.
+fn sign(value: f32) -> f32 {
+    if value > 0.0 {
+        1.0
+    } else if value < 0.0 {
+        -1.0
     } else {
-        idx - 1
+        0.0
     }
 }
@@ -0,0 +1,214 @@
+//! Software `f32` math for `no_std` builds.
+//!
+//! `core` lacks the transcendental functions `std` takes from the platform's
+//! libm, so this module supplies the few the cancellers use under the same
+//! method names. Modules import [`F32Ext`] only when the `no_std` feature is
+//! on; whenever `std` is linked, including in test builds, its inherent
+//! methods take precedence. Accuracy is a few ULPs over the ranges audio
+//! processing needs, not correctly rounded.
+
+use core::f32::consts::{FRAC_PI_2, LN_2, LN_10, PI};
+
+pub(crate) trait F32Ext {
+    fn trunc(self) -> f32;
+    fn round(self) -> f32;
+    fn sqrt(self) -> f32;
+    fn exp(self) -> f32;
+    fn ln(self) -> f32;
+    fn log10(self) -> f32;
+    fn sin(self) -> f32;
+    fn cos(self) -> f32;
+    fn tanh(self) -> f32;
+}
+
+pub(crate) trait F64Ext {
+    fn sqrt(self) -> f64;
+}
+
+/// Magnitude from which every `f32` is an integer.
+const INTEGRAL: f32 = 8_388_608.0;
+/// `ln(2)` split so that multiples of the high part stay exact during range
+/// reduction (Cody-Waite).
+const LN_2_HI: f32 = 0.693_145_75;
+const LN_2_LO: f32 = 1.428_606_8e-6;
+/// `2 * pi` split the same way.
+const TAU_HI: f32 = 6.281_25;
+const TAU_LO: f32 = 1.935_307_2e-3;
+
+impl F32Ext for f32 {
+    fn trunc(self) -> f32 {
+        if self.abs() < INTEGRAL {
+            self as i32 as f32
+        } else {
+            self
+        }
+    }
+
+    fn round(self) -> f32 {
+        let whole = F32Ext::trunc(self);
+        if (self - whole).abs() >= 0.5 {
+            whole + self.signum()
+        } else {
+            whole
+        }
+    }
+
+    fn sqrt(self) -> f32 {
+        if self <= 0.0 || !self.is_finite() {
+            return if self == 0.0 || self == f32::INFINITY {
+                self
+            } else {
+                f32::NAN
+            };
+        }
+        // Halving the exponent gives an estimate within a few percent, which
+        // Newton's method refines to full precision.
+        let mut root = f32::from_bits((self.to_bits() >> 1) + 0x1fbd_1df5);
+        for _ in 0..3 {
+            root = 0.5 * (root + self / root);
+        }
+        root
+    }
+
+    fn exp(self) -> f32 {
+        if self.is_nan() {
+            return self;
+        }
+        if self > 88.7 {
+            return f32::INFINITY;
+        }
+        if self < -103.9 {
+            return 0.0;
+        }
+        // exp(x) = 2^k * exp(r) with |r| <= ln(2) / 2.
+        let k = F32Ext::round(self / LN_2);
+        let r = (self - k * LN_2_HI) - k * LN_2_LO;
+        let mut term = 1.0;
+        let mut sum = 1.0;
+        for n in 1..=8 {
+            term *= r / n as f32;
+            sum += term;
+        }
+        scale_by_power_of_two(sum, k as i32)
+    }
+
+    fn ln(self) -> f32 {
+        if self < 0.0 || self.is_nan() {
+            return f32::NAN;
+        }
+        if self == 0.0 {
+            return f32::NEG_INFINITY;
+        }
+        if self == f32::INFINITY {
+            return self;
+        }
+        // ln(x) = e * ln(2) + ln(m) with m in [sqrt(0.5), sqrt(2)), and
+        // ln(m) = 2 * atanh((m - 1) / (m + 1)) as a short odd series.
+        let (mut mantissa, mut exponent) = split_exponent(self);
+        if mantissa > core::f32::consts::SQRT_2 {
+            mantissa *= 0.5;
+            exponent += 1;
+        }
+        let s = (mantissa - 1.0) / (mantissa + 1.0);
+        let s2 = s * s;
+        let series = s * (2.0 + s2 * (2.0 / 3.0 + s2 * (2.0 / 5.0 + s2 * (2.0 / 7.0 + s2 / 4.5))));
+        exponent as f32 * LN_2 + series
+    }
+
+    fn log10(self) -> f32 {
+        F32Ext::ln(self) / LN_10
+    }
+
+    fn sin(self) -> f32 {
+        if !self.is_finite() {
+            return f32::NAN;
+        }
+        // Fold onto [-pi/2, pi/2], where the Taylor series converges quickly.
+        let x = reduce_angle(self);
+        sin_kernel(if x > FRAC_PI_2 {
+            PI - x
+        } else if x < -FRAC_PI_2 {
+            -PI - x
+        } else {
+            x
+        })
+    }
+
+    fn cos(self) -> f32 {
+        if !self.is_finite() {
+            return f32::NAN;
+        }
+        sin_kernel(FRAC_PI_2 - reduce_angle(self).abs())
+    }
+
+    fn tanh(self) -> f32 {
+        if self.is_nan() {
+            return self;
+        }
+        let magnitude = self.abs();
+        if magnitude > 9.0 {
+            return self.signum();
+        }
+        if magnitude < 0.01 {
+            return self * (1.0 - self * self / 3.0);
+        }
+        let e = F32Ext::exp(2.0 * magnitude);
+        ((e - 1.0) / (e + 1.0)).copysign(self)
+    }
+}
+
+impl F64Ext for f64 {
+    fn sqrt(self) -> f64 {
+        if self <= 0.0 || !self.is_finite() {
+            return if self == 0.0 || self == f64::INFINITY {
+                self
+            } else {
+                f64::NAN
+            };
+        }
+        let mut root = f64::from_bits((self.to_bits() >> 1) + 0x1ff7_a3be_a91d_9b1b);
+        for _ in 0..4 {
+            root = 0.5 * (root + self / root);
+        }
+        root
+    }
+}
+
+/// Reduces an angle to `[-pi, pi]`.
+fn reduce_angle(angle: f32) -> f32 {
+    let turns = F32Ext::round(angle / (2.0 * PI));
+    (angle - turns * TAU_HI) - turns * TAU_LO
+}
+
+/// Sine of an angle within `[-pi/2, pi/2]`.
+fn sin_kernel(x: f32) -> f32 {
+    let x2 = x * x;
+    x * (1.0
+        - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0 * (1.0 - x2 / 72.0 * (1.0 - x2 / 110.0)))))
+}
+
+/// Returns `value * 2^exponent` for a normal `value` near one.
+fn scale_by_power_of_two(value: f32, exponent: i32) -> f32 {
+    // Two steps keep each factor representable when the result is
+    // subnormal or close to overflowing.
+    let half = exponent / 2;
+    value * power_of_two(half) * power_of_two(exponent - half)
+}
+
+fn power_of_two(exponent: i32) -> f32 {
+    f32::from_bits(((exponent + 127).clamp(1, 254) as u32) << 23)
+}
+
+/// Splits a positive finite `value` into a mantissa in `[1, 2)` and a power
+/// of two.
+fn split_exponent(value: f32) -> (f32, i32) {
+    let (value, bias) = if value < f32::MIN_POSITIVE {
+        (value * 16_777_216.0, 24)
+    } else {
+        (value, 0)
+    };
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 - bias;
+    let mantissa = f32::from_bits((bits & 0x007f_ffff) | 0x3f80_0000);
+    (mantissa, exponent)
+}
@@ -0,0 +1,153 @@
+//! Multi-channel wrapper around [`NlmsCanceller`].
+
+use alloc::vec::Vec;
+
+use crate::NlmsCanceller;
+
+/// Selects which render signal feeds the per-channel filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderReference {
+    /// Average of all render channels.
+    Mix,
+    /// A single render channel, by index.
+    Channel(usize),
+}
+
+/// Echo canceller running one adaptive filter per capture channel.
+///
+/// All slices are interleaved frame by frame: `render` carries
+/// `render_channels` samples per frame, `capture` and `output` carry
+/// `capture_channels`. Every capture channel is cancelled against the same
+/// mono render reference chosen by [`RenderReference`].
+pub struct MultiChannelCanceller {
+    render_channels: usize,
+    reference: RenderReference,
+    filters: Vec<NlmsCanceller>,
+    render_mono: Vec<i16>,
+    capture_plane: Vec<i16>,
+    output_plane: Vec<i16>,
+}
+
+impl MultiChannelCanceller {
+    /// Creates a canceller with one `tap_len`-tap filter per capture channel,
+    /// referenced against the mix of all render channels.
+    pub fn new(capture_channels: usize, render_channels: usize, tap_len: usize, mu: f32) -> Self {
+        assert!(capture_channels > 0, "capture_channels must be positive");
+        assert!(render_channels > 0, "render_channels must be positive");
+        Self {
+            render_channels,
+            reference: RenderReference::Mix,
+            filters: (0..capture_channels)
+                .map(|_| NlmsCanceller::new(tap_len, mu))
+                .collect(),
+            render_mono: Vec::new(),
+            capture_plane: Vec::new(),
+            output_plane: Vec::new(),
+        }
+    }
+
+    /// Chooses the render reference fed to every filter.
+    pub fn set_reference(&mut self, reference: RenderReference) {
+        if let RenderReference::Channel(idx) = reference {
+            assert!(
+                idx < self.render_channels,
+                "render reference channel out of range"
+            );
+        }
+        self.reference = reference;
+    }
+
+    /// Returns the filter handling capture channel `idx`.
+    pub fn channel(&self, idx: usize) -> &NlmsCanceller {
+        &self.filters[idx]
+    }
+
+    /// Returns the filter handling capture channel `idx` for tuning.
+    pub fn channel_mut(&mut self, idx: usize) -> &mut NlmsCanceller {
+        &mut self.filters[idx]
+    }
+
+    /// Processes one interleaved block, writing the interleaved residual of
+    /// every capture channel into `output`.
+    ///
+    /// `render` and `capture` must hold the same number of frames and
+    /// `output` must match `capture`.
+    pub fn process_block(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        adapt: bool,
+    ) {
+        let capture_channels = self.filters.len();
+        assert!(
+            render.len().is_multiple_of(self.render_channels),
+            "render block must hold whole frames"
+        );
+        assert!(
+            capture.len().is_multiple_of(capture_channels),
+            "capture block must hold whole frames"
+        );
+        let frames = capture.len() / capture_channels;
+        assert_eq!(
+            render.len() / self.render_channels,
+            frames,
+            "render and capture chunks must match"
+        );
+        assert_eq!(
+            capture.len(),
+            output.len(),
+            "output buffer length must match capture chunk"
+        );
+
+        self.render_mono.resize(frames, 0);
+        self.capture_plane.resize(frames, 0);
+        self.output_plane.resize(frames, 0);
+
+        for (mono, frame) in self
+            .render_mono
+            .iter_mut()
+            .zip(render.chunks_exact(self.render_channels))
+        {
+            *mono = match self.reference {
+                RenderReference::Mix => {
+                    let sum = frame.iter().map(|&s| i32::from(s)).sum::<i32>();
+                    (sum / self.render_channels as i32) as i16
+                }
+                RenderReference::Channel(idx) => frame[idx],
+            };
+        }
+
+        for (channel, filter) in self.filters.iter_mut().enumerate() {
+            for (dst, frame) in self
+                .capture_plane
+                .iter_mut()
+                .zip(capture.chunks_exact(capture_channels))
+            {
+                *dst = frame[channel];
+            }
+
+            filter.process_block(
+                &self.render_mono,
+                &self.capture_plane,
+                &mut self.output_plane,
+                adapt,
+            );
+
+            for (&src, frame) in self
+                .output_plane
+                .iter()
+                .zip(output.chunks_exact_mut(capture_channels))
+            {
+                frame[channel] = src;
+            }
+        }
+    }
+
+    /// Clears the adaptation state of every channel.
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Power-series (Hammerstein) echo canceller for loudspeaker distortion.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{DEFAULT_EPSILON, EchoCanceller, check_block_lengths};
+
+/// Full-scale render amplitude; the render is normalized by it before being
+/// raised to a power, so every branch stays in `i16` units.
+const FULL_SCALE: f32 = 32_768.0;
+
+/// Echo canceller modeling the echo path as a memoryless polynomial
+/// nonlinearity followed by linear filters.
+///
+/// The render reference is expanded into its first `order` powers (render,
+/// render², render³, ...) after normalizing to full scale, and each power
+/// drives its own adaptive filter. The branch estimates are summed and all
+/// branches adapt on the shared residual, each normalized by its own input
+/// energy so the weak higher powers converge as fast as the linear branch.
+/// With `order = 1` this is plain NLMS.
+pub struct NonlinearCanceller {
+    branches: Vec<Branch>,
+    tap_len: usize,
+    pos: usize,
+    mu: f32,
+    epsilon: f32,
+}
+
+/// One power of the render signal and the filter it feeds.
+struct Branch {
+    taps: Vec<f32>,
+    /// Inputs newest first, written twice `tap_len` apart so the current
+    /// input vector is a contiguous slice.
+    buffer: Vec<f32>,
+    energy: f32,
+}
+
+impl NonlinearCanceller {
+    /// Creates a canceller with `order` branches of `tap_len` taps each.
+    pub fn new(tap_len: usize, order: usize, mu: f32) -> Self {
+        assert!(tap_len > 0, "tap_len must be positive");
+        assert!(order > 0, "order must be positive");
+        crate::assert_valid_step_size(mu);
+
+        Self {
+            branches: (0..order)
+                .map(|_| Branch {
+                    taps: vec![0.0; tap_len],
+                    buffer: vec![0.0; tap_len * 2],
+                    energy: 0.0,
+                })
+                .collect(),
+            tap_len,
+            pos: 0,
+            mu,
+            epsilon: DEFAULT_EPSILON,
+        }
+    }
+
+    /// Returns the number of taps in each branch.
+    pub fn tap_len(&self) -> usize {
+        self.tap_len
+    }
+
+    /// Returns the number of branches, the highest power of the render
+    /// signal modeled.
+    pub fn order(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// Returns the taps of the branch driven by the render signal raised to
+    /// `power`, counting from one for the linear branch.
+    pub fn branch(&self, power: usize) -> &[f32] {
+        assert!(
+            (1..=self.branches.len()).contains(&power),
+            "power must be between 1 and the order"
+        );
+        &self.branches[power - 1].taps
+    }
+
+    /// Processes a capture block using the provided render block, writing the
+    /// residual echo-reduced samples into `output`.
+    pub fn process_block(
+        &mut self,
+        render: &[i16],
+        capture: &[i16],
+        output: &mut [i16],
+        adapt: bool,
+    ) {
+        check_block_lengths(render.len(), capture.len(), output.len());
+
+        for idx in 0..render.len() {
+            let error = self.step(render[idx] as f32, capture[idx] as f32, adapt);
+            output[idx] = error.clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+
+    /// Clears the adapted taps and render history.
+    pub fn reset(&mut self) {
+        for branch in &mut self.branches {
+            branch.taps.fill(0.0);
+            branch.buffer.fill(0.0);
+            branch.energy = 0.0;
+        }
+        self.pos = 0;
+    }
+
+    fn step(&mut self, render: f32, capture: f32, adapt: bool) -> f32 {
+        let tap_len = self.tap_len;
+        self.pos = if self.pos == 0 {
+            tap_len - 1
+        } else {
+            self.pos - 1
+        };
+        let pos = self.pos;
+
+        let normalized = render / FULL_SCALE;
+        let mut power = normalized;
+        let mut estimate = 0.0;
+        for branch in &mut self.branches {
+            let input = power * FULL_SCALE;
+            let leaving = branch.buffer[pos];
+            branch.buffer[pos] = input;
+            branch.buffer[pos + tap_len] = input;
+            branch.energy = if pos == 0 {
+                // Recompute once per pass over the buffer so rounding errors
+                // in the running sum do not accumulate.
+                dot(&branch.buffer[..tap_len], &branch.buffer[..tap_len])
+            } else {
+                (branch.energy + input * input - leaving * leaving).max(0.0)
+            };
+            estimate += dot(&branch.taps, &branch.buffer[pos..pos + tap_len]);
+            power *= normalized;
+        }
+        let error = capture - estimate;
+
+        if adapt && error.is_finite() {
+            let order = self.branches.len() as f32;
+            for branch in &mut self.branches {
+                let scale = self.mu * error / (order * (branch.energy + self.epsilon));
+                if !scale.is_finite() {
+                    continue;
+                }
+                for (w, &x) in branch
+                    .taps
+                    .iter_mut()
+                    .zip(&branch.buffer[pos..pos + tap_len])
+                {
+                    *w += scale * x;
+                }
+            }
+        }
+
+        error
+    }
+}
+
+impl EchoCanceller for NonlinearCanceller {
+    fn process_block(&mut self, render: &[i16], capture: &[i16], output: &mut [i16], adapt: bool) {
+        NonlinearCanceller::process_block(self, render, capture, output, adapt);
+    }
+
+    fn reset(&mut self) {
+        NonlinearCanceller::reset(self);
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
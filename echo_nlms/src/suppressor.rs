@@ -0,0 +1,102 @@
+//! Gain-based residual echo suppression.
+
+/// Render power (per sample, in squared `i16` units) below which the render
+/// path is treated as silent and no suppression is applied.
+const RENDER_POWER_FLOOR: f32 = 4_000.0;
+/// How quickly the coupling estimate may rise per block; falls are immediate.
+const COUPLING_RISE: f32 = 0.05;
+
+/// Attenuates the echo left over after linear cancellation.
+///
+/// The suppressor tracks how strongly render energy couples into the residual
+/// and, while the render path is active, scales each block by a
+/// frequency-independent Wiener-style gain that removes the estimated echo
+/// share. The gain never drops below `min_gain` and is ramped across each
+/// block to avoid zipper noise.
+pub struct ResidualSuppressor {
+    min_gain: f32,
+    overdrive: f32,
+    coupling: f32,
+    gain: f32,
+}
+
+impl ResidualSuppressor {
+    /// Creates a suppressor. `overdrive` scales the echo estimate (values above
+    /// `1.0` suppress more aggressively) and `min_gain` bounds the attenuation.
+    pub fn new(min_gain: f32, overdrive: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&min_gain),
+            "min_gain must be within [0.0, 1.0]"
+        );
+        assert!(
+            overdrive.is_finite() && overdrive > 0.0,
+            "overdrive must be positive and finite"
+        );
+        Self {
+            min_gain,
+            overdrive,
+            coupling: 1.0,
+            gain: 1.0,
+        }
+    }
+
+    /// Returns the gain applied at the end of the last block.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Applies suppression to `residual` in place, using the render block that
+    /// produced its echo.
+    pub fn suppress(&mut self, render: &[i16], residual: &mut [i16]) {
+        assert_eq!(
+            render.len(),
+            residual.len(),
+            "render and residual chunks must match"
+        );
+        if residual.is_empty() {
+            return;
+        }
+
+        let render_power = mean_power(render);
+        let residual_power = mean_power(residual);
+
+        let target = if render_power < RENDER_POWER_FLOOR {
+            1.0
+        } else {
+            let ratio = residual_power / render_power;
+            if ratio < self.coupling {
+                self.coupling = ratio;
+            } else {
+                self.coupling += (ratio - self.coupling) * COUPLING_RISE;
+            }
+
+            let echo_power = self.coupling * render_power * self.overdrive;
+            ((residual_power - echo_power) / residual_power.max(1e-9)).clamp(self.min_gain, 1.0)
+        };
+
+        let start = self.gain;
+        let step = (target - start) / residual.len() as f32;
+        for (idx, sample) in residual.iter_mut().enumerate() {
+            let gain = start + step * (idx + 1) as f32;
+            *sample = (*sample as f32 * gain) as i16;
+        }
+        self.gain = target;
+    }
+
+    /// Forgets the coupling estimate and restores unity gain.
+    pub fn reset(&mut self) {
+        self.coupling = 1.0;
+        self.gain = 1.0;
+    }
+}
+
+fn mean_power(samples: &[i16]) -> f32 {
+    samples
+        .iter()
+        .map(|&s| {
+            let v = s as f32;
+            v * v
+        })
+        .sum::<f32>()
+        / samples.len() as f32
+}
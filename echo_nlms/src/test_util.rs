@@ -0,0 +1,214 @@
+//! Deterministic test signals shared by the DSP tests of the workspace, and
+//! the fixtures the canceller tests build on them.
+//!
+//! Everything is seeded or closed-form, so a failing test reproduces exactly.
+//! Signals are generated as `f32` in sample units; [`to_i16`] converts them
+//! for the integer processing paths.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::TAU;
+
+use crate::{EchoCanceller, NlmsCanceller};
+
+#[cfg(all(feature = "no_std", not(test)))]
+use crate::math::F32Ext as _;
+
+/// Uniform white noise in `[-1.0, 1.0]` from a xorshift generator.
+#[derive(Clone, Debug)]
+pub struct WhiteNoise {
+    state: u32,
+}
+
+impl WhiteNoise {
+    /// Creates a generator; the same nonzero `seed` always produces the same
+    /// sequence.
+    pub fn new(seed: u32) -> Self {
+        assert!(seed != 0, "seed must be nonzero");
+        Self { state: seed }
+    }
+}
+
+impl Iterator for WhiteNoise {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        Some(self.state as f32 / u32::MAX as f32 * 2.0 - 1.0)
+    }
+}
+
+/// Returns `len` samples of uniform white noise with peak `amplitude`.
+pub fn white_noise(len: usize, amplitude: f32, seed: u32) -> Vec<f32> {
+    WhiteNoise::new(seed)
+        .take(len)
+        .map(|sample| sample * amplitude)
+        .collect()
+}
+
+/// Like [`white_noise`], rounded to `i16`.
+pub fn white_noise_i16(len: usize, amplitude: f32, seed: u32) -> Vec<i16> {
+    to_i16(&white_noise(len, amplitude, seed))
+}
+
+/// Returns `len` samples of white noise with peak `amplitude` passed through
+/// the all-pole filter `x[n] = w[n] + ar[0] x[n-1] + ar[1] x[n-2] + ...`,
+/// whose resonances color the spectrum.
+pub fn colored_noise(len: usize, amplitude: f32, ar: &[f32], seed: u32) -> Vec<f32> {
+    let mut output: Vec<f32> = Vec::with_capacity(len);
+    for sample in WhiteNoise::new(seed).take(len) {
+        let feedback: f32 = ar
+            .iter()
+            .zip(output.iter().rev())
+            .map(|(a, past)| a * past)
+            .sum();
+        output.push(sample * amplitude + feedback);
+    }
+    output
+}
+
+/// Returns `len` samples of a sine of `freq` Hz and peak `amplitude`,
+/// starting at zero phase.
+pub fn sine(len: usize, freq: f32, sample_rate: u32, amplitude: f32) -> Vec<f32> {
+    chord(len, &[freq], sample_rate, amplitude)
+}
+
+/// Returns the sum of sines at `freqs`, each of peak `amplitude`.
+pub fn chord(len: usize, freqs: &[f32], sample_rate: u32, amplitude: f32) -> Vec<f32> {
+    (0..len)
+        .map(|idx| {
+            let t = idx as f32 / sample_rate as f32;
+            freqs
+                .iter()
+                .map(|&freq| amplitude * (TAU * freq * t).sin())
+                .sum()
+        })
+        .collect()
+}
+
+/// Returns `signal` convolved with the impulse response `path`, truncated to
+/// the length of `signal`: the echo a loudspeaker playing `signal` leaves in
+/// the capture, with silence before the first sample.
+pub fn convolve(signal: &[f32], path: &[f32]) -> Vec<f32> {
+    (0..signal.len())
+        .map(|idx| {
+            path.iter()
+                .zip(signal[..=idx].iter().rev())
+                .map(|(h, x)| h * x)
+                .sum()
+        })
+        .collect()
+}
+
+/// Rounds samples to `i16`, saturating at full scale.
+pub fn to_i16(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|&sample| sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+        .collect()
+}
+
+/// Returns a decaying echo path of `len` taps with a few distinct
+/// reflections, peaking at tap 5.
+///
+/// # Panics
+///
+/// Panics if `len` is 20 or less, which would cut off a reflection.
+pub fn echo_path(len: usize) -> Vec<f32> {
+    assert!(len > 20, "echo path must be longer than 20 taps");
+    let mut path: Vec<f32> = (0..len)
+        .map(|idx| 0.3 * (-(idx as f32) / 12.0).exp() * if idx % 3 == 0 { 1.0 } else { -0.5 })
+        .collect();
+    path[5] += 0.4;
+    path[20] -= 0.2;
+    path
+}
+
+/// Direct sound and one reflection: a path a few taps model exactly, for
+/// tests about adaptation control rather than path identification.
+pub const SHORT_ECHO_PATH: [f32; 6] = [0.5, 0.0, 0.0, 0.0, 0.0, 0.25];
+
+/// Peak level of the white noise the [`SHORT_ECHO_PATH`] tests play and mix
+/// in as near-end talk.
+pub const NOISE_PEAK: f32 = 2048.0;
+
+/// Returns the echo of `render` through `path`, rounded to `i16`.
+pub fn echo(render: &[i16], path: &[f32]) -> Vec<i16> {
+    let render: Vec<f32> = render.iter().map(|&sample| sample as f32).collect();
+    to_i16(&convolve(&render, path))
+}
+
+/// Runs `canceller` over `render` and `capture` in blocks of `block`
+/// samples, adapting throughout, and returns the whole residual.
+pub fn cancel<C: EchoCanceller + ?Sized>(
+    canceller: &mut C,
+    render: &[i16],
+    capture: &[i16],
+    block: usize,
+) -> Vec<i16> {
+    let mut residual = vec![0i16; capture.len()];
+    for ((render, capture), output) in render
+        .chunks(block)
+        .zip(capture.chunks(block))
+        .zip(residual.chunks_mut(block))
+    {
+        canceller.process_block(render, capture, output, true);
+    }
+    residual
+}
+
+/// Runs `canceller` like [`cancel`] and returns the energies of the final
+/// block of `capture` and of the residual.
+pub fn final_block_energies<C: EchoCanceller + ?Sized>(
+    canceller: &mut C,
+    render: &[i16],
+    capture: &[i16],
+    block: usize,
+) -> (f64, f64) {
+    let residual = cancel(canceller, render, capture, block);
+    let last = capture.len().saturating_sub(block)..;
+    (energy(&capture[last.clone()]), energy(&residual[last]))
+}
+
+/// Returns the energy of `samples`.
+pub fn energy(samples: &[i16]) -> f64 {
+    samples
+        .iter()
+        .map(|&sample| f64::from(sample) * f64::from(sample))
+        .sum()
+}
+
+/// Returns the squared distance between `taps` and the true `path`, over
+/// the taps the two share.
+pub fn misalignment(taps: &[f32], path: &[f32]) -> f32 {
+    taps.iter().zip(path).map(|(w, h)| (w - h) * (w - h)).sum()
+}
+
+/// Runs `canceller` like [`cancel`] and returns its [`misalignment`] from
+/// `path` after every block.
+pub fn misalignment_curve(
+    canceller: &mut NlmsCanceller,
+    render: &[i16],
+    capture: &[i16],
+    path: &[f32],
+    block: usize,
+) -> Vec<f32> {
+    let mut residual = vec![0i16; block];
+    render
+        .chunks(block)
+        .zip(capture.chunks(block))
+        .map(|(render, capture)| {
+            let residual = &mut residual[..capture.len()];
+            canceller.process_block(render, capture, residual, true);
+            misalignment(canceller.coefficients(), path)
+        })
+        .collect()
+}
+
+/// The inner loops of the time-domain cancellers, for checking the vectorized
+/// kernels against their scalar fallbacks and timing both.
+pub mod kernels {
+    pub use crate::kernels::{dot_rev, leaky_axpy_rev, leaky_sign_axpy_rev, scalar};
+}
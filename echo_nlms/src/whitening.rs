@@ -0,0 +1,123 @@
+//! Adaptive linear-prediction prewhitening for the NLMS update.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Per-sample forgetting factor of the render autocorrelation estimate, a
+/// memory of about 4096 samples.
+const AUTOCORRELATION_SMOOTHING: f32 = 1.0 - 1.0 / 4096.0;
+/// Relative amount of white noise added to the autocorrelation before
+/// solving for the predictor, keeping it stable on near-tonal render.
+const NOISE_FLOOR: f32 = 1e-3;
+
+/// Prediction-error filter `A(z) = 1 + a1 z^-1 + ... + ap z^-p` fitted to the
+/// render signal.
+///
+/// Filtering the render through `A(z)` flattens its spectrum. Since the echo
+/// path is linear, the capture filtered the same way contains the echo of the
+/// whitened render, so the canceller can adapt on the whitened pair while
+/// still filtering the original render for its output. The predictor is
+/// refitted from a running autocorrelation estimate by
+/// [`refit`](Self::refit), once per block.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Whitener {
+    coefficients: Vec<f32>,
+    /// Recent raw render samples, newest first.
+    render: Vec<f32>,
+    /// Recent raw capture samples, newest first.
+    capture: Vec<f32>,
+    autocorrelation: Vec<f32>,
+    /// Scratch space for [`refit`](Self::refit), so it does not allocate.
+    candidate: Vec<f32>,
+    previous: Vec<f32>,
+}
+
+impl Whitener {
+    pub fn new(order: usize) -> Self {
+        Self {
+            coefficients: vec![0.0; order],
+            render: vec![0.0; order],
+            capture: vec![0.0; order],
+            autocorrelation: vec![0.0; order + 1],
+            candidate: vec![0.0; order],
+            previous: vec![0.0; order],
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// Filters one render sample, also folding it into the autocorrelation
+    /// estimate.
+    pub fn whiten_render(&mut self, sample: f32) -> f32 {
+        self.autocorrelation[0] =
+            AUTOCORRELATION_SMOOTHING * self.autocorrelation[0] + sample * sample;
+        for (r, &past) in self.autocorrelation[1..].iter_mut().zip(&self.render) {
+            *r = AUTOCORRELATION_SMOOTHING * *r + sample * past;
+        }
+        filter(&self.coefficients, &mut self.render, sample)
+    }
+
+    /// Filters one capture sample.
+    pub fn whiten_capture(&mut self, sample: f32) -> f32 {
+        filter(&self.coefficients, &mut self.capture, sample)
+    }
+
+    /// Refits the predictor to the current autocorrelation estimate by the
+    /// Levinson-Durbin recursion. Keeps the previous fit while the render
+    /// has been silent.
+    pub fn refit(&mut self) {
+        let energy = self.autocorrelation[0] * (1.0 + NOISE_FLOOR);
+        if !(energy > f32::MIN_POSITIVE && energy.is_finite()) {
+            return;
+        }
+
+        let candidate = &mut self.candidate;
+        let previous = &mut self.previous;
+        let mut error = energy;
+        for m in 0..candidate.len() {
+            let acc = self.autocorrelation[m + 1]
+                + candidate[..m]
+                    .iter()
+                    .zip(self.autocorrelation[1..=m].iter().rev())
+                    .map(|(a, r)| a * r)
+                    .sum::<f32>();
+            let reflection = -acc / error;
+            if !reflection.is_finite() || reflection.abs() >= 1.0 {
+                return;
+            }
+            previous[..m].copy_from_slice(&candidate[..m]);
+            for (a, &p) in candidate[..m].iter_mut().zip(previous[..m].iter().rev()) {
+                *a += reflection * p;
+            }
+            candidate[m] = reflection;
+            error *= 1.0 - reflection * reflection;
+        }
+        self.coefficients.copy_from_slice(candidate);
+    }
+
+    pub fn reset(&mut self) {
+        self.coefficients.fill(0.0);
+        self.render.fill(0.0);
+        self.capture.fill(0.0);
+        self.autocorrelation.fill(0.0);
+    }
+}
+
+/// Applies the prediction-error filter to `sample` and shifts it into
+/// `past`.
+fn filter(coefficients: &[f32], past: &mut [f32], sample: f32) -> f32 {
+    let output = sample
+        + coefficients
+            .iter()
+            .zip(past.iter())
+            .map(|(a, x)| a * x)
+            .sum::<f32>();
+    if !past.is_empty() {
+        past.rotate_right(1);
+        past[0] = sample;
+    }
+    output
+}
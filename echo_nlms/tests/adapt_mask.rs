@@ -0,0 +1,64 @@
+//! Per-sample adaptation masks against whole-block adaptation.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{echo, white_noise_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+
+/// White-noise render and its echo through a short decaying path.
+fn signals(blocks: usize) -> (Vec<i16>, Vec<i16>) {
+    let render = white_noise_i16(blocks * BLOCK, 4096.0, 0xdead_beef);
+    let path: Vec<f32> = (0..8).map(|k| 0.5 / (k + 1) as f32).collect();
+    let capture = echo(&render, &path);
+    (render, capture)
+}
+
+#[test]
+fn uniform_masks_match_block_adaptation() {
+    let (render, capture) = signals(8);
+    for adapt in [false, true] {
+        let mut plain = NlmsCanceller::new(TAPS, 0.5);
+        let mut masked = NlmsCanceller::new(TAPS, 0.5);
+        let mask = [adapt; BLOCK];
+        let mut expected = [0i16; BLOCK];
+        let mut output = [0i16; BLOCK];
+        for (render, capture) in render.chunks(BLOCK).zip(capture.chunks(BLOCK)) {
+            plain.process_block(render, capture, &mut expected, adapt);
+            masked.process_block_masked(render, capture, &mut output, &mask);
+            assert_eq!(output, expected);
+        }
+        assert_eq!(masked.coefficients(), plain.coefficients());
+    }
+}
+
+#[test]
+fn mask_freezes_adaptation_mid_block() {
+    let (render, capture) = signals(1);
+    let half = BLOCK / 2;
+
+    // Adapting only on the first half of a block must equal processing the
+    // halves as separate blocks, adapting on the first.
+    let mut masked = NlmsCanceller::new(TAPS, 0.5);
+    let mask: Vec<bool> = (0..BLOCK).map(|idx| idx < half).collect();
+    let mut output = [0i16; BLOCK];
+    masked.process_block_masked(&render, &capture, &mut output, &mask);
+
+    let mut split = NlmsCanceller::new(TAPS, 0.5);
+    let mut expected = [0i16; BLOCK];
+    let (first, second) = expected.split_at_mut(half);
+    split.process_block(&render[..half], &capture[..half], first, true);
+    split.process_block(&render[half..], &capture[half..], second, false);
+
+    assert_eq!(output, expected);
+    assert_eq!(masked.coefficients(), split.coefficients());
+}
+
+#[test]
+#[should_panic(expected = "adapt mask")]
+fn short_mask_is_rejected() {
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    let block = [0i16; BLOCK];
+    let mut output = [0i16; BLOCK];
+    canceller.process_block_masked(&block, &block, &mut output, &[true; BLOCK - 1]);
+}
@@ -0,0 +1,61 @@
+//! Tap-to-history alignment of `NlmsCanceller`: a pure delay of `d` samples
+//! must converge onto tap `d`, both for the echo estimate and the update.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{cancel, white_noise_i16};
+
+const TAPS: usize = 128;
+const BLOCK: usize = 256;
+
+fn peak_tap(taps: &[f32]) -> usize {
+    taps.iter()
+        .enumerate()
+        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        .map(|(idx, _)| idx)
+        .unwrap()
+}
+
+#[test]
+fn impulse_through_pure_delay_lands_on_matching_tap() {
+    for delay in [0, 1, 2, 17, TAPS - 1] {
+        let mut canceller = NlmsCanceller::new(TAPS, 1.0);
+        let mut render = vec![0i16; BLOCK];
+        let mut capture = vec![0i16; BLOCK];
+        let mut residual = vec![0i16; BLOCK];
+        render[0] = 10_000;
+        capture[delay] = 10_000;
+
+        canceller.process_block(&render, &capture, &mut residual, true);
+
+        let taps = canceller.coefficients();
+        assert_eq!(peak_tap(taps), delay, "delay {delay}");
+        assert!((taps[delay] - 1.0).abs() < 1e-3, "tap {}", taps[delay]);
+        assert!(
+            taps.iter()
+                .enumerate()
+                .all(|(idx, &tap)| idx == delay || tap == 0.0),
+            "delay {delay}: stray taps"
+        );
+    }
+}
+
+#[test]
+fn converged_filter_cancels_pure_delay_after_bulk_delay() {
+    const BULK: usize = 300;
+    const DELAY: usize = BULK + 40;
+
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    canceller.set_bulk_delay(BULK);
+
+    let render = white_noise_i16(100 * BLOCK, 4096.0, 0x9e37_79b9);
+    let capture: Vec<i16> = (0..render.len())
+        .map(|idx| idx.checked_sub(DELAY).map_or(0, |idx| render[idx]))
+        .collect();
+    let residual = cancel(&mut canceller, &render, &capture, BLOCK);
+    let residual = &residual[residual.len() - BLOCK..];
+
+    let taps = canceller.coefficients();
+    assert_eq!(peak_tap(taps), DELAY - BULK);
+    assert!((taps[DELAY - BULK] - 1.0).abs() < 0.01);
+    assert!(residual.iter().all(|&s| s.abs() <= 2), "{residual:?}");
+}
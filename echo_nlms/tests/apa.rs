@@ -0,0 +1,59 @@
+//! Affine projection against NLMS.
+
+use echo_nlms::test_util::{
+    cancel, colored_noise, echo, echo_path, energy, to_i16, white_noise_i16,
+};
+use echo_nlms::{ApaCanceller, EchoCanceller, NlmsCanceller};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 40;
+
+/// Returns the index of the first block whose residual sits `target_db`
+/// below its capture, with the render an AR(2) process resonating near
+/// 1.3 kHz.
+fn blocks_to_converge(canceller: &mut dyn EchoCanceller, target_db: f64) -> Option<usize> {
+    // Poles at radius 0.95, so the spectrum spans about 30 dB.
+    let render = to_i16(&colored_noise(
+        BLOCKS * BLOCK,
+        800.0,
+        &[1.8, -0.9025],
+        0x1234_5678,
+    ));
+    let capture = echo(&render, &echo_path(TAPS));
+    let residual = cancel(canceller, &render, &capture, BLOCK);
+    residual
+        .chunks(BLOCK)
+        .zip(capture.chunks(BLOCK))
+        .position(|(residual, capture)| {
+            10.0 * (energy(residual) / energy(capture)).log10() < -target_db
+        })
+}
+
+#[test]
+fn projection_converges_faster_on_colored_render() {
+    let apa = blocks_to_converge(&mut ApaCanceller::new(TAPS, 4, 0.5), 40.0);
+    let nlms = blocks_to_converge(&mut NlmsCanceller::new(TAPS, 0.5), 40.0);
+    let apa = apa.expect("APA never reached 40 dB");
+    assert!(
+        nlms.is_none_or(|nlms| apa < nlms),
+        "APA after {apa} blocks, NLMS after {nlms:?}"
+    );
+}
+
+#[test]
+fn first_order_reduces_to_nlms() {
+    let render = white_noise_i16(20 * BLOCK, 8000.0, 0x0a9a_0001);
+    let capture = echo(&render, &echo_path(TAPS));
+    let nlms = cancel(&mut NlmsCanceller::new(TAPS, 0.5), &render, &capture, BLOCK);
+    let apa = cancel(
+        &mut ApaCanceller::new(TAPS, 1, 0.5),
+        &render,
+        &capture,
+        BLOCK,
+    );
+    // The two only differ in how they sum the render energy.
+    for (idx, (nlms, apa)) in nlms.iter().zip(&apa).enumerate() {
+        assert!((nlms - apa).abs() <= 1, "sample {idx}: {nlms} vs {apa}");
+    }
+}
@@ -0,0 +1,109 @@
+//! Builder validation and parity with the plain constructor.
+
+use echo_nlms::test_util::{cancel, echo, echo_path, white_noise_i16};
+use echo_nlms::{ConfigError, NlmsCanceller, NlmsCancellerBuilder};
+
+const TAPS: usize = 64;
+const MU: f32 = 0.5;
+const BLOCK: usize = 256;
+
+fn error(builder: NlmsCancellerBuilder) -> Option<ConfigError> {
+    builder.build().err()
+}
+
+#[test]
+fn invalid_values_report_their_variant() {
+    let builder = || NlmsCanceller::builder(TAPS, MU);
+
+    assert_eq!(
+        error(NlmsCanceller::builder(0, MU)),
+        Some(ConfigError::TapLen)
+    );
+    for mu in [0.0, -0.1, f32::INFINITY] {
+        assert_eq!(
+            error(NlmsCanceller::builder(TAPS, mu)),
+            Some(ConfigError::StepSize(mu))
+        );
+    }
+    assert!(matches!(
+        error(NlmsCanceller::builder(TAPS, f32::NAN)),
+        Some(ConfigError::StepSize(mu)) if mu.is_nan()
+    ));
+    for epsilon in [0.0, -1.0, f32::INFINITY] {
+        assert_eq!(
+            error(builder().epsilon(epsilon)),
+            Some(ConfigError::Epsilon(epsilon))
+        );
+    }
+    for leak in [-0.1, 1.0, f32::INFINITY] {
+        assert_eq!(
+            error(builder().leakage(leak)),
+            Some(ConfigError::Leakage(leak))
+        );
+    }
+    for ratio in [0.0, -2.0, f32::INFINITY] {
+        assert_eq!(
+            error(builder().divergence_guard(ratio, 4)),
+            Some(ConfigError::DivergenceRatio(ratio))
+        );
+    }
+    assert_eq!(
+        error(builder().divergence_guard(4.0, 0)),
+        Some(ConfigError::DivergenceBlocks)
+    );
+    assert_eq!(
+        error(builder().energy_refresh_interval(0)),
+        Some(ConfigError::EnergyRefreshInterval)
+    );
+    assert_eq!(
+        error(builder().erle_window(0)),
+        Some(ConfigError::ErleWindow)
+    );
+}
+
+#[test]
+fn checks_run_in_declaration_order() {
+    assert_eq!(
+        error(NlmsCanceller::builder(0, 0.0).epsilon(0.0)),
+        Some(ConfigError::TapLen)
+    );
+    assert_eq!(
+        error(NlmsCanceller::builder(TAPS, 0.0).epsilon(0.0)),
+        Some(ConfigError::StepSize(0.0))
+    );
+}
+
+#[test]
+fn boundary_values_are_accepted() {
+    assert!(
+        NlmsCanceller::builder(1, MU)
+            .leakage(0.0)
+            .divergence_guard(1.0, 1)
+            .energy_refresh_interval(1)
+            .erle_window(1)
+            .build()
+            .is_ok()
+    );
+}
+
+#[test]
+fn new_matches_the_default_builder() {
+    let path = echo_path(TAPS);
+    let render = white_noise_i16(40 * BLOCK, 8000.0, 0xb11d_0001);
+    let capture = echo(&render, &path);
+
+    let mut plain = NlmsCanceller::new(TAPS, MU);
+    let mut built = NlmsCanceller::builder(TAPS, MU).build().unwrap();
+    assert_eq!(plain.tap_len(), built.tap_len());
+    assert_eq!(plain.step_size(), built.step_size());
+    assert_eq!(plain.leakage(), built.leakage());
+    assert_eq!(plain.bulk_delay(), built.bulk_delay());
+    assert_eq!(plain.whitening(), built.whitening());
+    assert_eq!(plain.double_talk_scaling(), built.double_talk_scaling());
+
+    let plain_residual = cancel(&mut plain, &render, &capture, BLOCK);
+    let built_residual = cancel(&mut built, &render, &capture, BLOCK);
+    assert_eq!(plain_residual, built_residual);
+    assert_eq!(plain.coefficients(), built.coefficients());
+    assert_eq!(plain.erle_db(), built.erle_db());
+}
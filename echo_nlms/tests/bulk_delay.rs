@@ -0,0 +1,79 @@
+//! Bulk delay estimation and its use with a short canceller.
+
+use echo_nlms::test_util::{echo, echo_path, final_block_energies, white_noise_i16};
+use echo_nlms::{NlmsCanceller, estimate_bulk_delay};
+
+const DELAY: usize = 400;
+const TAIL: usize = 40;
+const TAPS: usize = 64;
+const MAX_LAG: usize = 1024;
+const BLOCK: usize = 256;
+/// How far past the onset of the tail the estimate may land: the first and
+/// sixth taps of `echo_path` are almost equally loud.
+const SLACK: usize = 8;
+
+/// Returns `echo_path(TAIL)` behind `DELAY` samples of silence.
+fn delayed_path() -> Vec<f32> {
+    let mut path = vec![0.0; DELAY];
+    path.extend(echo_path(TAIL));
+    path
+}
+
+#[test]
+fn delay_is_recovered_from_a_delayed_echo() {
+    let render = white_noise_i16(8 * MAX_LAG, 8000.0, 0xb0d1_0001);
+    let capture = echo(&render, &delayed_path());
+    let delay = estimate_bulk_delay(&render, &capture, MAX_LAG).expect("delay should be found");
+    assert!(
+        (DELAY..DELAY + SLACK).contains(&delay),
+        "estimated {delay} for a {DELAY}-sample delay"
+    );
+}
+
+#[test]
+fn uncorrelated_capture_gives_none() {
+    let render = white_noise_i16(8 * MAX_LAG, 8000.0, 0xb0d1_0002);
+    let capture = white_noise_i16(8 * MAX_LAG, 8000.0, 0xb0d1_0003);
+    assert_eq!(estimate_bulk_delay(&render, &capture, MAX_LAG), None);
+}
+
+#[test]
+fn silent_render_gives_none() {
+    let render = vec![0; 8 * MAX_LAG];
+    let capture = white_noise_i16(8 * MAX_LAG, 8000.0, 0xb0d1_0004);
+    assert_eq!(estimate_bulk_delay(&render, &capture, MAX_LAG), None);
+}
+
+#[test]
+fn lag_beyond_the_signal_gives_none() {
+    let render = white_noise_i16(MAX_LAG, 8000.0, 0xb0d1_0005);
+    let capture = echo(&render, &delayed_path());
+    assert_eq!(estimate_bulk_delay(&render, &capture, MAX_LAG), None);
+}
+
+#[test]
+fn short_canceller_converges_behind_the_estimated_delay() {
+    let path = delayed_path();
+    let render = white_noise_i16(80 * BLOCK, 8000.0, 0xb0d1_0006);
+    let capture = echo(&render, &path);
+    let delay = estimate_bulk_delay(&render[..8 * MAX_LAG], &capture[..8 * MAX_LAG], MAX_LAG)
+        .expect("delay should be found");
+
+    // Back off so the start of the tail stays covered whichever of its
+    // loudest taps the estimate picked.
+    let mut aligned = NlmsCanceller::new(TAPS, 0.5);
+    aligned.set_bulk_delay(delay.saturating_sub(SLACK));
+    let (capture_energy, aligned_energy) =
+        final_block_energies(&mut aligned, &render, &capture, BLOCK);
+    assert!(
+        aligned_energy < 1e-3 * capture_energy,
+        "aligned canceller left {aligned_energy} of {capture_energy}"
+    );
+
+    let mut unaligned = NlmsCanceller::new(TAPS, 0.5);
+    let (_, unaligned_energy) = final_block_energies(&mut unaligned, &render, &capture, BLOCK);
+    assert!(
+        unaligned_energy > 0.5 * capture_energy,
+        "taps short of the delay still removed the echo"
+    );
+}
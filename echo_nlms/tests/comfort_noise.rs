@@ -0,0 +1,65 @@
+//! Comfort noise level matching.
+
+use echo_nlms::ComfortNoise;
+use echo_nlms::test_util::{colored_noise, energy, to_i16, white_noise_i16};
+
+const BLOCK: usize = 256;
+const BLOCKS: usize = 100;
+
+fn rms(samples: &[i16]) -> f64 {
+    (energy(samples) / samples.len() as f64).sqrt()
+}
+
+/// Feeds `background` to a fresh generator, then fills silence at full
+/// level and returns the tracked background RMS and the fill RMS.
+fn fill_levels(background: &[i16]) -> (f64, f64) {
+    let mut comfort = ComfortNoise::new();
+    for block in background.chunks(BLOCK) {
+        comfort.update(block);
+    }
+
+    let mut fill = vec![0; BLOCKS * BLOCK];
+    for block in fill.chunks_mut(BLOCK) {
+        comfort.fill(block, 1.0);
+    }
+    (f64::from(comfort.noise_level()), rms(&fill))
+}
+
+#[test]
+fn full_level_matches_a_white_background() {
+    let background = white_noise_i16(BLOCKS * BLOCK, 300.0, 0xc0f0_0001);
+    let (tracked, fill_rms) = fill_levels(&background);
+    assert!(
+        (fill_rms / tracked - 1.0).abs() < 0.05,
+        "filled {fill_rms} RMS for a tracked {tracked} RMS"
+    );
+    let background_rms = rms(&background);
+    assert!(
+        (tracked / background_rms - 1.0).abs() < 0.15,
+        "tracked {tracked} RMS of a {background_rms} RMS background"
+    );
+}
+
+#[test]
+fn full_level_matches_a_colored_background() {
+    let background = to_i16(&colored_noise(BLOCKS * BLOCK, 100.0, &[0.8], 0xc0f0_0002));
+    let (tracked, fill_rms) = fill_levels(&background);
+    assert!(
+        (fill_rms / tracked - 1.0).abs() < 0.1,
+        "filled {fill_rms} RMS for a tracked {tracked} RMS"
+    );
+}
+
+#[test]
+fn zero_level_leaves_the_output_untouched() {
+    let mut comfort = ComfortNoise::new();
+    for block in white_noise_i16(BLOCKS * BLOCK, 300.0, 0xc0f0_0003).chunks(BLOCK) {
+        comfort.update(block);
+    }
+    assert!(comfort.noise_level() > 0.0);
+
+    let speech = white_noise_i16(BLOCK, 5000.0, 0xc0f0_0004);
+    let mut out = speech.clone();
+    comfort.fill(&mut out, 0.0);
+    assert_eq!(out, speech);
+}
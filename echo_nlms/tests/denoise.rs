@@ -0,0 +1,70 @@
+//! Spectral subtraction on stationary noise with an intermittent tone.
+
+use echo_nlms::SpectralDenoiser;
+use echo_nlms::test_util::{sine, to_i16, white_noise};
+
+const FRAME: usize = 512;
+const SAMPLE_RATE: u32 = 48_000;
+
+/// Uniform white noise with an RMS of `rms`.
+fn noise(len: usize, rms: f32, seed: u32) -> Vec<f32> {
+    white_noise(len, rms * 3.0f32.sqrt(), seed)
+}
+
+fn rms(samples: &[i16]) -> f32 {
+    (samples.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn without_subtraction_the_input_is_reconstructed() {
+    let input = to_i16(&noise(8 * FRAME, 3000.0, 7));
+    let mut denoiser = SpectralDenoiser::new(FRAME, 0.0, 1.0);
+    let mut output = input.clone();
+    // Uneven blocks exercise the internal buffering.
+    for block in output.chunks_mut(300) {
+        denoiser.process(block);
+    }
+
+    let latency = denoiser.latency();
+    for (out, inp) in output[latency..].iter().zip(&input) {
+        assert!((out - inp).abs() <= 1, "{out} vs {inp}");
+    }
+}
+
+#[test]
+fn stationary_noise_is_reduced_and_tone_kept() {
+    let len = 200 * FRAME;
+    let background = noise(len, 300.0, 0x1234_5678);
+    let tone_start = len / 2;
+    let mut input = background;
+    for (sample, tone) in
+        input[tone_start..]
+            .iter_mut()
+            .zip(sine(len - tone_start, 440.0, SAMPLE_RATE, 6000.0))
+    {
+        *sample += tone;
+    }
+    let input = to_i16(&input);
+
+    let mut denoiser = SpectralDenoiser::new(FRAME, 2.0, 0.05);
+    let mut output = input.clone();
+    for block in output.chunks_mut(FRAME) {
+        denoiser.process(block);
+    }
+    let latency = denoiser.latency();
+    assert!((250.0..350.0).contains(&denoiser.noise_level()));
+
+    // Skip the first frames while the noise estimate settles.
+    let noise_in = rms(&input[tone_start / 2..tone_start]);
+    let noise_out = rms(&output[tone_start / 2 + latency..tone_start + latency]);
+    let reduction_db = 20.0 * (noise_in / noise_out).log10();
+    assert!(reduction_db > 12.0, "noise reduced by {reduction_db:.1} dB");
+
+    let tone_in = rms(&input[tone_start + 4 * FRAME..len - latency]);
+    let tone_out = rms(&output[tone_start + 4 * FRAME + latency..]);
+    let change_db = 20.0 * (tone_out / tone_in).log10();
+    assert!(
+        change_db.abs() < 1.0,
+        "tone level changed by {change_db:.1} dB"
+    );
+}
@@ -0,0 +1,59 @@
+//! TPDF dither on the residual's conversion to `i16`.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{NOISE_PEAK, white_noise_i16};
+
+const TAPS: usize = 32;
+const BLOCK: usize = 256;
+
+#[test]
+fn dither_stays_within_one_lsb() {
+    let render = white_noise_i16(16 * BLOCK, NOISE_PEAK, 0x1234_5678);
+    let capture: Vec<i16> = render
+        .iter()
+        .zip(white_noise_i16(render.len(), NOISE_PEAK, 0x8765_4321))
+        .map(|(&echo, near)| echo / 3 + near / 8)
+        .collect();
+
+    let mut plain = NlmsCanceller::new(TAPS, 0.5);
+    let mut dithered = NlmsCanceller::new(TAPS, 0.5);
+    dithered.set_dither(true);
+    let mut expected = [0i16; BLOCK];
+    let mut output = [0i16; BLOCK];
+    let mut differing = 0;
+    for (render, capture) in render.chunks(BLOCK).zip(capture.chunks(BLOCK)) {
+        plain.process_block(render, capture, &mut expected, true);
+        dithered.process_block(render, capture, &mut output, true);
+        for (&a, &b) in expected.iter().zip(&output) {
+            // Truncation and dithered rounding each land within one LSB of
+            // the exact residual.
+            assert!((a as i32 - b as i32).abs() <= 2, "{a} vs {b}");
+            differing += usize::from(a != b);
+        }
+    }
+    assert!(differing > 0, "dither had no effect");
+}
+
+#[test]
+fn dither_of_silence_is_a_zero_mean_floor() {
+    let silence = [0i16; BLOCK];
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    canceller.set_dither(true);
+    let mut output = [0i16; BLOCK];
+    let mut sum = 0i64;
+    let mut nonzero = 0;
+    for _ in 0..64 {
+        canceller.process_block(&silence, &silence, &mut output, true);
+        for &sample in &output {
+            assert!(sample.abs() <= 1, "dither exceeded one LSB: {sample}");
+            sum += sample as i64;
+            nonzero += usize::from(sample != 0);
+        }
+    }
+    let count = 64 * BLOCK;
+    assert!(nonzero > count / 8, "too few dithered samples: {nonzero}");
+    assert!(
+        (sum as f64 / count as f64).abs() < 0.05,
+        "mean {sum}/{count}"
+    );
+}
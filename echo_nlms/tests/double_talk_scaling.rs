@@ -0,0 +1,90 @@
+//! Smooth step size reduction during double-talk.
+
+use echo_nlms::test_util::{NOISE_PEAK, SHORT_ECHO_PATH, cancel, echo, energy, white_noise_i16};
+use echo_nlms::{DoubleTalkScaling, NlmsCanceller};
+
+const TAPS: usize = 32;
+const BLOCK: usize = 256;
+
+#[test]
+fn scale_falls_smoothly_from_onset_to_cutoff() {
+    let scaling = DoubleTalkScaling::new(1.0, 3.0);
+    assert_eq!(scaling.scale(0.0), 1.0);
+    assert_eq!(scaling.scale(1.0), 1.0);
+    assert_eq!(scaling.scale(2.0), 0.5);
+    assert_eq!(scaling.scale(3.0), 0.0);
+    assert_eq!(scaling.scale(10.0), 0.0);
+    assert_eq!(scaling.scale(f32::NAN), 0.0);
+    let samples: Vec<f32> = (0..=40).map(|i| scaling.scale(i as f32 * 0.1)).collect();
+    assert!(samples.windows(2).all(|pair| pair[1] <= pair[0]));
+}
+
+#[test]
+#[should_panic(expected = "cutoff must be finite and greater than onset")]
+fn rejects_cutoff_below_onset() {
+    DoubleTalkScaling::new(2.0, 1.0);
+}
+
+#[test]
+fn echo_alone_keeps_the_full_step_size() {
+    let render = white_noise_i16(16 * BLOCK, NOISE_PEAK, 0x1234_5678);
+    let capture = echo(&render, &SHORT_ECHO_PATH);
+
+    let mut plain = NlmsCanceller::new(TAPS, 0.5);
+    let mut scaled = NlmsCanceller::new(TAPS, 0.5);
+    scaled.set_double_talk_scaling(Some(DoubleTalkScaling::new(1.0, 2.5)));
+
+    assert_eq!(
+        cancel(&mut plain, &render, &capture, BLOCK),
+        cancel(&mut scaled, &render, &capture, BLOCK)
+    );
+    assert_eq!(scaled.effective_step_size(), 0.5);
+}
+
+#[test]
+fn loud_near_end_slows_adaptation() {
+    let render = white_noise_i16(32 * BLOCK, NOISE_PEAK, 0x1234_5678);
+    let mut capture = echo(&render, &SHORT_ECHO_PATH);
+    let split = 16 * BLOCK;
+    for (sample, near) in capture[split..].iter_mut().zip(white_noise_i16(
+        render.len() - split,
+        NOISE_PEAK,
+        0x8765_4321,
+    )) {
+        *sample = sample.saturating_add(near.saturating_mul(4));
+    }
+
+    let mut plain = NlmsCanceller::new(TAPS, 0.5);
+    let mut scaled = NlmsCanceller::new(TAPS, 0.5);
+    scaled.set_double_talk_scaling(Some(DoubleTalkScaling::new(1.0, 2.5)));
+    cancel(&mut plain, &render, &capture, BLOCK);
+    cancel(&mut scaled, &render, &capture, BLOCK);
+    assert!(scaled.effective_step_size() < 0.5);
+
+    // Measure how much echo each filter still removes once the near end is
+    // silent again.
+    let probe = white_noise_i16(4 * BLOCK, NOISE_PEAK, 0x0bad_cafe);
+    let probe_echo = echo(&probe, &SHORT_ECHO_PATH);
+    let mut output = vec![0i16; BLOCK];
+    let mut residual = |canceller: &mut NlmsCanceller| {
+        (0..probe.len())
+            .step_by(BLOCK)
+            .map(|start| {
+                let end = start + BLOCK;
+                canceller.process_block(
+                    &probe[start..end],
+                    &probe_echo[start..end],
+                    &mut output,
+                    false,
+                );
+                energy(&output)
+            })
+            .sum::<f64>()
+    };
+    let plain_residual = residual(&mut plain);
+    let scaled_residual = residual(&mut scaled);
+    assert!(
+        scaled_residual * 10.0 < plain_residual,
+        "scaled {scaled_residual}, plain {plain_residual}"
+    );
+}
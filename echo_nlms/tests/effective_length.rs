@@ -0,0 +1,59 @@
+//! Effective-length detection and trimming on a short echo tail.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{echo, final_block_energies, white_noise_i16};
+
+const TAPS: usize = 512;
+const ECHO_LEN: usize = 48;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 150;
+
+/// An echo path that decays to nothing well within `ECHO_LEN` taps.
+fn echo_path() -> Vec<f32> {
+    (0..ECHO_LEN)
+        .map(|idx| {
+            let t = idx as f32;
+            0.6 * (-t / 6.0).exp() * if idx % 2 == 0 { 1.0 } else { -0.7 }
+        })
+        .collect()
+}
+
+/// Drives the canceller with white noise through `path`, returning the
+/// capture and residual energies of the final block.
+fn run(canceller: &mut NlmsCanceller, path: &[f32]) -> (f64, f64) {
+    let render = white_noise_i16(BLOCKS * BLOCK, 8192.0, 0x2545_f491);
+    let capture = echo(&render, path);
+    final_block_energies(canceller, &render, &capture, BLOCK)
+}
+
+#[test]
+fn untrained_filter_has_no_effective_length() {
+    let canceller = NlmsCanceller::new(TAPS, 0.5);
+    assert_eq!(canceller.effective_length(), 0);
+    assert_eq!(canceller.active_length(), TAPS);
+}
+
+#[test]
+fn effective_length_covers_short_echo() {
+    let path = echo_path();
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    run(&mut canceller, &path);
+
+    // The path falls below 1% of its peak after about 28 taps.
+    let effective = canceller.effective_length();
+    assert!((20..=ECHO_LEN).contains(&effective), "{effective}");
+}
+
+#[test]
+fn trimmed_filter_still_cancels() {
+    let path = echo_path();
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    canceller.set_trim(true);
+    let (capture, residual) = run(&mut canceller, &path);
+
+    assert!(canceller.active_length() < TAPS / 4);
+    assert!(
+        residual < capture * 1e-3,
+        "residual {residual} vs capture {capture}"
+    );
+}
@@ -0,0 +1,53 @@
+//! The running render energy against the exact sum of squares.
+
+use std::sync::{Arc, Mutex};
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{cancel, energy, white_noise_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 2048;
+
+/// Runs loud, steady white noise through a canceller refreshing its energy
+/// every `interval` samples and returns the worst relative error of the
+/// render energy it reports against the exact sum of squares over the
+/// filter window.
+fn worst_energy_error(interval: usize) -> f64 {
+    let render = white_noise_i16(BLOCKS * BLOCK, 20_000.0, 0xe4e7_0001);
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    canceller.set_energy_refresh_interval(interval);
+    let log = Arc::clone(&reported);
+    canceller.set_step_size_schedule(move |stats| {
+        log.lock().unwrap().push(stats.render_energy);
+        0.5
+    });
+    cancel(&mut canceller, &render, &vec![0; render.len()], BLOCK);
+
+    let reported = reported.lock().unwrap();
+    assert_eq!(reported.len(), BLOCKS);
+    reported
+        .iter()
+        .enumerate()
+        .map(|(block, &reported)| {
+            let end = (block + 1) * BLOCK;
+            let exact = energy(&render[end - TAPS..end]);
+            (f64::from(reported) - exact).abs() / exact
+        })
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn running_energy_stays_on_the_sum_of_squares() {
+    let error = worst_energy_error(TAPS);
+    assert!(error < 1e-6, "relative error {error:e}");
+}
+
+#[test]
+fn without_refresh_the_running_energy_drifts() {
+    // Guards the test above against a signal too tame to drift at all.
+    let error = worst_energy_error(usize::MAX);
+    assert!(error > 1e-5, "relative error {error:e}");
+}
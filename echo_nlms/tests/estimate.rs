@@ -0,0 +1,68 @@
+//! Echo estimate output alongside the residual.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{NOISE_PEAK, SHORT_ECHO_PATH, echo, energy, white_noise_i16};
+
+const TAPS: usize = 32;
+const BLOCK: usize = 256;
+
+#[test]
+fn estimate_tracks_the_true_echo() {
+    let render = white_noise_i16(16 * BLOCK, NOISE_PEAK, 0x1234_5678);
+    let true_echo = echo(&render, &SHORT_ECHO_PATH);
+    let near = white_noise_i16(render.len(), NOISE_PEAK, 0x8765_4321);
+    let capture: Vec<i16> = true_echo
+        .iter()
+        .zip(&near)
+        .map(|(&echo, &near)| echo + near / 16)
+        .collect();
+
+    let mut plain = NlmsCanceller::new(TAPS, 0.5);
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    let mut expected = [0i16; BLOCK];
+    let mut residual = [0i16; BLOCK];
+    let mut estimate = [0i16; BLOCK];
+    let mut last_error = 0.0;
+    for start in (0..render.len()).step_by(BLOCK) {
+        let range = start..start + BLOCK;
+        plain.process_block(
+            &render[range.clone()],
+            &capture[range.clone()],
+            &mut expected,
+            true,
+        );
+        canceller.process_block_with_estimate(
+            &render[range.clone()],
+            &capture[range.clone()],
+            &mut residual,
+            &mut estimate,
+            true,
+        );
+        // The residual is unchanged by also asking for the estimate.
+        assert_eq!(residual, expected);
+        for ((&residual, &estimate), &capture) in
+            residual.iter().zip(&estimate).zip(&capture[range.clone()])
+        {
+            assert!((capture as i32 - estimate as i32 - residual as i32).abs() <= 1);
+        }
+        last_error = estimate
+            .iter()
+            .zip(&true_echo[range])
+            .map(|(&estimate, &echo)| (estimate as f64 - echo as f64).powi(2))
+            .sum::<f64>()
+            / BLOCK as f64;
+    }
+
+    let echo_power = energy(&true_echo) / true_echo.len() as f64;
+    assert!(last_error < echo_power / 100.0);
+}
+
+#[test]
+#[should_panic(expected = "estimate buffer length must match capture chunk")]
+fn rejects_short_estimate_buffer() {
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    let block = [0i16; BLOCK];
+    let mut residual = [0i16; BLOCK];
+    let mut estimate = [0i16; BLOCK - 1];
+    canceller.process_block_with_estimate(&block, &block, &mut residual, &mut estimate, true);
+}
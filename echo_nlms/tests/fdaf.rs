@@ -0,0 +1,43 @@
+//! The frequency-domain canceller against the time-domain NLMS reference.
+
+use echo_nlms::test_util::{echo, echo_path, energy, final_block_energies, white_noise_i16};
+use echo_nlms::{FdafCanceller, NlmsCanceller};
+
+const TAPS: usize = 64;
+const PARTITION: usize = 32;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 200;
+
+/// Power ratio in dB.
+fn db(numerator: f64, denominator: f64) -> f64 {
+    10.0 * (numerator / denominator).log10()
+}
+
+#[test]
+fn residual_tracks_nlms_on_the_same_echo_path() {
+    let render = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x00fd_af01);
+    // Quiet near-end noise keeps the residual floor finite for both.
+    let near = white_noise_i16(render.len(), 100.0, 0x10fd_af02);
+    let capture: Vec<i16> = echo(&render, &echo_path(TAPS))
+        .iter()
+        .zip(&near)
+        .map(|(&echo, &near)| echo + near)
+        .collect();
+
+    let (capture_energy, nlms) =
+        final_block_energies(&mut NlmsCanceller::new(TAPS, 0.5), &render, &capture, BLOCK);
+    let mut fdaf = FdafCanceller::new(TAPS, PARTITION, 0.5);
+    let (_, fdaf) = final_block_energies(&mut fdaf, &render, &capture, BLOCK);
+
+    // Both converge to within a few dB of the near-end floor...
+    let floor = db(capture_energy, energy(&near[near.len() - BLOCK..]));
+    for (name, residual) in [("nlms", nlms), ("fdaf", fdaf)] {
+        let erle = db(capture_energy, residual);
+        assert!(
+            erle > floor - 3.0,
+            "{name}: {erle:.1} dB, floor {floor:.1} dB"
+        );
+    }
+    // ...and leave residuals of matching energy.
+    assert!(db(fdaf, nlms).abs() < 1.0, "fdaf {fdaf} vs nlms {nlms}");
+}
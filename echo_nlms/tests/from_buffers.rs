@@ -0,0 +1,39 @@
+//! Cancellers built around caller-provided buffers.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{SHORT_ECHO_PATH, echo, white_noise_i16};
+
+const TAPS: usize = 48;
+const BLOCK: usize = 128;
+
+#[test]
+fn matches_an_allocating_canceller() {
+    let render = white_noise_i16(16 * BLOCK, 4096.0, 0x0bad_cafe);
+    let capture = echo(&render, &SHORT_ECHO_PATH);
+
+    let mut allocating = NlmsCanceller::new(TAPS, 0.5);
+    // Stale contents must not leak into the new filter.
+    let mut borrowed = NlmsCanceller::from_buffers(vec![1.0; TAPS], vec![-3.0; 2 * TAPS], 0.5);
+    assert_eq!(borrowed.tap_len(), TAPS);
+    assert!(borrowed.coefficients().iter().all(|&w| w == 0.0));
+
+    let mut expected = [0i16; BLOCK];
+    let mut output = [0i16; BLOCK];
+    for (render, capture) in render.chunks(BLOCK).zip(capture.chunks(BLOCK)) {
+        allocating.process_block(render, capture, &mut expected, true);
+        borrowed.process_block(render, capture, &mut output, true);
+        assert_eq!(expected, output);
+    }
+}
+
+#[test]
+#[should_panic(expected = "history must be twice as long as taps")]
+fn rejects_a_history_of_the_tap_length() {
+    NlmsCanceller::from_buffers(vec![0.0; TAPS], vec![0.0; TAPS], 0.5);
+}
+
+#[test]
+#[should_panic(expected = "history must be twice as long as taps")]
+fn rejects_mismatched_lengths() {
+    NlmsCanceller::from_buffers(vec![0.0; TAPS], vec![0.0; 2 * TAPS + 1], 0.5);
+}
@@ -0,0 +1,103 @@
+//! Geigel detector triggering, hangover and reset.
+
+use echo_nlms::GeigelDetector;
+use echo_nlms::test_util::{echo, white_noise_i16};
+
+const WINDOW: usize = 64;
+const THRESHOLD: f32 = 0.5;
+const HOLD_BLOCKS: usize = 2;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 20;
+
+/// A short path that loses 10 dB, comfortably under the threshold.
+fn quiet_path() -> Vec<f32> {
+    let mut path = vec![0.0; 16];
+    path[10] = 0.3;
+    path
+}
+
+fn blocks(samples: &[i16]) -> impl Iterator<Item = &[i16]> {
+    samples.chunks_exact(BLOCK)
+}
+
+#[test]
+fn echo_alone_does_not_trigger() {
+    let render = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x6e16_0001);
+    let capture = echo(&render, &quiet_path());
+
+    let mut detector = GeigelDetector::new(WINDOW, THRESHOLD, HOLD_BLOCKS);
+    for (idx, (r, c)) in blocks(&render).zip(blocks(&capture)).enumerate() {
+        assert!(
+            !detector.is_double_talk(r, c),
+            "echo alone triggered in block {idx}"
+        );
+    }
+}
+
+#[test]
+fn near_end_above_threshold_triggers() {
+    let render = white_noise_i16(BLOCK, 8000.0, 0x6e16_0002);
+    let render_peak = render.iter().map(|s| s.unsigned_abs()).max().unwrap() as f32;
+    let mut capture = echo(&render, &quiet_path());
+    capture[BLOCK / 2] = (1.2 * THRESHOLD * render_peak) as i16;
+
+    let mut detector = GeigelDetector::new(WINDOW, THRESHOLD, HOLD_BLOCKS);
+    assert!(detector.is_double_talk(&render, &capture));
+}
+
+#[test]
+fn near_end_below_threshold_does_not_trigger() {
+    let render = vec![8000; BLOCK];
+    let capture = vec![(0.9 * THRESHOLD * 8000.0) as i16; BLOCK];
+
+    let mut detector = GeigelDetector::new(WINDOW, THRESHOLD, HOLD_BLOCKS);
+    assert!(!detector.is_double_talk(&render, &capture));
+}
+
+#[test]
+fn hangover_counts_down_over_hold_blocks() {
+    let render = vec![8000; BLOCK];
+    let loud = vec![8000; BLOCK];
+    let quiet = vec![1000; BLOCK];
+
+    let mut detector = GeigelDetector::new(WINDOW, THRESHOLD, HOLD_BLOCKS);
+    assert!(detector.is_double_talk(&render, &loud));
+    for held in 0..HOLD_BLOCKS {
+        assert!(
+            detector.is_double_talk(&render, &quiet),
+            "hangover ended after {held} blocks"
+        );
+    }
+    assert!(!detector.is_double_talk(&render, &quiet));
+
+    // A fresh trigger during the hangover restarts it.
+    assert!(detector.is_double_talk(&render, &loud));
+    assert!(detector.is_double_talk(&render, &quiet));
+    assert!(detector.is_double_talk(&render, &loud));
+    for _ in 0..HOLD_BLOCKS {
+        assert!(detector.is_double_talk(&render, &quiet));
+    }
+    assert!(!detector.is_double_talk(&render, &quiet));
+}
+
+#[test]
+fn reset_clears_hangover_and_render_window() {
+    let render = vec![8000; BLOCK];
+    let loud = vec![8000; BLOCK];
+    let quiet = vec![1000; BLOCK];
+
+    let mut detector = GeigelDetector::new(WINDOW, THRESHOLD, HOLD_BLOCKS);
+    assert!(detector.is_double_talk(&render, &loud));
+    detector.reset();
+    assert!(!detector.is_double_talk(&render, &quiet));
+
+    // Within the window the old render peak would explain a quiet capture
+    // over silent render; after a reset it no longer does.
+    let silence = vec![0; WINDOW / 2];
+    let short_quiet = vec![1000; WINDOW / 2];
+    detector.is_double_talk(&render, &quiet);
+    assert!(!detector.is_double_talk(&silence, &short_quiet));
+    detector.is_double_talk(&render, &quiet);
+    detector.reset();
+    assert!(detector.is_double_talk(&silence, &short_quiet));
+}
@@ -0,0 +1,61 @@
+//! The vectorized filter kernels against their scalar fallbacks.
+
+use echo_nlms::test_util::kernels::{self, scalar};
+use echo_nlms::test_util::white_noise;
+
+/// Lengths around the four-lane vector width, with and without a tail.
+const LENGTHS: [usize; 12] = [0, 1, 2, 3, 4, 5, 7, 9, 31, 33, 127, 1021];
+
+#[test]
+fn dot_product_matches_scalar() {
+    for len in LENGTHS {
+        let taps = white_noise(len, 0.5, 0x1234_5678);
+        let history = white_noise(len, 8000.0, 0x8765_4321);
+        let simd = kernels::dot_rev(&taps, &history);
+        let reference = scalar::dot_rev(&taps, &history);
+        // Only the summation order differs, so allow a few rounding steps of
+        // the largest partial sum.
+        let magnitude: f32 = taps.iter().zip(&history).map(|(w, h)| (w * h).abs()).sum();
+        assert!(
+            (simd - reference).abs() <= magnitude * 1e-6,
+            "{len}: {simd} vs {reference}"
+        );
+    }
+}
+
+#[test]
+fn tap_update_matches_scalar_exactly() {
+    for len in LENGTHS {
+        let taps = white_noise(len, 0.5, 0x0bad_cafe);
+        let history = white_noise(len, 8000.0, 0xdead_beef);
+        let mut simd = taps.clone();
+        let mut reference = taps;
+        kernels::leaky_axpy_rev(&mut simd, &history, 0.999, 1.5e-6);
+        scalar::leaky_axpy_rev(&mut reference, &history, 0.999, 1.5e-6);
+        // Each tap goes through the same two products and one sum.
+        assert_eq!(simd, reference, "{len}");
+    }
+}
+
+#[test]
+fn history_is_read_newest_first() {
+    let taps = [1.0, 10.0, 100.0];
+    let history = [3.0, 2.0, 1.0];
+    assert_eq!(kernels::dot_rev(&taps, &history), 321.0);
+
+    let mut updated = [0.0; 3];
+    kernels::leaky_sign_axpy_rev(&mut updated, &[-5.0, 0.0, 5.0], 1.0, 0.5);
+    assert_eq!(updated, [0.5, 0.0, -0.5]);
+}
+
+#[test]
+#[should_panic(expected = "taps and history must have the same length")]
+fn rejects_a_short_history() {
+    kernels::dot_rev(&[0.0; 8], &[0.0; 4]);
+}
+
+#[test]
+#[should_panic(expected = "taps and history must have the same length")]
+fn rejects_a_short_history_when_updating() {
+    kernels::leaky_axpy_rev(&mut [0.0; 8], &[0.0; 4], 1.0, 1.0);
+}
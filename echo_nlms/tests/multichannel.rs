@@ -0,0 +1,167 @@
+//! Stereo echo cancellation with per-channel echo paths.
+
+use echo_nlms::test_util::{echo, echo_path, energy, misalignment, white_noise_i16};
+use echo_nlms::{MultiChannelCanceller, RenderReference};
+
+const CHANNELS: usize = 2;
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 60;
+const MU: f32 = 0.5;
+
+/// Returns the echo path heard by mic `channel`: the shared test path for
+/// the left mic, a quieter and inverted copy two samples later for the right.
+fn mic_path(channel: usize) -> Vec<f32> {
+    let path = echo_path(TAPS);
+    if channel == 0 {
+        return path;
+    }
+    let mut shifted = vec![0.0; TAPS];
+    for (dst, &src) in shifted[2..].iter_mut().zip(&path) {
+        *dst = -0.6 * src;
+    }
+    shifted
+}
+
+fn interleave(channels: &[Vec<i16>]) -> Vec<i16> {
+    (0..channels[0].len())
+        .flat_map(|frame| channels.iter().map(move |channel| channel[frame]))
+        .collect()
+}
+
+fn deinterleave(samples: &[i16], channel: usize) -> Vec<i16> {
+    samples
+        .iter()
+        .skip(channel)
+        .step_by(CHANNELS)
+        .copied()
+        .collect()
+}
+
+/// Runs `canceller` over interleaved `render` and `capture` and returns the
+/// interleaved residual.
+fn run(canceller: &mut MultiChannelCanceller, render: &[i16], capture: &[i16]) -> Vec<i16> {
+    let mut output = vec![0; capture.len()];
+    for ((render, capture), output) in render
+        .chunks(BLOCK * CHANNELS)
+        .zip(capture.chunks(BLOCK * CHANNELS))
+        .zip(output.chunks_mut(BLOCK * CHANNELS))
+    {
+        canceller.process_block(render, capture, output, true);
+    }
+    output
+}
+
+/// Builds a stereo capture where each mic hears `reference` through its own
+/// path, and only the left mic also picks up near-end noise.
+fn stereo_capture(reference: &[i16], near_end: &[i16]) -> Vec<i16> {
+    let left: Vec<i16> = echo(reference, &mic_path(0))
+        .iter()
+        .zip(near_end)
+        .map(|(&e, &n)| e.saturating_add(n))
+        .collect();
+    let right = echo(reference, &mic_path(1));
+    interleave(&[left, right])
+}
+
+/// Checks that both filters found their own path and that each residual
+/// landed in its own slot: the left one holds the near-end noise, the right
+/// one next to nothing.
+fn assert_converged(canceller: &MultiChannelCanceller, output: &[i16], near_end: &[i16]) {
+    for channel in 0..CHANNELS {
+        let error = misalignment(
+            canceller.channel(channel).coefficients(),
+            &mic_path(channel),
+        );
+        assert!(error < 1e-2, "channel {channel} misalignment {error}");
+    }
+
+    let tail = (BLOCKS - 1) * BLOCK..;
+    let left = deinterleave(output, 0);
+    let right = deinterleave(output, 1);
+    let near_end_energy = energy(&near_end[tail.clone()]);
+    let left_energy = energy(&left[tail.clone()]);
+    assert!(
+        (0.5..2.0).contains(&(left_energy / near_end_energy)),
+        "left residual {left_energy} against near-end {near_end_energy}"
+    );
+    assert!(
+        energy(&right[tail]) < 1e-2 * near_end_energy,
+        "right residual holds the near-end signal"
+    );
+}
+
+#[test]
+fn mix_reference_cancels_each_mic_path() {
+    let left = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x5e12_0001);
+    let right = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x5e12_0002);
+    let mix: Vec<i16> = left
+        .iter()
+        .zip(&right)
+        .map(|(&l, &r)| ((i32::from(l) + i32::from(r)) / 2) as i16)
+        .collect();
+    let near_end = white_noise_i16(BLOCKS * BLOCK, 500.0, 0x5e12_0003);
+    let render = interleave(&[left, right]);
+    let capture = stereo_capture(&mix, &near_end);
+
+    let mut canceller = MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU);
+    let output = run(&mut canceller, &render, &capture);
+    assert_converged(&canceller, &output, &near_end);
+}
+
+#[test]
+fn channel_reference_follows_one_render_channel() {
+    let left = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x5e12_0004);
+    let right = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x5e12_0005);
+    let near_end = white_noise_i16(BLOCKS * BLOCK, 500.0, 0x5e12_0006);
+    let capture = stereo_capture(&right, &near_end);
+    let render = interleave(&[left, right]);
+
+    let mut canceller = MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU);
+    canceller.set_reference(RenderReference::Channel(1));
+    let output = run(&mut canceller, &render, &capture);
+    assert_converged(&canceller, &output, &near_end);
+
+    // The mix only half explains the echo, so it cannot cancel it.
+    let mut mixed = MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU);
+    let output = run(&mut mixed, &render, &capture);
+    let right = deinterleave(&output, 1);
+    let tail = (BLOCKS - 1) * BLOCK..;
+    let echo_energy = energy(&deinterleave(&capture, 1)[tail.clone()]);
+    assert!(energy(&right[tail]) > 0.1 * echo_energy);
+}
+
+#[test]
+#[should_panic(expected = "render reference channel out of range")]
+fn reference_channel_must_exist() {
+    MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU)
+        .set_reference(RenderReference::Channel(2));
+}
+
+#[test]
+#[should_panic(expected = "render block must hold whole frames")]
+fn render_must_hold_whole_frames() {
+    let mut canceller = MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU);
+    canceller.process_block(&[0; 3], &[0; 4], &mut [0; 4], true);
+}
+
+#[test]
+#[should_panic(expected = "capture block must hold whole frames")]
+fn capture_must_hold_whole_frames() {
+    let mut canceller = MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU);
+    canceller.process_block(&[0; 4], &[0; 3], &mut [0; 3], true);
+}
+
+#[test]
+#[should_panic(expected = "render and capture chunks must match")]
+fn frame_counts_must_match() {
+    let mut canceller = MultiChannelCanceller::new(CHANNELS, 1, TAPS, MU);
+    canceller.process_block(&[0; 4], &[0; 4], &mut [0; 4], true);
+}
+
+#[test]
+#[should_panic(expected = "output buffer length must match capture chunk")]
+fn output_must_match_capture() {
+    let mut canceller = MultiChannelCanceller::new(CHANNELS, CHANNELS, TAPS, MU);
+    canceller.process_block(&[0; 4], &[0; 4], &mut [0; 2], true);
+}
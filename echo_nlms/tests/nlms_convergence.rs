@@ -0,0 +1,56 @@
+//! Convergence of `NlmsCanceller` on a synthetic echo path.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{echo, echo_path, final_block_energies, white_noise_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 200;
+
+/// Runs the canceller against `path` and returns the capture and residual
+/// energies of the final block.
+fn run(canceller: &mut NlmsCanceller, path: &[f32]) -> (f64, f64) {
+    let render = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x1234_5678);
+    let capture = echo(&render, path);
+    final_block_energies(canceller, &render, &capture, BLOCK)
+}
+
+#[test]
+fn residual_energy_drops_on_white_noise() {
+    let path = echo_path(TAPS);
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    let (capture, residual) = run(&mut canceller, &path);
+
+    // At least 30 dB of echo reduction; only rounding noise should remain.
+    assert!(
+        residual < capture * 1e-3,
+        "residual {residual} vs capture {capture}"
+    );
+}
+
+#[test]
+fn taps_approximate_the_echo_path() {
+    let path = echo_path(TAPS);
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    run(&mut canceller, &path);
+
+    for (idx, (&tap, &expected)) in canceller.coefficients().iter().zip(&path).enumerate() {
+        assert!(
+            (tap - expected).abs() < 0.01,
+            "tap {idx}: {tap} vs {expected}"
+        );
+    }
+}
+
+#[test]
+fn frozen_filter_passes_capture_through() {
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    let render = white_noise_i16(BLOCK, 8000.0, 42);
+    let capture = white_noise_i16(BLOCK, 8000.0, 43);
+    let mut residual = vec![0i16; BLOCK];
+
+    canceller.process_block(&render, &capture, &mut residual, false);
+
+    assert_eq!(residual, capture);
+    assert!(canceller.coefficients().iter().all(|&tap| tap == 0.0));
+}
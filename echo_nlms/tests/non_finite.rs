@@ -0,0 +1,39 @@
+//! Full-scale bursts must not poison the taps.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{cancel, echo, echo_path, final_block_energies, white_noise_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+
+#[test]
+fn i16_min_burst_leaves_the_taps_finite() {
+    let path = echo_path(TAPS);
+    let render = white_noise_i16(40 * BLOCK, 8000.0, 0x0b57_0001);
+    let capture = echo(&render, &path);
+
+    for proportionate in [false, true] {
+        let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+        canceller.set_proportionate(proportionate);
+        cancel(&mut canceller, &render, &capture, BLOCK);
+
+        // A glitch pins both signals to the negative rail, then the render
+        // drops out while the capture stays pinned.
+        let burst = [i16::MIN; 4 * BLOCK];
+        let silence = [0i16; 4 * BLOCK];
+        cancel(&mut canceller, &burst, &burst, BLOCK);
+        cancel(&mut canceller, &silence, &burst, BLOCK);
+        assert!(
+            canceller.coefficients().iter().all(|w| w.is_finite()),
+            "proportionate {proportionate}: {:?}",
+            canceller.coefficients()
+        );
+
+        // And the filter reconverges on the echo path afterwards.
+        let (capture, residual) = final_block_energies(&mut canceller, &render, &capture, BLOCK);
+        assert!(
+            residual < capture * 1e-3,
+            "proportionate {proportionate}: residual {residual} of {capture}"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Cancellation of an echo path with loudspeaker-like distortion.
+
+use echo_nlms::test_util::{cancel, energy, white_noise_i16};
+use echo_nlms::{EchoCanceller, NlmsCanceller, NonlinearCanceller};
+
+const TAPS: usize = 32;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 300;
+
+/// Loud white-noise render and its echo through a soft-clipping speaker
+/// followed by a short room response.
+fn signals() -> (Vec<i16>, Vec<i16>) {
+    let render = white_noise_i16(BLOCKS * BLOCK, 16_384.0, 0x0bad_cafe);
+    let speaker: Vec<f32> = render
+        .iter()
+        .map(|&s| {
+            let x = s as f32 / 32_768.0;
+            (x + 0.4 * x * x - 0.8 * x * x * x) * 32_768.0
+        })
+        .collect();
+    let room = [0.5, -0.3, 0.2, 0.1, -0.05];
+    let capture = (0..speaker.len())
+        .map(|idx| {
+            room.iter()
+                .enumerate()
+                .filter(|&(k, _)| k <= idx)
+                .map(|(k, &h)| speaker[idx - k] * h)
+                .sum::<f32>()
+                .round() as i16
+        })
+        .collect();
+    (render, capture)
+}
+
+/// Residual-to-capture energy over the last quarter, in dB.
+fn final_residual_db(canceller: &mut dyn EchoCanceller, render: &[i16], capture: &[i16]) -> f32 {
+    let residual = cancel(canceller, render, capture, BLOCK);
+    let tail = capture.len() * 3 / 4;
+    10.0 * (energy(&residual[tail..]) / energy(&capture[tail..])).log10() as f32
+}
+
+#[test]
+fn power_branches_cancel_distortion_linear_nlms_leaves() {
+    let (render, capture) = signals();
+
+    let mut linear = NlmsCanceller::new(TAPS, 0.5);
+    let linear_db = final_residual_db(&mut linear, &render, &capture);
+    let mut nonlinear = NonlinearCanceller::new(TAPS, 3, 0.5);
+    let nonlinear_db = final_residual_db(&mut nonlinear, &render, &capture);
+
+    assert!(
+        nonlinear_db < linear_db - 10.0,
+        "nonlinear {nonlinear_db:.1} dB vs linear {linear_db:.1} dB"
+    );
+    // The quadratic branch should have picked up the speaker's even-order
+    // term through the room response.
+    assert!((nonlinear.branch(2)[0] - 0.2).abs() < 0.05);
+}
+
+#[test]
+fn first_order_matches_linear_cancellation() {
+    let (render, capture) = signals();
+    let mut nonlinear = NonlinearCanceller::new(TAPS, 1, 0.5);
+    let db = final_residual_db(&mut nonlinear, &render, &capture);
+    let mut linear = NlmsCanceller::new(TAPS, 0.5);
+    let linear_db = final_residual_db(&mut linear, &render, &capture);
+    assert!(
+        (db - linear_db).abs() < 1.0,
+        "{db:.1} dB vs {linear_db:.1} dB"
+    );
+}
@@ -0,0 +1,51 @@
+//! Proportionate updates on a sparse echo path.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{cancel, echo, energy, white_noise_i16};
+
+const TAPS: usize = 512;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 40;
+const TARGET_ERLE_DB: f64 = 30.0;
+
+/// A long path that is silent apart from a few isolated reflections.
+fn sparse_path() -> Vec<f32> {
+    let mut path = vec![0.0; TAPS];
+    path[30] = 0.6;
+    path[31] = -0.2;
+    path[140] = 0.25;
+    path[310] = -0.1;
+    path
+}
+
+/// Returns the index of the first block whose echo return loss enhancement
+/// reaches [`TARGET_ERLE_DB`].
+fn blocks_to_converge(canceller: &mut NlmsCanceller) -> Option<usize> {
+    let render = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x5a45_0001);
+    let near = white_noise_i16(render.len(), 20.0, 0x5a45_0002);
+    let capture: Vec<i16> = echo(&render, &sparse_path())
+        .iter()
+        .zip(&near)
+        .map(|(&echo, &near)| echo + near)
+        .collect();
+    let residual = cancel(canceller, &render, &capture, BLOCK);
+    residual
+        .chunks(BLOCK)
+        .zip(capture.chunks(BLOCK))
+        .position(|(residual, capture)| {
+            10.0 * (energy(capture) / energy(residual)).log10() >= TARGET_ERLE_DB
+        })
+}
+
+#[test]
+fn proportionate_converges_faster_on_a_sparse_path() {
+    let mut proportionate = NlmsCanceller::new(TAPS, 0.5);
+    proportionate.set_proportionate(true);
+    let proportionate = blocks_to_converge(&mut proportionate).expect("PNLMS never converged");
+    let nlms =
+        blocks_to_converge(&mut NlmsCanceller::new(TAPS, 0.5)).expect("NLMS never converged");
+    assert!(
+        proportionate < nlms,
+        "PNLMS after {proportionate} blocks, NLMS after {nlms}"
+    );
+}
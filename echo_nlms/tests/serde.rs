@@ -0,0 +1,47 @@
+//! Serialized canceller state resumes exactly where it left off.
+#![cfg(feature = "serde")]
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{cancel, echo, echo_path, white_noise_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+
+#[test]
+fn round_trip_resumes_with_identical_output() {
+    let render = white_noise_i16(32 * BLOCK, 8000.0, 0x5e7d_e001);
+    let near = white_noise_i16(render.len(), 200.0, 0x5e7d_e002);
+    let capture: Vec<i16> = echo(&render, &echo_path(TAPS))
+        .iter()
+        .zip(&near)
+        .map(|(&echo, &near)| echo + near)
+        .collect();
+    let (warm_up, rest) = (16 * BLOCK, 16 * BLOCK..);
+
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    // Exercise the optional state as well as the taps and history.
+    canceller.set_bulk_delay(3);
+    canceller.set_whitening(2);
+    canceller.set_variable_step(true);
+    canceller.set_dither(true);
+    cancel(
+        &mut canceller,
+        &render[..warm_up],
+        &capture[..warm_up],
+        BLOCK,
+    );
+
+    let json = serde_json::to_string(&canceller).unwrap();
+    let mut restored: NlmsCanceller = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.coefficients(), canceller.coefficients());
+
+    let expected = cancel(
+        &mut canceller,
+        &render[rest.clone()],
+        &capture[rest.clone()],
+        BLOCK,
+    );
+    let output = cancel(&mut restored, &render[rest.clone()], &capture[rest], BLOCK);
+    assert_eq!(output, expected);
+    assert_eq!(restored.coefficients(), canceller.coefficients());
+}
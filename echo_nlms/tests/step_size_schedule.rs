@@ -0,0 +1,52 @@
+//! Step size scheduling through a per-block callback.
+
+use std::sync::{Arc, Mutex};
+
+use echo_nlms::test_util::{NOISE_PEAK, SHORT_ECHO_PATH, cancel, echo, white_noise_i16};
+use echo_nlms::{NlmsCanceller, NlmsStats};
+
+const TAPS: usize = 32;
+const BLOCK: usize = 256;
+
+#[test]
+fn constant_schedule_matches_fixed_step_size() {
+    let render = white_noise_i16(16 * BLOCK, NOISE_PEAK, 0x1234_5678);
+    let capture = echo(&render, &SHORT_ECHO_PATH);
+
+    let mut fixed = NlmsCanceller::new(TAPS, 0.3);
+    let mut scheduled = NlmsCanceller::new(TAPS, 0.3);
+    scheduled.set_step_size_schedule(|_| 0.3);
+
+    assert_eq!(
+        cancel(&mut fixed, &render, &capture, BLOCK),
+        cancel(&mut scheduled, &render, &capture, BLOCK)
+    );
+}
+
+#[test]
+fn schedule_sees_every_block_and_sets_the_step_size() {
+    let render = white_noise_i16(8 * BLOCK, NOISE_PEAK, 0x8765_4321);
+    let capture = echo(&render, &SHORT_ECHO_PATH);
+    let seen = Arc::new(Mutex::new(Vec::<NlmsStats>::new()));
+
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    let log = Arc::clone(&seen);
+    canceller.set_step_size_schedule(move |stats| {
+        log.lock().unwrap().push(*stats);
+        0.5 / (stats.block + 2) as f32
+    });
+    cancel(&mut canceller, &render, &capture, BLOCK);
+
+    let seen = seen.lock().unwrap();
+    let blocks: Vec<u64> = seen.iter().map(|stats| stats.block).collect();
+    assert_eq!(blocks, (0..8).collect::<Vec<_>>());
+    assert!(seen.iter().all(|stats| stats.render_energy > 0.0));
+    // The filter converges, so the residual shrinks from the first block on.
+    assert!(seen[7].error_rms < seen[0].error_rms);
+    assert_eq!(canceller.step_size(), 0.5 / 9.0);
+
+    canceller.clear_step_size_schedule();
+    cancel(&mut canceller, &render, &capture, BLOCK);
+    assert_eq!(canceller.step_size(), 0.5 / 9.0);
+    assert_eq!(seen.len(), 8);
+}
@@ -0,0 +1,92 @@
+//! Residual echo suppression gain.
+
+use echo_nlms::ResidualSuppressor;
+use echo_nlms::test_util::{energy, white_noise_i16};
+
+const BLOCK: usize = 256;
+const MIN_GAIN: f32 = 0.1;
+const OVERDRIVE: f32 = 1.5;
+
+/// Returns `samples` scaled by `gain`, rounded to `i16`.
+fn scaled(samples: &[i16], gain: f32) -> Vec<i16> {
+    samples.iter().map(|&s| (s as f32 * gain) as i16).collect()
+}
+
+#[test]
+fn quiet_render_leaves_the_residual_alone() {
+    // Peak 60 keeps the render power well under the silence floor.
+    let render = white_noise_i16(BLOCK, 60.0, 0x5b9e_0001);
+    let near_end = white_noise_i16(BLOCK, 4000.0, 0x5b9e_0002);
+
+    let mut suppressor = ResidualSuppressor::new(MIN_GAIN, OVERDRIVE);
+    for _ in 0..10 {
+        let mut residual = near_end.clone();
+        suppressor.suppress(&render, &mut residual);
+        assert_eq!(suppressor.gain(), 1.0);
+        assert_eq!(residual, near_end);
+    }
+}
+
+#[test]
+fn pure_echo_residual_is_pulled_down_to_min_gain() {
+    let mut suppressor = ResidualSuppressor::new(MIN_GAIN, OVERDRIVE);
+    let mut previous_gain = suppressor.gain();
+    for block in 0..10 {
+        let render = white_noise_i16(BLOCK, 8000.0, 0x5b9e_0100 + block);
+        let echo = scaled(&render, 0.1);
+        let mut residual = echo.clone();
+        suppressor.suppress(&render, &mut residual);
+
+        assert!(suppressor.gain() <= previous_gain);
+        previous_gain = suppressor.gain();
+        if block > 0 {
+            let ratio = energy(&residual) / energy(&echo);
+            let expected = f64::from(MIN_GAIN * MIN_GAIN);
+            assert!(
+                (ratio / expected - 1.0).abs() < 0.05,
+                "block {block} passed {ratio} of the echo energy"
+            );
+        }
+    }
+    assert_eq!(suppressor.gain(), MIN_GAIN);
+}
+
+#[test]
+fn gain_never_drops_below_min_gain() {
+    let mut suppressor = ResidualSuppressor::new(MIN_GAIN, 4.0);
+    for block in 0..200 {
+        let render = white_noise_i16(BLOCK, 8000.0, 0x5b9e_0200 + block);
+        // Alternate echo-only blocks with blocks where near-end talk rises
+        // out of a varying echo level.
+        let echo_gain = 0.02 * (1 + block % 7) as f32;
+        let mut residual = scaled(&render, echo_gain);
+        if block % 3 == 0 {
+            let near_end = white_noise_i16(BLOCK, 2000.0, 0x5b9e_0300 + block);
+            for (sample, talk) in residual.iter_mut().zip(near_end) {
+                *sample = sample.saturating_add(talk);
+            }
+        }
+        let input = residual.clone();
+        suppressor.suppress(&render, &mut residual);
+
+        assert!(suppressor.gain() >= MIN_GAIN, "block {block}");
+        for (&out, &inp) in residual.iter().zip(&input) {
+            assert!(
+                f32::from(out).abs() >= MIN_GAIN * f32::from(inp).abs() - 1.0,
+                "block {block} attenuated {inp} to {out}"
+            );
+        }
+    }
+}
+
+#[test]
+fn reset_restores_unity_gain() {
+    let render = white_noise_i16(BLOCK, 8000.0, 0x5b9e_0004);
+    let mut suppressor = ResidualSuppressor::new(MIN_GAIN, OVERDRIVE);
+    let mut residual = scaled(&render, 0.1);
+    suppressor.suppress(&render, &mut residual);
+    assert!(suppressor.gain() < 1.0);
+
+    suppressor.reset();
+    assert_eq!(suppressor.gain(), 1.0);
+}
@@ -0,0 +1,49 @@
+//! The shared test signal generators.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{
+    cancel, chord, colored_noise, convolve, echo, energy, misalignment, sine, to_i16, white_noise,
+};
+
+#[test]
+fn noise_is_deterministic_and_bounded() {
+    let a = white_noise(1000, 100.0, 7);
+    assert_eq!(a, white_noise(1000, 100.0, 7));
+    assert_ne!(a, white_noise(1000, 100.0, 8));
+    assert!(a.iter().all(|s| s.abs() <= 100.0));
+    // Without feedback coloring changes nothing.
+    assert_eq!(colored_noise(1000, 100.0, &[], 7), a);
+}
+
+#[test]
+fn chord_is_the_sum_of_its_sines() {
+    let low = sine(256, 220.0, 48_000, 0.5);
+    let high = sine(256, 330.0, 48_000, 0.5);
+    for ((sum, low), high) in chord(256, &[220.0, 330.0], 48_000, 0.5)
+        .iter()
+        .zip(&low)
+        .zip(&high)
+    {
+        assert!((sum - (low + high)).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn convolution_delays_and_scales() {
+    let signal = [1.0, 2.0, 3.0, 4.0];
+    assert_eq!(convolve(&signal, &[0.0, 0.5]), [0.0, 0.5, 1.0, 1.5]);
+    assert_eq!(convolve(&signal, &[1.0, 1.0]), [1.0, 3.0, 5.0, 7.0]);
+    assert_eq!(to_i16(&[0.4, -0.6, 40_000.0]), [0, -1, i16::MAX]);
+}
+
+#[test]
+fn canceller_fixtures_agree_with_their_definitions() {
+    assert_eq!(echo(&[100, 200, -300], &[0.5, 0.25]), [50, 125, -100]);
+    assert_eq!(energy(&[3, -4]), 25.0);
+    assert_eq!(misalignment(&[1.0, 2.0, 9.0], &[1.0, 0.0]), 4.0);
+
+    // Nothing to cancel leaves the capture untouched, whatever the block.
+    let capture = [7i16; 300];
+    let mut canceller = NlmsCanceller::new(16, 0.5);
+    assert_eq!(cancel(&mut canceller, &[0; 300], &capture, 128), capture);
+}
@@ -0,0 +1,72 @@
+//! VSS-NLMS against fixed-step NLMS on a noisy echo path.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{self, echo, white_noise, white_noise_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 400;
+
+/// A smooth decaying echo path; neighbouring taps are strongly correlated
+/// like those of a real room response.
+fn echo_path() -> Vec<f32> {
+    (0..TAPS)
+        .map(|idx| {
+            let t = idx as f32;
+            0.5 * (-t / 16.0).exp() * (t / 6.0).cos()
+        })
+        .collect()
+}
+
+/// Returns the squared distance between the taps and the true path after
+/// every block.
+fn misalignment_curve(canceller: &mut NlmsCanceller) -> Vec<f32> {
+    let path = echo_path();
+    let render = white_noise_i16(BLOCKS * BLOCK, 8000.0, 0x0bad_5eed);
+    let near = white_noise(render.len(), 300.0, 0x5eed_0bad);
+    let capture: Vec<i16> = echo(&render, &path)
+        .iter()
+        .zip(&near)
+        .map(|(&echo, &near)| (echo as f32 + near).round() as i16)
+        .collect();
+    test_util::misalignment_curve(canceller, &render, &capture, &path, BLOCK)
+}
+
+#[test]
+fn variable_step_lowers_steady_state_error() {
+    let mut fixed = NlmsCanceller::new(TAPS, 0.5);
+    let mut variable = NlmsCanceller::builder(TAPS, 0.5)
+        .variable_step(true)
+        .build()
+        .unwrap();
+    let fixed = misalignment_curve(&mut fixed);
+    let variable = misalignment_curve(&mut variable);
+
+    // Both reach -30 dB misalignment within a block of each other...
+    let settled = |curve: &[f32]| curve.iter().position(|&m| m < 1e-3).unwrap();
+    assert!(
+        settled(&variable) <= settled(&fixed) + 1,
+        "fixed settles after {} blocks, variable after {}",
+        settled(&fixed),
+        settled(&variable)
+    );
+
+    // ...but the variable step ends up at least 6 dB closer to the true path.
+    let tail = |curve: &[f32]| curve[BLOCKS - 100..].iter().sum::<f32>() / 100.0;
+    assert!(
+        tail(&variable) < tail(&fixed) / 4.0,
+        "steady-state misalignment: fixed {}, variable {}",
+        tail(&fixed),
+        tail(&variable)
+    );
+}
+
+#[test]
+fn variable_step_shrinks_once_converged() {
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    canceller.set_variable_step(true);
+    assert_eq!(canceller.effective_step_size(), 0.5);
+
+    misalignment_curve(&mut canceller);
+    assert!(canceller.effective_step_size() < 0.1);
+}
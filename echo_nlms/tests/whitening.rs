@@ -0,0 +1,64 @@
+//! Prewhitened adaptation on a strongly colored render signal.
+
+use echo_nlms::NlmsCanceller;
+use echo_nlms::test_util::{self, colored_noise, echo, echo_path, to_i16};
+
+const TAPS: usize = 64;
+const BLOCK: usize = 256;
+const BLOCKS: usize = 40;
+
+/// Returns the normalized misalignment `|w - h|^2 / |h|^2` in dB after
+/// every block, with the render an AR(2) process resonating near 1.3 kHz.
+fn misalignment_curve(canceller: &mut NlmsCanceller) -> Vec<f32> {
+    let path = echo_path(TAPS);
+    let path_energy: f32 = path.iter().map(|h| h * h).sum();
+    // Poles at radius 0.95, so the spectrum spans about 30 dB.
+    let render = to_i16(&colored_noise(
+        BLOCKS * BLOCK,
+        800.0,
+        &[1.8, -0.9025],
+        0x1234_5678,
+    ));
+    let capture = echo(&render, &path);
+    test_util::misalignment_curve(canceller, &render, &capture, &path, BLOCK)
+        .into_iter()
+        .map(|error| 10.0 * (error / path_energy).log10())
+        .collect()
+}
+
+#[test]
+fn whitening_speeds_up_convergence() {
+    let mut plain = NlmsCanceller::new(TAPS, 0.5);
+    let mut whitened = NlmsCanceller::new(TAPS, 0.5);
+    whitened.set_whitening(4);
+    let plain = misalignment_curve(&mut plain);
+    let whitened = misalignment_curve(&mut whitened);
+
+    // After the first few blocks the whitened filter stays well ahead.
+    for (block, (p, w)) in plain.iter().zip(&whitened).enumerate().skip(10) {
+        assert!(
+            w + 10.0 < *p,
+            "block {block}: whitened {w} dB vs plain {p} dB"
+        );
+    }
+    assert!(
+        *whitened.last().unwrap() < -30.0,
+        "whitened misalignment {} dB",
+        whitened.last().unwrap()
+    );
+}
+
+#[test]
+fn whitening_can_be_switched_off() {
+    let mut canceller = NlmsCanceller::new(TAPS, 0.5);
+    canceller.set_whitening(4);
+    assert_eq!(canceller.whitening(), 4);
+    canceller.set_whitening(0);
+    assert_eq!(canceller.whitening(), 0);
+
+    let mut plain = NlmsCanceller::new(TAPS, 0.5);
+    assert_eq!(
+        misalignment_curve(&mut canceller),
+        misalignment_curve(&mut plain)
+    );
+}
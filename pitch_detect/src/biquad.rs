@@ -0,0 +1,95 @@
+//! Second-order IIR sections for conditioning analysis blocks.
+
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+/// Direct form I biquad with coefficients from the RBJ audio EQ cookbook.
+///
+/// With `w0 = 2π · cutoff / sample_rate` and `alpha = sin(w0) / (2q)`, the
+/// coefficients before normalizing by `a0 = 1 + alpha` are:
+///
+/// - high-pass: `b = [(1 + cos w0) / 2, -(1 + cos w0), (1 + cos w0) / 2]`
+/// - low-pass: `b = [(1 - cos w0) / 2, 1 - cos w0, (1 - cos w0) / 2]`
+///
+/// and `a = [1 + alpha, -2 cos w0, 1 - alpha]` for both.
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    /// Butterworth quality factor, maximally flat in the passband.
+    pub const BUTTERWORTH_Q: f32 = FRAC_1_SQRT_2;
+
+    /// Creates a high-pass section attenuating content below `cutoff` Hz.
+    pub fn highpass(sample_rate: u32, cutoff: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, cutoff, q);
+        let b0 = (1.0 + cos_w0) / 2.0;
+        Self::normalized(b0, -2.0 * b0, b0, 1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha)
+    }
+
+    /// Creates a low-pass section attenuating content above `cutoff` Hz.
+    pub fn lowpass(sample_rate: u32, cutoff: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, cutoff, q);
+        let b0 = (1.0 - cos_w0) / 2.0;
+        Self::normalized(b0, 2.0 * b0, b0, 1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha)
+    }
+
+    /// Filters one sample.
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+
+    /// Filters `samples` in place.
+    pub fn process_slice(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.process(*sample);
+        }
+    }
+
+    /// Clears the filter memory.
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+
+    fn prewarp(sample_rate: u32, cutoff: f32, q: f32) -> (f32, f32) {
+        assert!(
+            cutoff > 0.0 && cutoff < sample_rate as f32 / 2.0,
+            "cutoff must lie between 0 and Nyquist"
+        );
+        assert!(q.is_finite() && q > 0.0, "q must be positive and finite");
+        let w0 = 2.0 * PI * cutoff / sample_rate as f32;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Overlapping analysis frames over a stream of blocks.
+
+/// Splits a stream of samples into frames of a fixed length that advance by
+/// a hop smaller than or equal to the frame.
+///
+/// Blocks of any size are [`push`](Self::push)ed in, and a new frame becomes
+/// available from [`next_frame`](Self::next_frame) after every `hop` samples.
+/// The buffer starts out as silence, so the first frame is ready after one
+/// hop rather than a whole frame. With the hop equal to the frame length the
+/// frames are the input split into consecutive disjoint blocks.
+pub struct OverlappingFrames {
+    frame_len: usize,
+    hop: usize,
+    buffer: Vec<i16>,
+    start: usize,
+}
+
+impl OverlappingFrames {
+    /// Creates a buffer producing `frame_len`-sample frames every `hop`
+    /// samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hop` is zero or longer than `frame_len`.
+    pub fn new(frame_len: usize, hop: usize) -> Self {
+        assert!(
+            hop > 0 && hop <= frame_len,
+            "hop must be between 1 and the frame length"
+        );
+        let mut buffer = Vec::with_capacity(2 * frame_len);
+        buffer.resize(frame_len - hop, 0);
+        Self {
+            frame_len,
+            hop,
+            buffer,
+            start: 0,
+        }
+    }
+
+    /// Returns the number of samples in each frame.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Returns the number of samples between the starts of consecutive
+    /// frames.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Appends `samples` to the stream.
+    pub fn push(&mut self, samples: &[i16]) {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.buffer.extend_from_slice(samples);
+    }
+
+    /// Returns the next complete frame, or `None` until another hop of
+    /// samples has been pushed.
+    pub fn next_frame(&mut self) -> Option<&[i16]> {
+        let start = self.start;
+        if self.buffer.len() - start < self.frame_len {
+            return None;
+        }
+        self.start += self.hop;
+        Some(&self.buffer[start..start + self.frame_len])
+    }
+
+    /// Discards buffered samples, returning to the initial silence.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.buffer.resize(self.frame_len - self.hop, 0);
+        self.start = 0;
+    }
+}
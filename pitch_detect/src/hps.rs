@@ -0,0 +1,207 @@
+//! Harmonic product spectrum pitch detection.
+
+use echo_nlms::fft::{Complex, Fft};
+
+use crate::{PitchResult, PitchSeparation, Window, parabolic_peak};
+
+/// Zero-padding factor applied before the FFT so spectral peaks can be
+/// located more finely than the block length alone allows.
+const ZERO_PAD: usize = 4;
+
+/// Floor added to bin powers before taking logarithms.
+const POWER_FLOOR: f32 = 1e-12;
+
+/// Polyphonic pitch detector based on the harmonic product spectrum.
+///
+/// Each block is mean-subtracted, windowed, zero-padded and transformed. The
+/// magnitude spectrum is then multiplied by copies of itself decimated by 2,
+/// 3, ... up to the harmonic count, which reinforces bins whose overtones are
+/// all present. The strongest local peaks of the product, refined by
+/// parabolic interpolation, are reported as fundamentals.
+///
+/// The product is accumulated in the log domain. A result's `correlation` is
+/// the geometric mean magnitude of its harmonics relative to the loudest bin
+/// of the block, so a clean harmonic tone scores close to one.
+pub struct HpsDetector {
+    block_len: usize,
+    sample_rate: u32,
+    fft: Fft,
+    harmonics: usize,
+    min_hz: f32,
+    max_hz: f32,
+    min_bin: usize,
+    max_bin: usize,
+    max_results: usize,
+    min_strength: f32,
+    separation: PitchSeparation,
+    window: Window,
+    floated: Vec<f32>,
+    spectrum: Vec<Complex>,
+    log_magnitude: Vec<f32>,
+    product: Vec<f32>,
+    peaks: Vec<(usize, f32)>,
+    results: Vec<PitchResult>,
+}
+
+impl HpsDetector {
+    /// Creates a detector for blocks of `block_len` samples searching between
+    /// `min_hz` and `max_hz`.
+    ///
+    /// Defaults to four harmonics, a single result, no strength floor, a
+    /// 5 Hz separation and a Hann window.
+    pub fn new(block_len: usize, sample_rate: u32, min_hz: f32, max_hz: f32) -> Self {
+        assert!(block_len > 0, "block_len must be positive");
+        let fft_len = (block_len * ZERO_PAD).next_power_of_two();
+
+        let mut detector = Self {
+            block_len,
+            sample_rate,
+            fft: Fft::new(fft_len),
+            harmonics: 4,
+            min_hz,
+            max_hz,
+            min_bin: 0,
+            max_bin: 0,
+            max_results: 1,
+            min_strength: 0.0,
+            separation: PitchSeparation::default(),
+            window: Window::Hann,
+            floated: vec![0.0; block_len],
+            spectrum: vec![Complex::ZERO; fft_len],
+            log_magnitude: vec![0.0; fft_len / 2 + 1],
+            product: vec![0.0; fft_len / 2 + 1],
+            peaks: Vec::with_capacity(fft_len / 2),
+            results: Vec::with_capacity(1),
+        };
+        detector.update_bins();
+        detector
+    }
+
+    /// Sets how many decimated spectra are multiplied together, counting the
+    /// original one.
+    pub fn set_harmonics(&mut self, harmonics: usize) {
+        assert!(harmonics > 0, "harmonics must be positive");
+        self.harmonics = harmonics;
+        self.update_bins();
+    }
+
+    /// Sets how many pitches [`detect`](Self::detect) reports at most.
+    pub fn set_max_results(&mut self, max_results: usize) {
+        self.max_results = max_results;
+        self.results.reserve(max_results);
+    }
+
+    /// Sets the relative harmonic strength a peak needs to be reported.
+    pub fn set_min_strength(&mut self, min_strength: f32) {
+        self.min_strength = min_strength;
+    }
+
+    /// Sets the minimum spacing between reported pitches.
+    pub fn set_separation(&mut self, separation: PitchSeparation) {
+        self.separation = separation;
+    }
+
+    /// Sets the analysis window applied to each block.
+    pub fn set_window(&mut self, window: Window) {
+        self.window = window;
+    }
+
+    /// Detects pitches in `samples`, strongest first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is not exactly the block length given to
+    /// [`new`](Self::new).
+    pub fn detect(&mut self, samples: &[i16]) -> &[PitchResult] {
+        assert_eq!(
+            samples.len(),
+            self.block_len,
+            "samples must match the detector block length"
+        );
+
+        self.results.clear();
+        if self.min_bin + 2 > self.max_bin || self.max_results == 0 {
+            return &self.results;
+        }
+
+        for (dst, &src) in self.floated.iter_mut().zip(samples) {
+            *dst = src as f32;
+        }
+        let mean = self.floated.iter().sum::<f32>() / self.block_len as f32;
+        for sample in &mut self.floated {
+            *sample -= mean;
+        }
+        self.window.apply(&mut self.floated);
+
+        self.spectrum.fill(Complex::ZERO);
+        for (bin, &sample) in self.spectrum.iter_mut().zip(&self.floated) {
+            bin.re = sample;
+        }
+        self.fft.forward(&mut self.spectrum);
+
+        let mut loudest = f32::NEG_INFINITY;
+        for (log_mag, bin) in self.log_magnitude.iter_mut().zip(&self.spectrum) {
+            *log_mag = 0.5 * (bin.norm_sqr() + POWER_FLOOR).ln();
+            loudest = loudest.max(*log_mag);
+        }
+
+        for k in self.min_bin - 1..=self.max_bin + 1 {
+            self.product[k] = (1..=self.harmonics)
+                .map(|h| self.log_magnitude[k * h])
+                .sum();
+        }
+
+        self.peaks.clear();
+        for k in self.min_bin..=self.max_bin {
+            let value = self.product[k];
+            if value > self.product[k - 1] && value >= self.product[k + 1] {
+                self.peaks.push((k, value));
+            }
+        }
+        self.peaks.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+
+        let bin_hz = self.sample_rate as f32 / self.fft.len() as f32;
+        for &(k, value) in &self.peaks {
+            let strength = (value / self.harmonics as f32 - loudest).exp();
+            if strength < self.min_strength {
+                break;
+            }
+
+            let (offset, _) =
+                parabolic_peak(self.product[k - 1], self.product[k], self.product[k + 1]);
+            let freq = (k as f32 + offset) * bin_hz;
+            if freq < self.min_hz || freq > self.max_hz {
+                continue;
+            }
+
+            let is_distinct = self
+                .results
+                .iter()
+                .all(|existing| self.separation.is_distinct(existing.freq, freq));
+            if is_distinct {
+                self.results.push(PitchResult {
+                    freq,
+                    correlation: strength,
+                    period: self.sample_rate as f32 / freq,
+                });
+            }
+
+            if self.results.len() == self.max_results {
+                break;
+            }
+        }
+
+        &self.results
+    }
+
+    /// Recomputes the searched bin range so every harmonic of the highest
+    /// candidate, and one neighbour on either side, stays below Nyquist.
+    fn update_bins(&mut self) {
+        let bin_hz = self.sample_rate as f32 / self.fft.len() as f32;
+        let nyquist_bin = self.fft.len() / 2;
+        self.min_bin = ((self.min_hz / bin_hz).floor() as usize).max(1);
+        self.max_bin = ((self.max_hz / bin_hz).ceil() as usize)
+            .min(nyquist_bin / self.harmonics)
+            .saturating_sub(1);
+    }
+}
@@ -0,0 +1,622 @@
+//! Autocorrelation-based multi-pitch detection.
+
+mod biquad;
+mod frames;
+mod hps;
+mod midi;
+mod tracker;
+mod window;
+
+pub use biquad::Biquad;
+pub use frames::OverlappingFrames;
+pub use hps::HpsDetector;
+pub use midi::{NoteName, hz_to_midi};
+pub use tracker::PitchTracker;
+pub use window::Window;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// Coarse peaks refined at full rate per requested result, so a strong
+/// octave or subharmonic peak cannot crowd out the true pitch.
+const COARSE_CANDIDATES_PER_RESULT: usize = 3;
+/// Fraction of a peak's correlation its half lag needs for octave correction
+/// to move the pitch up an octave.
+const OCTAVE_TOLERANCE: f32 = 0.9;
+
+/// A detected pitch and how strongly the signal correlates at its period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PitchResult {
+    /// Fundamental frequency in Hz.
+    pub freq: f32,
+    /// Normalized autocorrelation at the detected period, in `[-1.0, 1.0]`.
+    pub correlation: f32,
+    /// Period in samples: the winning lag including its fractional
+    /// refinement, or for spectral detectors the period matching `freq`.
+    pub period: f32,
+}
+
+/// Minimum spacing for two detected pitches to count as separate voices.
+///
+/// Defaults to 5 Hz. Parses from and displays as a number with an `hz` or
+/// `cents` suffix, such as `5hz` or `50cents`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PitchSeparation {
+    /// Absolute spacing in Hz.
+    Hz(f32),
+    /// Musical spacing in cents (100 cents per semitone), which scales with
+    /// register.
+    Cents(f32),
+}
+
+impl PitchSeparation {
+    fn is_distinct(self, a: f32, b: f32) -> bool {
+        match self {
+            PitchSeparation::Hz(hz) => (a - b).abs() > hz,
+            PitchSeparation::Cents(cents) => (1200.0 * (a / b).log2()).abs() > cents,
+        }
+    }
+}
+
+impl Default for PitchSeparation {
+    fn default() -> Self {
+        PitchSeparation::Hz(5.0)
+    }
+}
+
+impl fmt::Display for PitchSeparation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PitchSeparation::Hz(hz) => write!(f, "{hz}hz"),
+            PitchSeparation::Cents(cents) => write!(f, "{cents}cents"),
+        }
+    }
+}
+
+impl FromStr for PitchSeparation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        let (value, unit): (&str, fn(f32) -> Self) = if let Some(hz) = lower.strip_suffix("hz") {
+            (hz, PitchSeparation::Hz)
+        } else if let Some(cents) = lower.strip_suffix("cents") {
+            (cents, PitchSeparation::Cents)
+        } else {
+            return Err(format!(
+                "separation `{s}` needs a unit (expected e.g. 5hz or 50cents)"
+            ));
+        };
+        match value.trim().parse::<f32>() {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(unit(value)),
+            _ => Err(format!(
+                "invalid separation `{s}` (expected a non-negative number of hz or cents)"
+            )),
+        }
+    }
+}
+
+/// Pitch estimation method used by the jammers' analysis loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PitchAlgorithm {
+    /// Normalized autocorrelation via [`detect_pitches`]; reports several
+    /// voices.
+    #[default]
+    Autocorrelation,
+    /// YIN via [`detect_pitches_yin`]; monophonic but less prone to octave
+    /// errors.
+    Yin,
+    /// Harmonic product spectrum via [`HpsDetector`]; better at separating
+    /// the notes of a chord.
+    Hps,
+}
+
+/// Detects up to `max_results` distinct pitches between `min_hz` and `max_hz`,
+/// strongest first.
+///
+/// Convenience wrapper that builds a one-off [`PitchDetector`] sized to
+/// `samples`; real-time callers should keep a detector around instead.
+#[allow(clippy::too_many_arguments)]
+pub fn detect_pitches(
+    samples: &[i16],
+    sample_rate: u32,
+    min_hz: f32,
+    max_hz: f32,
+    max_results: usize,
+    min_correlation: f32,
+    separation: PitchSeparation,
+    window: Window,
+) -> Vec<PitchResult> {
+    let mut detector = PitchDetector::new(samples.len(), sample_rate, min_hz, max_hz);
+    detector.set_window(window);
+    detector.set_max_results(max_results);
+    detector.set_min_correlation(min_correlation);
+    detector.set_separation(separation);
+    detector.detect(samples).to_vec()
+}
+
+/// Autocorrelation pitch detector that reuses its buffers between blocks.
+///
+/// Each block is optionally band-limited by a prefilter, mean-subtracted and
+/// windowed (Hann by default), then every candidate lag is scored by its
+/// normalized autocorrelation. Lags correlating below the minimum
+/// correlation are ignored, as are candidates closer than the separation to
+/// an already accepted pitch, and the whole block is treated as unvoiced
+/// when even its best lag falls short of the minimum
+/// [clarity](Self::clarity). Each accepted peak is refined to a fractional
+/// lag by fitting a parabola through its neighbouring lags.
+///
+/// With [`set_coarse_search`](Self::set_coarse_search) the full lag range is
+/// only scanned on a decimated copy of the block, and the full-rate
+/// correlation is computed just around the strongest coarse peaks.
+///
+/// With [`set_octave_correction`](Self::set_octave_correction) each accepted
+/// peak is checked against its half lag first, guarding against the
+/// autocorrelation locking onto twice the period.
+///
+/// All buffers are sized in [`new`](Self::new), so [`detect`](Self::detect)
+/// does not allocate.
+pub struct PitchDetector {
+    block_len: usize,
+    sample_rate: u32,
+    min_period: usize,
+    max_period: usize,
+    max_results: usize,
+    min_correlation: f32,
+    min_clarity: f32,
+    clarity: f32,
+    separation: PitchSeparation,
+    window: Window,
+    prefilter: Option<(Biquad, Biquad)>,
+    coarse: Option<CoarseSearch>,
+    octave_correction: bool,
+    floated: Vec<f32>,
+    energy_prefix: Vec<f32>,
+    by_lag: Vec<Option<f32>>,
+    correlations: Vec<(usize, f32)>,
+    results: Vec<PitchResult>,
+}
+
+impl PitchDetector {
+    /// Creates a detector for blocks of `block_len` samples searching between
+    /// `min_hz` and `max_hz`.
+    ///
+    /// Defaults to a single result, no correlation floor, a 5 Hz separation
+    /// and a Hann window. A frequency range that does not fit in the block
+    /// leaves the detector permanently reporting no pitches.
+    pub fn new(block_len: usize, sample_rate: u32, min_hz: f32, max_hz: f32) -> Self {
+        let min_period = ((sample_rate as f32) / max_hz).floor() as usize;
+        let max_period = ((sample_rate as f32) / min_hz).ceil() as usize;
+        let lags = if min_period < 2 || max_period >= block_len || min_period >= max_period {
+            0
+        } else {
+            max_period - min_period + 1
+        };
+
+        Self {
+            block_len,
+            sample_rate,
+            min_period,
+            max_period,
+            max_results: 1,
+            min_correlation: -1.0,
+            min_clarity: -1.0,
+            clarity: 0.0,
+            separation: PitchSeparation::default(),
+            window: Window::Hann,
+            prefilter: None,
+            coarse: None,
+            octave_correction: false,
+            floated: vec![0.0; block_len],
+            energy_prefix: vec![0.0; block_len + 1],
+            by_lag: vec![None; lags],
+            correlations: Vec::with_capacity(lags),
+            results: Vec::with_capacity(1),
+        }
+    }
+
+    /// Returns the lags, in samples, searched for a period: those of
+    /// `max_hz` up to `min_hz`, rounded outwards.
+    pub fn period_range(&self) -> RangeInclusive<usize> {
+        self.min_period..=self.max_period
+    }
+
+    /// Sets how many pitches [`detect`](Self::detect) reports at most.
+    pub fn set_max_results(&mut self, max_results: usize) {
+        self.max_results = max_results;
+        self.results.reserve(max_results);
+    }
+
+    /// Sets the normalized correlation a lag needs to be reported.
+    pub fn set_min_correlation(&mut self, min_correlation: f32) {
+        self.min_correlation = min_correlation;
+    }
+
+    /// Sets the clarity a block needs for any pitch to be reported, so noise
+    /// that happens to push one lag over the minimum correlation stays
+    /// unvoiced.
+    pub fn set_min_clarity(&mut self, min_clarity: f32) {
+        self.min_clarity = min_clarity;
+    }
+
+    /// Returns the clarity of the last block: its best normalized
+    /// autocorrelation over the searched lags, near one for a clean periodic
+    /// signal and low for noise. Zero before the first block and for silent
+    /// blocks.
+    pub fn clarity(&self) -> f32 {
+        self.clarity
+    }
+
+    /// Sets the minimum spacing between reported pitches.
+    pub fn set_separation(&mut self, separation: PitchSeparation) {
+        self.separation = separation;
+    }
+
+    /// Sets the analysis window applied to each block.
+    pub fn set_window(&mut self, window: Window) {
+        self.window = window;
+    }
+
+    /// Band-limits blocks to `low_hz..high_hz` with a Butterworth high-pass
+    /// and low-pass pair before analysis, or disables the prefilter with
+    /// `None`. The filter state carries over between consecutive blocks.
+    pub fn set_prefilter(&mut self, band: Option<(f32, f32)>) {
+        self.prefilter = band.map(|(low_hz, high_hz)| {
+            (
+                Biquad::highpass(self.sample_rate, low_hz, Biquad::BUTTERWORTH_Q),
+                Biquad::lowpass(self.sample_rate, high_hz, Biquad::BUTTERWORTH_Q),
+            )
+        });
+    }
+
+    /// Enables the coarse-to-fine lag search, decimating blocks by `factor`
+    /// for the coarse pass, or scans every lag at full rate with `None`.
+    ///
+    /// Decimating by `factor` cuts the cost of the coarse pass by roughly
+    /// `factor` squared; 2 to 4 keeps the coarse lags fine enough to locate
+    /// pitches up to a few hundred Hz. The decimation averages each group of
+    /// samples, so frequencies near the decimated Nyquist rate are poorly
+    /// resolved in the coarse pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is less than 2.
+    pub fn set_coarse_search(&mut self, factor: Option<usize>) {
+        self.coarse = factor.map(|factor| {
+            assert!(factor >= 2, "decimation factor must be at least 2");
+            CoarseSearch::new(self.block_len, factor)
+        });
+    }
+
+    /// Enables moving each detected pitch up an octave, repeatedly, while the
+    /// lag at half its period correlates nearly as well.
+    ///
+    /// A periodic signal correlates almost as strongly at twice its period
+    /// as at the period itself, so noise or vibrato can tip the strongest
+    /// peak an octave low; the fundamental is the shortest lag that still
+    /// explains the signal. Notes genuinely an octave apart in a chord are
+    /// merged by this, so it suits single voices best.
+    pub fn set_octave_correction(&mut self, enabled: bool) {
+        self.octave_correction = enabled;
+    }
+
+    /// Detects pitches in `samples`, strongest first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is not exactly the block length given to
+    /// [`new`](Self::new).
+    pub fn detect(&mut self, samples: &[i16]) -> &[PitchResult] {
+        assert_eq!(
+            samples.len(),
+            self.block_len,
+            "samples must match the detector block length"
+        );
+
+        self.results.clear();
+        self.clarity = 0.0;
+        if self.by_lag.is_empty() || self.max_results == 0 {
+            return &self.results;
+        }
+
+        let len = self.block_len;
+        let floated = &mut self.floated;
+        for (dst, &src) in floated.iter_mut().zip(samples) {
+            *dst = src as f32;
+        }
+        if let Some((highpass, lowpass)) = self.prefilter.as_mut() {
+            highpass.process_slice(floated);
+            lowpass.process_slice(floated);
+        }
+        let mean = floated.iter().sum::<f32>() / len as f32;
+
+        for sample in floated.iter_mut() {
+            *sample -= mean;
+        }
+
+        self.window.apply(floated);
+
+        let energy_prefix = &mut self.energy_prefix;
+        for (idx, sample) in floated.iter().enumerate() {
+            energy_prefix[idx + 1] = energy_prefix[idx] + sample * sample;
+        }
+
+        let min_period = self.min_period;
+        self.by_lag.fill(None);
+        self.correlations.clear();
+
+        let by_lag = &mut self.by_lag;
+        let correlations = &mut self.correlations;
+        let mut score = |lag: usize| {
+            let slot = &mut by_lag[lag - min_period];
+            if slot.is_some() {
+                return;
+            }
+            if let Some(normalized) = normalized_correlation(floated, energy_prefix, lag) {
+                *slot = Some(normalized);
+                correlations.push((lag, normalized));
+            }
+        };
+
+        match self.coarse.as_mut() {
+            Some(coarse) => {
+                let candidates = self.max_results * COARSE_CANDIDATES_PER_RESULT;
+                let factor = coarse.factor;
+                for coarse_lag in coarse.peaks(floated, min_period, self.max_period, candidates) {
+                    let centre = coarse_lag * factor;
+                    let low = centre.saturating_sub(factor).max(min_period);
+                    let high = (centre + factor).min(self.max_period);
+                    for lag in low..=high {
+                        score(lag);
+                    }
+                }
+            }
+            None => {
+                for lag in min_period..=self.max_period {
+                    score(lag);
+                }
+            }
+        }
+
+        self.correlations
+            .sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        self.clarity = self.correlations.first().map_or(0.0, |&(_, corr)| corr);
+        if self.clarity < self.min_clarity {
+            return &self.results;
+        }
+
+        for idx in 0..self.correlations.len() {
+            let (mut lag, mut corr) = self.correlations[idx];
+            if corr < self.min_correlation {
+                continue;
+            }
+            if self.octave_correction {
+                (lag, corr) = self.correct_octave(lag, corr);
+            }
+
+            let (offset, corr) = match (self.correlation_at(lag - 1), self.correlation_at(lag + 1))
+            {
+                (Some(before), Some(after)) => parabolic_peak(before, corr, after),
+                _ => (0.0, corr),
+            };
+
+            let period = lag as f32 + offset;
+            let freq = self.sample_rate as f32 / period;
+            let is_distinct = self
+                .results
+                .iter()
+                .all(|existing| self.separation.is_distinct(existing.freq, freq));
+            if is_distinct {
+                self.results.push(PitchResult {
+                    freq,
+                    correlation: corr,
+                    period,
+                });
+            }
+
+            if self.results.len() == self.max_results {
+                break;
+            }
+        }
+
+        &self.results
+    }
+
+    /// Follows `lag` down to its half as long as that keeps at least
+    /// `OCTAVE_TOLERANCE` of the correlation.
+    fn correct_octave(&mut self, mut lag: usize, mut corr: f32) -> (usize, f32) {
+        while corr > 0.0 {
+            let half = lag.div_ceil(2);
+            let mut best: Option<(usize, f32)> = None;
+            // At the shortest lags the neighbourhood reaches `lag` itself,
+            // which would always qualify and never move on.
+            for candidate in half - 1..=(half + 1).min(lag - 1) {
+                if let Some(value) = self.correlation_at(candidate)
+                    && best.is_none_or(|(_, best)| value > best)
+                {
+                    best = Some((candidate, value));
+                }
+            }
+            match best {
+                Some((half_lag, half_corr)) if half_corr >= corr * OCTAVE_TOLERANCE => {
+                    (lag, corr) = (half_lag, half_corr);
+                }
+                _ => break,
+            }
+        }
+        (lag, corr)
+    }
+
+    /// Returns the normalized correlation at `lag`, scoring it now if the
+    /// search skipped it, or `None` outside the search range.
+    fn correlation_at(&mut self, lag: usize) -> Option<f32> {
+        if !(self.min_period..=self.max_period).contains(&lag) {
+            return None;
+        }
+        let slot = &mut self.by_lag[lag - self.min_period];
+        if slot.is_none() {
+            *slot = normalized_correlation(&self.floated, &self.energy_prefix, lag);
+        }
+        *slot
+    }
+}
+
+/// Decimated copy of a block and the scratch space for scanning it.
+struct CoarseSearch {
+    factor: usize,
+    decimated: Vec<f32>,
+    energy_prefix: Vec<f32>,
+    peaks: Vec<(usize, f32)>,
+}
+
+impl CoarseSearch {
+    fn new(block_len: usize, factor: usize) -> Self {
+        let len = block_len / factor;
+        Self {
+            factor,
+            decimated: vec![0.0; len],
+            energy_prefix: vec![0.0; len + 1],
+            peaks: Vec::with_capacity(len),
+        }
+    }
+
+    /// Decimates `samples` and returns the decimated lags of the `count`
+    /// strongest local correlation maxima between `min_period` and
+    /// `max_period` full-rate samples.
+    fn peaks(
+        &mut self,
+        samples: &[f32],
+        min_period: usize,
+        max_period: usize,
+        count: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let factor = self.factor;
+        for (dst, group) in self.decimated.iter_mut().zip(samples.chunks_exact(factor)) {
+            *dst = group.iter().sum::<f32>() / factor as f32;
+        }
+        for (idx, sample) in self.decimated.iter().enumerate() {
+            self.energy_prefix[idx + 1] = self.energy_prefix[idx] + sample * sample;
+        }
+
+        // Pad the range by a lag on each side so peaks at its ends are still
+        // recognized as maxima.
+        let low = (min_period / factor).saturating_sub(1).max(1);
+        let high = (max_period.div_ceil(factor) + 1).min(self.decimated.len().saturating_sub(1));
+        let score = |lag: usize| {
+            normalized_correlation(&self.decimated, &self.energy_prefix, lag).unwrap_or(-1.0)
+        };
+
+        self.peaks.clear();
+        let (mut before, mut current) = (score(low), score(low + 1));
+        for lag in low + 1..high {
+            let after = score(lag + 1);
+            if current >= before && current > after {
+                self.peaks.push((lag, current));
+            }
+            (before, current) = (current, after);
+        }
+        self.peaks
+            .sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        self.peaks.iter().take(count).map(|&(lag, _)| lag)
+    }
+}
+
+/// Returns the normalized correlation of `samples` with itself shifted by
+/// `lag`, given the running energy of `samples`, or `None` when either
+/// segment is too short or silent.
+fn normalized_correlation(samples: &[f32], energy_prefix: &[f32], lag: usize) -> Option<f32> {
+    let len = samples.len();
+    let segment_len = len.checked_sub(lag).filter(|&n| n >= 2)?;
+
+    let energy_a = energy_prefix[segment_len] - energy_prefix[0];
+    let energy_b = energy_prefix[len] - energy_prefix[lag];
+    let denom = (energy_a * energy_b).sqrt();
+    if denom <= 1e-9 {
+        return None;
+    }
+
+    let mut sum = 0.0;
+    for i in 0..segment_len {
+        sum += samples[i] * samples[i + lag];
+    }
+    Some((sum / denom).clamp(-1.0, 1.0))
+}
+
+/// Detects a single pitch between `min_hz` and `max_hz` with the YIN
+/// algorithm.
+///
+/// The cumulative mean normalized difference function is scanned for the
+/// first lag dipping below `threshold` (0.1 to 0.2 is typical), then followed
+/// down to its local minimum and refined by parabolic interpolation. The
+/// returned `correlation` is one minus the normalized difference at that lag.
+/// Returns `None` when no lag falls below the threshold.
+pub fn detect_pitches_yin(
+    samples: &[i16],
+    sample_rate: u32,
+    min_hz: f32,
+    max_hz: f32,
+    threshold: f32,
+) -> Option<PitchResult> {
+    let len = samples.len();
+    let min_period = ((sample_rate as f32) / max_hz).floor() as usize;
+    let max_period = ((sample_rate as f32) / min_hz).ceil() as usize;
+
+    if min_period < 2 || max_period >= len || min_period >= max_period {
+        return None;
+    }
+
+    let floated: Vec<f32> = samples.iter().map(|&s| s as f32).collect();
+    let window = len - max_period;
+
+    // cmnd[tau] holds d'(tau); the running sum must start at tau = 1 even
+    // though lags below min_period are never picked.
+    let mut cmnd = vec![1.0f32; max_period + 1];
+    let mut running_sum = 0.0f32;
+    for (tau, value) in cmnd.iter_mut().enumerate().skip(1) {
+        let diff: f32 = floated[..window]
+            .iter()
+            .zip(&floated[tau..tau + window])
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        running_sum += diff;
+        *value = if running_sum > 0.0 {
+            diff * tau as f32 / running_sum
+        } else {
+            1.0
+        };
+    }
+
+    let mut tau = (min_period..=max_period).find(|&tau| cmnd[tau] < threshold)?;
+    while tau < max_period && cmnd[tau + 1] < cmnd[tau] {
+        tau += 1;
+    }
+
+    let offset = if tau > min_period && tau < max_period {
+        parabolic_peak(-cmnd[tau - 1], -cmnd[tau], -cmnd[tau + 1]).0
+    } else {
+        0.0
+    };
+
+    let period = tau as f32 + offset;
+    Some(PitchResult {
+        freq: sample_rate as f32 / period,
+        correlation: (1.0 - cmnd[tau]).clamp(-1.0, 1.0),
+        period,
+    })
+}
+
+/// Fits a parabola through three equally spaced points and returns the
+/// vertex offset from the middle point, within `[-0.5, 0.5]`, and the
+/// interpolated peak height.
+fn parabolic_peak(before: f32, peak: f32, after: f32) -> (f32, f32) {
+    let curvature = before - 2.0 * peak + after;
+    if curvature >= 0.0 {
+        return (0.0, peak);
+    }
+
+    let offset = (0.5 * (before - after) / curvature).clamp(-0.5, 0.5);
+    let height = peak - 0.25 * (before - after) * offset;
+    (offset, height.clamp(-1.0, 1.0))
+}
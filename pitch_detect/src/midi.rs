@@ -0,0 +1,34 @@
+//! Frequency to MIDI note conversion.
+
+use std::fmt;
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Converts a frequency to the nearest MIDI note number (A4 = 440 Hz = 69)
+/// and the deviation from it in cents, within `[-50.0, 50.0]` unless the
+/// frequency lies outside the MIDI range and the note had to be clamped.
+pub fn hz_to_midi(freq: f32) -> (u8, f32) {
+    let exact = 69.0 + 12.0 * (freq / 440.0).log2();
+    let note = exact.round().clamp(0.0, 127.0);
+    (note as u8, 100.0 * (exact - note))
+}
+
+/// Formats a frequency as a note name with cents deviation, e.g. `A4 +12c`.
+#[derive(Clone, Copy, Debug)]
+pub struct NoteName(pub f32);
+
+impl fmt::Display for NoteName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (note, cents) = hz_to_midi(self.0);
+        let octave = note as i32 / 12 - 1;
+        write!(
+            f,
+            "{}{} {:+}c",
+            NOTE_NAMES[note as usize % 12],
+            octave,
+            cents.round() as i32
+        )
+    }
+}
@@ -0,0 +1,157 @@
+//! Frame-to-frame pitch smoothing.
+
+use std::collections::VecDeque;
+
+use crate::PitchResult;
+
+/// Largest jump, in cents, that still continues an existing voice.
+const MAX_JUMP_CENTS: f32 = 300.0;
+
+/// Median filter over the last few detected frequencies of each voice.
+///
+/// Voices live in fixed slots. Each frame's pitches are matched to the slot
+/// whose current frequency is nearest, as long as it lies within a few
+/// semitones, so a voice keeps its slot even when the detector reorders its
+/// results. Pitches left unmatched take a free slot. A slot that receives no
+/// pitch for a frame has its history cleared so a new note does not inherit
+/// the old one's median.
+pub struct PitchTracker {
+    window: usize,
+    history: Vec<VecDeque<f32>>,
+    smoothed: Vec<Option<PitchResult>>,
+    scratch: Vec<f32>,
+    pairs: Vec<(f32, usize, usize)>,
+    matched: Vec<bool>,
+    assigned: Vec<Option<PitchResult>>,
+}
+
+impl PitchTracker {
+    /// Creates a tracker for up to `voices` voices, each smoothed over the
+    /// last `window` frames. A window of one passes pitches through unchanged.
+    pub fn new(voices: usize, window: usize) -> Self {
+        assert!(window > 0, "window must be positive");
+        Self {
+            window,
+            history: (0..voices)
+                .map(|_| VecDeque::with_capacity(window))
+                .collect(),
+            smoothed: vec![None; voices],
+            scratch: Vec::with_capacity(window),
+            pairs: Vec::new(),
+            matched: Vec::new(),
+            assigned: vec![None; voices],
+        }
+    }
+
+    /// Returns the number of frames each voice is smoothed over.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Changes the smoothing window, dropping the oldest frames if it shrinks.
+    pub fn set_window(&mut self, window: usize) {
+        assert!(window > 0, "window must be positive");
+        self.window = window;
+        for history in &mut self.history {
+            while history.len() > window {
+                history.pop_front();
+            }
+        }
+    }
+
+    /// Pushes one frame of detected pitches and returns every voice slot,
+    /// `None` for slots without a voice this frame. Each voice reports its
+    /// median frequency, and the matching period, alongside the correlation
+    /// of its latest detection.
+    /// Pitches that find neither a matching nor a free slot are ignored.
+    pub fn update(&mut self, pitches: &[PitchResult]) -> &[Option<PitchResult>] {
+        self.assign(pitches);
+
+        for ((history, smoothed), &assigned) in self
+            .history
+            .iter_mut()
+            .zip(&mut self.smoothed)
+            .zip(&self.assigned)
+        {
+            let Some(pitch) = assigned else {
+                history.clear();
+                *smoothed = None;
+                continue;
+            };
+
+            if history.len() == self.window {
+                history.pop_front();
+            }
+            history.push_back(pitch.freq);
+
+            self.scratch.clear();
+            self.scratch.extend(history.iter().copied());
+            self.scratch.sort_by(f32::total_cmp);
+            let mid = self.scratch.len() / 2;
+            let median = if self.scratch.len().is_multiple_of(2) {
+                0.5 * (self.scratch[mid - 1] + self.scratch[mid])
+            } else {
+                self.scratch[mid]
+            };
+            *smoothed = Some(PitchResult {
+                freq: median,
+                correlation: pitch.correlation,
+                period: pitch.period * pitch.freq / median,
+            });
+        }
+        &self.smoothed
+    }
+
+    /// Forgets every voice's history.
+    pub fn reset(&mut self) {
+        for history in &mut self.history {
+            history.clear();
+        }
+        self.smoothed.fill(None);
+    }
+
+    /// Fills `assigned` with this frame's raw pitch for each slot, pairing
+    /// pitches and occupied slots greedily from the closest match outwards.
+    fn assign(&mut self, pitches: &[PitchResult]) {
+        self.assigned.fill(None);
+
+        self.pairs.clear();
+        for (pitch_idx, pitch) in pitches.iter().enumerate() {
+            for (slot, current) in self.smoothed.iter().enumerate() {
+                if let Some(current) = current {
+                    let cents = (1200.0 * (pitch.freq / current.freq).log2()).abs();
+                    if cents <= MAX_JUMP_CENTS {
+                        self.pairs.push((cents, pitch_idx, slot));
+                    }
+                }
+            }
+        }
+        self.pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        self.matched.clear();
+        self.matched.resize(pitches.len(), false);
+        for &(_, pitch_idx, slot) in &self.pairs {
+            if !self.matched[pitch_idx] && self.assigned[slot].is_none() {
+                self.assigned[slot] = Some(pitches[pitch_idx]);
+                self.matched[pitch_idx] = true;
+            }
+        }
+
+        for (&pitch, _) in pitches
+            .iter()
+            .zip(&self.matched)
+            .filter(|(_, matched)| !**matched)
+        {
+            let free = self
+                .assigned
+                .iter()
+                .zip(&self.smoothed)
+                .position(|(assigned, current)| assigned.is_none() && current.is_none());
+            let Some(slot) = free.or_else(|| self.assigned.iter().position(Option::is_none)) else {
+                break;
+            };
+            self.history[slot].clear();
+            self.assigned[slot] = Some(pitch);
+        }
+    }
+}
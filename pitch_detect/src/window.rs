@@ -0,0 +1,70 @@
+//! Analysis window functions.
+
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Taper applied to each analysis block before autocorrelation.
+///
+/// With `N` the block length and `x = 2πn / (N - 1)`, sample `n` is scaled by:
+///
+/// - `Hann`: `0.5 - 0.5 cos(x)`
+/// - `Hamming`: `0.54 - 0.46 cos(x)`
+/// - `Blackman`: `0.42 - 0.5 cos(x) + 0.08 cos(2x)`
+/// - `Rectangular`: `1`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Window {
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    Rectangular,
+}
+
+impl Window {
+    /// Multiplies `samples` by the window in place.
+    pub fn apply(self, samples: &mut [f32]) {
+        if samples.len() < 2 || self == Window::Rectangular {
+            return;
+        }
+
+        let denom = (samples.len() - 1) as f32;
+        for (n, sample) in samples.iter_mut().enumerate() {
+            let x = 2.0 * PI * n as f32 / denom;
+            let weight = match self {
+                Window::Hann => 0.5 - 0.5 * x.cos(),
+                Window::Hamming => 0.54 - 0.46 * x.cos(),
+                Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                Window::Rectangular => 1.0,
+            };
+            *sample *= weight;
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Window::Hann => "hann",
+            Window::Hamming => "hamming",
+            Window::Blackman => "blackman",
+            Window::Rectangular => "rectangular",
+        })
+    }
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hann" => Ok(Window::Hann),
+            "hamming" => Ok(Window::Hamming),
+            "blackman" => Ok(Window::Blackman),
+            "rectangular" => Ok(Window::Rectangular),
+            _ => Err(format!(
+                "unknown window `{s}` (expected hann, hamming, blackman or rectangular)"
+            )),
+        }
+    }
+}
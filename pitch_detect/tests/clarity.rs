@@ -0,0 +1,49 @@
+//! Per-block clarity and the voicing decision based on it.
+
+use echo_nlms::test_util::{self, to_i16, white_noise};
+use pitch_detect::PitchDetector;
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+
+fn noise() -> Vec<i16> {
+    to_i16(&white_noise(BLOCK, 8192.0, 0x1357_9bdf))
+}
+
+fn sine(freq: f32) -> Vec<i16> {
+    to_i16(&test_util::sine(BLOCK, freq, SAMPLE_RATE, 8000.0))
+}
+
+fn detector() -> PitchDetector {
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+    detector.set_min_correlation(0.0);
+    detector
+}
+
+#[test]
+fn clarity_separates_tones_from_noise() {
+    let mut detector = detector();
+    assert_eq!(detector.clarity(), 0.0);
+
+    detector.detect(&sine(220.0));
+    let tone = detector.clarity();
+    detector.detect(&noise());
+    let noise = detector.clarity();
+    assert!(tone > 0.95, "tone clarity {tone}");
+    assert!(noise < 0.3, "noise clarity {noise}");
+
+    detector.detect(&[0; BLOCK]);
+    assert_eq!(detector.clarity(), 0.0);
+}
+
+#[test]
+fn unclear_blocks_report_no_pitches() {
+    let mut detector = detector();
+    // Noise still pushes some lag over the low per-pitch floor.
+    assert!(!detector.detect(&noise()).is_empty());
+
+    detector.set_min_clarity(0.5);
+    assert!(detector.detect(&noise()).is_empty());
+    assert!(detector.clarity() > 0.0);
+    assert_eq!(detector.detect(&sine(220.0)).len(), 1);
+}
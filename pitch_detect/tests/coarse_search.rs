@@ -0,0 +1,60 @@
+//! The coarse-to-fine lag search against the exhaustive one.
+
+use echo_nlms::test_util::{sine, to_i16, white_noise};
+use pitch_detect::PitchDetector;
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+const MIN_HZ: f32 = 60.0;
+const MAX_HZ: f32 = 1000.0;
+
+/// A harmonic tone at `freq` with five partials and a little noise.
+fn tone(freq: f32) -> Vec<i16> {
+    let mut samples = white_noise(BLOCK, 1024.0, 0x9e37_79b9);
+    for harmonic in 1..=5 {
+        let partial = sine(
+            BLOCK,
+            freq * harmonic as f32,
+            SAMPLE_RATE,
+            6000.0 / harmonic as f32,
+        );
+        for (sample, partial) in samples.iter_mut().zip(partial) {
+            *sample += partial;
+        }
+    }
+    to_i16(&samples)
+}
+
+fn detector(coarse: Option<usize>) -> PitchDetector {
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    detector.set_min_correlation(0.3);
+    detector.set_coarse_search(coarse);
+    detector
+}
+
+#[test]
+fn coarse_search_matches_full_search() {
+    let mut full = detector(None);
+    for factor in [2, 4, 8] {
+        let mut coarse = detector(Some(factor));
+        for freq in [61.0, 82.4, 110.0, 196.0, 330.0, 440.0] {
+            let block = tone(freq);
+            let expected = full.detect(&block)[0];
+            let found = coarse.detect(&block)[0];
+            assert_eq!(found, expected, "{freq} Hz, factor {factor}");
+            assert!((found.freq - freq).abs() < freq * 0.01, "{freq} Hz");
+        }
+    }
+}
+
+#[test]
+fn coarse_search_reports_nothing_in_silence() {
+    let mut coarse = detector(Some(4));
+    assert!(coarse.detect(&[0; BLOCK]).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "decimation factor")]
+fn coarse_search_rejects_unit_factor() {
+    detector(Some(1));
+}
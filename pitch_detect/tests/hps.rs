@@ -0,0 +1,109 @@
+//! Harmonic product spectrum detection of tones and chords.
+
+use echo_nlms::test_util::{chord, sine, to_i16};
+use pitch_detect::HpsDetector;
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+const MIN_HZ: f32 = 60.0;
+const MAX_HZ: f32 = 1000.0;
+const HARMONICS: usize = 4;
+const NOTES: [f32; 3] = [220.0, 277.2, 329.6];
+
+/// Returns a block of `notes`, each with its first [`HARMONICS`] partials
+/// at equal amplitude.
+fn harmonic_chord(notes: &[f32]) -> Vec<i16> {
+    let partials: Vec<f32> = notes
+        .iter()
+        .flat_map(|&f0| (1..=HARMONICS).map(move |h| f0 * h as f32))
+        .collect();
+    to_i16(&chord(BLOCK, &partials, SAMPLE_RATE, 2000.0))
+}
+
+#[test]
+fn harmonic_tone_is_detected_within_a_few_hz() {
+    let mut detector = HpsDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    for f0 in [98.0, 196.0, 440.0, 880.0] {
+        let results = detector.detect(&harmonic_chord(&[f0]));
+        assert_eq!(results.len(), 1);
+        assert!(
+            (results[0].freq - f0).abs() < 2.0,
+            "{f0} Hz detected as {}",
+            results[0].freq
+        );
+        assert!((results[0].period - SAMPLE_RATE as f32 / results[0].freq).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn pure_sine_needs_a_single_harmonic() {
+    let mut detector = HpsDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    detector.set_harmonics(1);
+    let results = detector.detect(&to_i16(&sine(BLOCK, 440.0, SAMPLE_RATE, 8000.0)));
+    assert!((results[0].freq - 440.0).abs() < 2.0, "{results:?}");
+    assert!(results[0].correlation > 0.99);
+}
+
+#[test]
+fn every_chord_note_is_detected() {
+    let mut detector = HpsDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    detector.set_max_results(3);
+    let results = detector.detect(&harmonic_chord(&NOTES));
+    assert_eq!(results.len(), 3, "{results:?}");
+    for note in NOTES {
+        assert!(
+            results.iter().any(|pitch| (pitch.freq - note).abs() < 2.0),
+            "{note} Hz missing from {results:?}"
+        );
+    }
+    assert!(
+        results
+            .windows(2)
+            .all(|pair| pair[0].correlation >= pair[1].correlation),
+        "results are not strongest first: {results:?}"
+    );
+}
+
+#[test]
+fn max_results_caps_the_report() {
+    let samples = harmonic_chord(&NOTES);
+    let mut detector = HpsDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    for max_results in 0..=3 {
+        detector.set_max_results(max_results);
+        assert_eq!(detector.detect(&samples).len(), max_results);
+    }
+}
+
+#[test]
+fn min_strength_drops_weak_peaks() {
+    let samples = harmonic_chord(&NOTES);
+    let mut detector = HpsDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    detector.set_max_results(3);
+    let strongest = detector.detect(&samples)[0].correlation;
+    let weakest = detector.detect(&samples)[2].correlation;
+
+    let min_strength = 0.5 * (strongest + weakest);
+    detector.set_min_strength(min_strength);
+    let results = detector.detect(&samples);
+    assert!((1..3).contains(&results.len()), "{results:?}");
+    assert!(
+        results
+            .iter()
+            .all(|pitch| pitch.correlation >= min_strength)
+    );
+
+    // Strength is relative to the loudest bin, so nothing can reach above 1.
+    detector.set_min_strength(1.01);
+    assert!(detector.detect(&samples).is_empty());
+}
+
+#[test]
+fn empty_bin_range_reports_nothing() {
+    // Every fourth harmonic of 8 kHz lies above Nyquist.
+    let mut detector = HpsDetector::new(BLOCK, SAMPLE_RATE, 8000.0, 9000.0);
+    let samples = to_i16(&sine(BLOCK, 8500.0, SAMPLE_RATE, 8000.0));
+    assert!(detector.detect(&samples).is_empty());
+
+    detector.set_harmonics(1);
+    assert!(!detector.detect(&samples).is_empty());
+}
@@ -0,0 +1,48 @@
+//! MIDI note numbers and note names.
+
+use pitch_detect::{NoteName, hz_to_midi};
+
+#[test]
+fn concert_a_is_note_69() {
+    assert_eq!(hz_to_midi(440.0), (69, 0.0));
+    let (note, cents) = hz_to_midi(880.0);
+    assert_eq!(note, 81);
+    assert!(cents.abs() < 1e-3);
+}
+
+#[test]
+fn cents_carry_the_sign_of_the_deviation() {
+    let (note, cents) = hz_to_midi(450.0);
+    assert_eq!(note, 69);
+    assert!((cents - 38.9).abs() < 0.1, "{cents}");
+
+    let (note, cents) = hz_to_midi(430.0);
+    assert_eq!(note, 69);
+    assert!((cents + 39.8).abs() < 0.1, "{cents}");
+
+    // Past the quarter tone the next note up is nearer.
+    let (note, cents) = hz_to_midi(455.0);
+    assert_eq!(note, 70);
+    assert!(cents < 0.0, "{cents}");
+}
+
+#[test]
+fn out_of_range_notes_are_clamped() {
+    let (note, cents) = hz_to_midi(1.0);
+    assert_eq!(note, 0);
+    assert!(cents < -50.0, "{cents}");
+
+    let (note, cents) = hz_to_midi(20_000.0);
+    assert_eq!(note, 127);
+    assert!(cents > 50.0, "{cents}");
+}
+
+#[test]
+fn note_names_show_octave_and_cents() {
+    assert_eq!(NoteName(440.0).to_string(), "A4 +0c");
+    assert_eq!(NoteName(261.63).to_string(), "C4 +0c");
+    assert_eq!(NoteName(450.0).to_string(), "A4 +39c");
+    assert_eq!(NoteName(430.0).to_string(), "A4 -40c");
+    assert_eq!(NoteName(466.16).to_string(), "A#4 +0c");
+    assert_eq!(NoteName(27.5).to_string(), "A0 +0c");
+}
@@ -0,0 +1,62 @@
+//! Octave correction on tones the plain search places an octave low.
+
+use echo_nlms::test_util::{sine, to_i16, white_noise};
+use pitch_detect::PitchDetector;
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+
+/// A harmonic tone at `freq` with five partials and a little noise.
+fn tone(freq: f32) -> Vec<i16> {
+    let mut samples = white_noise(BLOCK, 1024.0, 0x9e37_79b9);
+    for harmonic in 1..=5 {
+        let partial = sine(
+            BLOCK,
+            freq * harmonic as f32,
+            SAMPLE_RATE,
+            6000.0 / harmonic as f32,
+        );
+        for (sample, partial) in samples.iter_mut().zip(partial) {
+            *sample += partial;
+        }
+    }
+    to_i16(&samples)
+}
+
+fn detect(freq: f32, octave_correction: bool) -> f32 {
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+    detector.set_octave_correction(octave_correction);
+    detector.detect(&tone(freq))[0].freq
+}
+
+#[test]
+fn period_double_is_moved_up_an_octave() {
+    // Without correction the period double wins on this block.
+    let uncorrected = detect(880.0, false);
+    assert!((uncorrected - 440.0).abs() < 4.4, "{uncorrected}");
+
+    let corrected = detect(880.0, true);
+    assert!((corrected - 880.0).abs() < 8.8, "{corrected}");
+}
+
+#[test]
+fn correct_pitches_are_left_alone() {
+    for freq in [65.0, 110.0, 220.0, 440.0, 523.0, 660.0] {
+        let corrected = detect(freq, true);
+        assert!(
+            (corrected - freq).abs() < freq * 0.01,
+            "{freq}: {corrected}"
+        );
+    }
+}
+
+#[test]
+fn terminates_at_the_shortest_lags() {
+    // A search range reaching up to a lag of two puts the current lag inside
+    // its own half-lag neighbourhood, which once made the correction spin.
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 23_000.0);
+    detector.set_octave_correction(true);
+    let tone = to_i16(&sine(BLOCK, 16_000.0, SAMPLE_RATE, 8000.0));
+    let freq = detector.detect(&tone)[0].freq;
+    assert!((freq - 16_000.0).abs() < 160.0, "{freq}");
+}
@@ -0,0 +1,67 @@
+//! Framing of a sample stream into overlapping analysis frames.
+
+use pitch_detect::OverlappingFrames;
+
+fn ramp(len: usize) -> Vec<i16> {
+    (1..=len as i16).collect()
+}
+
+#[test]
+fn first_frame_is_ready_after_one_hop() {
+    let mut frames = OverlappingFrames::new(8, 2);
+    frames.push(&[1]);
+    assert_eq!(frames.next_frame(), None);
+    frames.push(&[2]);
+    assert_eq!(frames.next_frame(), Some(&[0, 0, 0, 0, 0, 0, 1, 2][..]));
+    assert_eq!(frames.next_frame(), None);
+}
+
+#[test]
+fn frames_advance_by_the_hop() {
+    let mut frames = OverlappingFrames::new(8, 3);
+    let input = ramp(40);
+    let mut ends = Vec::new();
+    // Push in blocks unrelated to the hop to exercise the buffering.
+    for block in input.chunks(7) {
+        frames.push(block);
+        while let Some(frame) = frames.next_frame() {
+            assert_eq!(frame.len(), 8);
+            let last = *frame.last().unwrap();
+            assert!(
+                frame
+                    .windows(2)
+                    .all(|pair| pair[1] == pair[0] + 1 || pair[0] == 0)
+            );
+            ends.push(last);
+        }
+    }
+    // One frame per completed hop, each ending three samples after the last.
+    assert_eq!(ends, (1..=13).map(|n| n * 3).collect::<Vec<_>>());
+}
+
+#[test]
+fn full_hop_splits_into_disjoint_blocks() {
+    let mut frames = OverlappingFrames::new(4, 4);
+    let input = ramp(12);
+    frames.push(&input);
+    for expected in input.chunks(4) {
+        assert_eq!(frames.next_frame(), Some(expected));
+    }
+    assert_eq!(frames.next_frame(), None);
+}
+
+#[test]
+fn reset_returns_to_silence() {
+    let mut frames = OverlappingFrames::new(4, 2);
+    frames.push(&ramp(5));
+    while frames.next_frame().is_some() {}
+    frames.reset();
+    frames.push(&[9, 9]);
+    assert_eq!(frames.next_frame(), Some(&[0, 0, 9, 9][..]));
+}
+
+#[test]
+#[should_panic(expected = "hop")]
+fn hop_longer_than_frame_is_rejected() {
+    OverlappingFrames::new(4, 5);
+}
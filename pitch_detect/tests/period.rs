@@ -0,0 +1,69 @@
+//! Reported periods and the searched lag range.
+
+use echo_nlms::test_util::{self, to_i16};
+use pitch_detect::{PitchDetector, PitchTracker, detect_pitches_yin};
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+
+fn sine(period: f32) -> Vec<i16> {
+    to_i16(&test_util::sine(
+        BLOCK,
+        SAMPLE_RATE as f32 / period,
+        SAMPLE_RATE,
+        8000.0,
+    ))
+}
+
+#[test]
+fn period_range_covers_the_frequency_range() {
+    let detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+    assert_eq!(detector.period_range(), 48..=800);
+}
+
+#[test]
+fn reported_period_matches_the_signal() {
+    for period in [61.3, 137.5, 402.8] {
+        let samples = sine(period);
+        let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+        let pitch = detector.detect(&samples)[0];
+        // Windowing biases long lags slightly; allow half a percent.
+        assert!(
+            (pitch.period - period).abs() < period * 0.005,
+            "period {} for {period}",
+            pitch.period
+        );
+        assert!((pitch.freq * pitch.period - SAMPLE_RATE as f32).abs() < 1.0);
+
+        let yin = detect_pitches_yin(&samples, SAMPLE_RATE, 60.0, 1000.0, 0.15).unwrap();
+        assert!(
+            (yin.period - period).abs() < 0.5,
+            "YIN period {}",
+            yin.period
+        );
+    }
+}
+
+#[test]
+fn parabolic_refinement_beats_the_integer_lag() {
+    for period in [83.3, 120.5, 157.7, 240.4] {
+        let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+        let refined = detector.detect(&sine(period))[0].period;
+        let lag = refined.round();
+        assert!(
+            (refined - period).abs() < (lag - period).abs(),
+            "refined {refined} and lag {lag} for {period}"
+        );
+    }
+}
+
+#[test]
+fn tracker_keeps_period_consistent_with_the_median() {
+    let mut tracker = PitchTracker::new(1, 3);
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+    for period in [100.0, 110.0, 105.0] {
+        let pitches = detector.detect(&sine(period)).to_vec();
+        let voice = tracker.update(&pitches)[0].unwrap();
+        assert!((voice.freq * voice.period - SAMPLE_RATE as f32).abs() < 1.0);
+    }
+}
@@ -0,0 +1,58 @@
+//! Band-limiting blocks before analysis.
+
+use echo_nlms::test_util::{sine, to_i16, white_noise};
+use pitch_detect::PitchDetector;
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+const BLOCKS: usize = 16;
+const TONE_HZ: f32 = 220.0;
+
+/// White noise through four cascaded one-pole low-passes at 15 Hz, scaled to
+/// an RMS of `rms`: rumble well below the tone but loud enough to dominate
+/// the unfiltered autocorrelation.
+fn rumble(len: usize, rms: f32) -> Vec<f32> {
+    let mut rumble = white_noise(len, 1.0, 0x0f11_7e01);
+    let pole = (-std::f32::consts::TAU * 15.0 / SAMPLE_RATE as f32).exp();
+    for _ in 0..4 {
+        let mut state = 0.0;
+        for sample in &mut rumble {
+            state = pole * state + (1.0 - pole) * *sample;
+            *sample = state;
+        }
+    }
+    let current = (rumble.iter().map(|s| s * s).sum::<f32>() / len as f32).sqrt();
+    rumble.iter().map(|s| s * rms / current).collect()
+}
+
+/// Returns the worst pitch error over all blocks, in Hz, of a detector
+/// using `prefilter`.
+fn worst_error(prefilter: Option<(f32, f32)>) -> f32 {
+    let tone = sine(BLOCKS * BLOCK, TONE_HZ, SAMPLE_RATE, 2000.0);
+    let samples = to_i16(
+        &tone
+            .iter()
+            .zip(rumble(tone.len(), 6500.0))
+            .map(|(tone, rumble)| tone + rumble)
+            .collect::<Vec<_>>(),
+    );
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+    detector.set_prefilter(prefilter);
+    samples
+        .chunks(BLOCK)
+        .map(|block| {
+            detector
+                .detect(block)
+                .first()
+                .map_or(f32::INFINITY, |pitch| (pitch.freq - TONE_HZ).abs())
+        })
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn prefilter_removes_low_frequency_rumble() {
+    let unfiltered = worst_error(None);
+    assert!(unfiltered > 10.0, "rumble too tame: {unfiltered} Hz off");
+    let filtered = worst_error(Some((80.0, 1000.0)));
+    assert!(filtered < 1.0, "{filtered} Hz off");
+}
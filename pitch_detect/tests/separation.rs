@@ -0,0 +1,36 @@
+//! Merging of nearby pitches into one voice.
+
+use echo_nlms::test_util::{chord, to_i16};
+use pitch_detect::{PitchDetector, PitchSeparation};
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+
+#[test]
+fn parses_and_displays_hz_and_cents() {
+    assert_eq!("5hz".parse(), Ok(PitchSeparation::Hz(5.0)));
+    assert_eq!("2.5 Hz".parse(), Ok(PitchSeparation::Hz(2.5)));
+    assert_eq!("50cents".parse(), Ok(PitchSeparation::Cents(50.0)));
+    for separation in [PitchSeparation::Hz(5.0), PitchSeparation::Cents(12.5)] {
+        assert_eq!(separation.to_string().parse(), Ok(separation));
+    }
+    for invalid in ["5", "cents", "-1hz", "infhz", "5 semitones"] {
+        assert!(invalid.parse::<PitchSeparation>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn default_merges_pitches_within_5_hz() {
+    assert_eq!(PitchSeparation::default(), PitchSeparation::Hz(5.0));
+
+    let samples = to_i16(&chord(BLOCK, &[220.0, 330.0], SAMPLE_RATE, 4000.0));
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, 60.0, 1000.0);
+    detector.set_max_results(4);
+    let pitches = detector.detect(&samples);
+    assert!(pitches.len() > 1);
+    for (idx, a) in pitches.iter().enumerate() {
+        for b in &pitches[idx + 1..] {
+            assert!((a.freq - b.freq).abs() > 5.0, "{} and {}", a.freq, b.freq);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Median smoothing and voice slot assignment in the pitch tracker.
+
+use pitch_detect::{PitchResult, PitchTracker};
+
+const SAMPLE_RATE: f32 = 48_000.0;
+
+fn pitch(freq: f32) -> PitchResult {
+    PitchResult {
+        freq,
+        correlation: 0.9,
+        period: SAMPLE_RATE / freq,
+    }
+}
+
+fn freqs(voices: &[Option<PitchResult>]) -> Vec<Option<f32>> {
+    voices.iter().map(|voice| voice.map(|p| p.freq)).collect()
+}
+
+#[test]
+fn one_frame_outlier_is_rejected_by_the_median() {
+    let mut tracker = PitchTracker::new(1, 5);
+    for _ in 0..3 {
+        tracker.update(&[pitch(220.0)]);
+    }
+
+    // A glitch within the matching range joins the voice's history but
+    // cannot move its median.
+    let smoothed = tracker.update(&[pitch(250.0)])[0].unwrap();
+    assert_eq!(smoothed.freq, 220.0);
+    assert!((smoothed.period - SAMPLE_RATE / 220.0).abs() < 1e-3);
+
+    assert_eq!(freqs(tracker.update(&[pitch(220.0)])), [Some(220.0)]);
+}
+
+#[test]
+fn window_of_one_passes_pitches_through() {
+    let mut tracker = PitchTracker::new(1, 1);
+    tracker.update(&[pitch(220.0)]);
+    assert_eq!(freqs(tracker.update(&[pitch(250.0)])), [Some(250.0)]);
+}
+
+#[test]
+fn voice_keeps_its_slot_when_results_are_reordered() {
+    let mut tracker = PitchTracker::new(2, 3);
+    assert_eq!(
+        freqs(tracker.update(&[pitch(220.0), pitch(330.0)])),
+        [Some(220.0), Some(330.0)]
+    );
+    assert_eq!(
+        freqs(tracker.update(&[pitch(331.0), pitch(221.0)])),
+        [Some(220.5), Some(330.5)]
+    );
+    assert_eq!(freqs(tracker.update(&[pitch(332.0)])), [None, Some(331.0)]);
+}
+
+#[test]
+fn missed_frame_clears_the_history() {
+    let mut tracker = PitchTracker::new(1, 5);
+    for _ in 0..3 {
+        tracker.update(&[pitch(220.0)]);
+    }
+    assert_eq!(freqs(tracker.update(&[])), [None]);
+
+    // Without the gap the median would still sit at 220 Hz.
+    assert_eq!(freqs(tracker.update(&[pitch(250.0)])), [Some(250.0)]);
+}
+
+#[test]
+fn reset_forgets_every_voice() {
+    let mut tracker = PitchTracker::new(2, 5);
+    for _ in 0..3 {
+        tracker.update(&[pitch(220.0), pitch(330.0)]);
+    }
+    tracker.reset();
+    assert_eq!(
+        freqs(tracker.update(&[pitch(250.0), pitch(350.0)])),
+        [Some(250.0), Some(350.0)]
+    );
+}
@@ -0,0 +1,70 @@
+//! YIN against autocorrelation on signals prone to octave errors.
+
+use echo_nlms::test_util::{sine, to_i16, white_noise};
+use pitch_detect::{PitchDetector, detect_pitches_yin};
+
+const SAMPLE_RATE: u32 = 48_000;
+const BLOCK: usize = 4096;
+const MIN_HZ: f32 = 60.0;
+const MAX_HZ: f32 = 1000.0;
+
+/// Returns a block of harmonics of `f0` with the relative amplitudes
+/// `weights`, plus white noise of peak `noise`.
+fn voice(f0: f32, weights: &[f32], noise: f32) -> Vec<i16> {
+    let mut samples = white_noise(BLOCK, noise, 0x0419_0001);
+    for (harmonic, &weight) in weights.iter().enumerate() {
+        let partial = sine(
+            BLOCK,
+            f0 * (harmonic + 1) as f32,
+            SAMPLE_RATE,
+            3000.0 * weight,
+        );
+        for (sample, partial) in samples.iter_mut().zip(partial) {
+            *sample += partial;
+        }
+    }
+    to_i16(&samples)
+}
+
+/// Counts the fundamentals in a sweep over the lower two octaves of the
+/// range for which `detect` misses by half an octave or more.
+fn errors(weights: &[f32], noise: f32, mut detect: impl FnMut(&[i16]) -> Option<f32>) -> usize {
+    (0..24)
+        .map(|step| 70.0 * 2f32.powf(step as f32 / 12.0))
+        .filter(|&f0| {
+            detect(&voice(f0, weights, noise)).is_none_or(|freq| (freq / f0).log2().abs() >= 0.5)
+        })
+        .count()
+}
+
+fn compare(weights: &[f32], noise: f32) -> (usize, usize) {
+    let mut detector = PitchDetector::new(BLOCK, SAMPLE_RATE, MIN_HZ, MAX_HZ);
+    let autocorrelation = errors(weights, noise, |samples| {
+        detector.detect(samples).first().map(|pitch| pitch.freq)
+    });
+    let yin = errors(weights, noise, |samples| {
+        detect_pitches_yin(samples, SAMPLE_RATE, MIN_HZ, MAX_HZ, 0.15).map(|pitch| pitch.freq)
+    });
+    (autocorrelation, yin)
+}
+
+#[test]
+fn yin_has_fewer_errors_on_low_sines() {
+    let (autocorrelation, yin) = compare(&[1.0], 0.0);
+    assert_eq!(yin, 0);
+    assert!(
+        autocorrelation > yin,
+        "autocorrelation {autocorrelation}, YIN {yin}"
+    );
+}
+
+#[test]
+fn yin_has_fewer_errors_on_a_breathy_weak_fundamental() {
+    // The second harmonic dominates, as in some vowels.
+    let (autocorrelation, yin) = compare(&[0.2, 1.0, 0.0, 0.5], 1500.0);
+    assert_eq!(yin, 0);
+    assert!(
+        autocorrelation > yin,
+        "autocorrelation {autocorrelation}, YIN {yin}"
+    );
+}
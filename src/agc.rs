@@ -0,0 +1,153 @@
+//! Automatic gain control for the capture path.
+
+use crate::level::rms_level;
+
+/// Automatic gain control normalizing the RMS level of a mono signal towards
+/// a target.
+///
+/// Each block sets the gain that would bring its RMS level to the target,
+/// limited to `max_gain_db` of boost, and the applied gain glides towards it
+/// sample by sample. The gain rises over the attack time, so a quiet input or
+/// a pause is not pumped up at once, and falls over the release time, so a
+/// loud onset is not overdriven for long.
+#[derive(Clone, Debug)]
+pub struct Agc {
+    target: f32,
+    max_gain: f32,
+    attack: f32,
+    release: f32,
+    gain: f32,
+}
+
+impl Agc {
+    /// Creates an AGC at unity gain aiming for an RMS level of `target_db`
+    /// dBFS, with time constants in milliseconds at `sample_rate`.
+    pub fn new(
+        target_db: f32,
+        max_gain_db: f32,
+        attack_ms: f32,
+        release_ms: f32,
+        sample_rate: u32,
+    ) -> Self {
+        assert!(
+            target_db.is_finite() && target_db <= 0.0,
+            "target level must not exceed full scale"
+        );
+        assert!(
+            max_gain_db.is_finite() && max_gain_db >= 0.0,
+            "maximum gain must be non-negative"
+        );
+        assert!(
+            attack_ms.is_finite() && attack_ms >= 0.0,
+            "attack time must be non-negative"
+        );
+        assert!(
+            release_ms.is_finite() && release_ms >= 0.0,
+            "release time must be non-negative"
+        );
+        let coefficient = |ms: f32| {
+            let samples = ms * sample_rate as f32 / 1000.0;
+            if samples > 0.0 {
+                1.0 - (-1.0 / samples).exp()
+            } else {
+                1.0
+            }
+        };
+        Self {
+            target: 10.0f32.powf(target_db / 20.0),
+            max_gain: 10.0f32.powf(max_gain_db / 20.0),
+            attack: coefficient(attack_ms),
+            release: coefficient(release_ms),
+            gain: 1.0,
+        }
+    }
+
+    /// Returns the gain applied to the last sample.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Applies the gain to `block` in place, saturating at full scale.
+    pub fn process(&mut self, block: &mut [i16]) {
+        let level = rms_level(block);
+        let desired = if level > 0.0 {
+            (self.target / level).min(self.max_gain)
+        } else {
+            self.max_gain
+        };
+        let coefficient = if desired > self.gain {
+            self.attack
+        } else {
+            self.release
+        };
+        for sample in block {
+            self.gain += (desired - self.gain) * coefficient;
+            *sample = (*sample as f32 * self.gain)
+                .round()
+                .clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::rms_dbfs;
+
+    const SAMPLE_RATE: u32 = 48_000;
+    /// 10 ms at 48 kHz.
+    const BLOCK: usize = 480;
+
+    /// Returns a constant block at `db` dBFS.
+    fn block_at(db: f32) -> Vec<i16> {
+        vec![(10.0f32.powf(db / 20.0) * i16::MAX as f32).round() as i16; BLOCK]
+    }
+
+    fn to_db(gain: f32) -> f32 {
+        20.0 * gain.log10()
+    }
+
+    #[test]
+    fn quiet_input_is_brought_to_the_target() {
+        let mut agc = Agc::new(-20.0, 30.0, 10.0, 10.0, SAMPLE_RATE);
+        for _ in 0..19 {
+            agc.process(&mut block_at(-40.0));
+        }
+        let mut block = block_at(-40.0);
+        agc.process(&mut block);
+        assert!((to_db(agc.gain()) - 20.0).abs() < 0.1, "{}", agc.gain());
+        assert!((rms_dbfs(&block) + 20.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn boost_is_capped_at_the_maximum_gain() {
+        let mut agc = Agc::new(-20.0, 12.0, 10.0, 10.0, SAMPLE_RATE);
+        for _ in 0..50 {
+            agc.process(&mut block_at(-60.0));
+            assert!(to_db(agc.gain()) <= 12.0 + 1e-4);
+        }
+        assert!((to_db(agc.gain()) - 12.0).abs() < 0.01);
+
+        // Silence asks for the whole boost too, but no more.
+        for _ in 0..50 {
+            agc.process(&mut [0; BLOCK]);
+        }
+        assert!((to_db(agc.gain()) - 12.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn attack_and_release_run_at_their_own_rates() {
+        // Slow attack, fast release: 100 ms against 5 ms.
+        let mut rising = Agc::new(-20.0, 20.0, 100.0, 5.0, SAMPLE_RATE);
+        rising.process(&mut block_at(-40.0));
+        let rise = (rising.gain() - 1.0) / (10.0 - 1.0);
+
+        let mut falling = Agc::new(-20.0, 20.0, 100.0, 5.0, SAMPLE_RATE);
+        falling.process(&mut block_at(0.0));
+        let fall = (1.0 - falling.gain()) / (1.0 - 0.1);
+
+        // One block is a tenth of the attack time but two release times.
+        assert!((rise - (1.0 - (-0.1f32).exp())).abs() < 0.01, "rise {rise}");
+        assert!((fall - (1.0 - (-2.0f32).exp())).abs() < 0.01, "fall {fall}");
+    }
+}
@@ -1,146 +1,545 @@
-use alsa::nix::errno::Errno;
-use alsa::pcm::{Access, Format, Frames, HwParams, IO, PCM};
-use alsa::{Direction, ValueOr};
-use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use anyhow::{Result, ensure};
 use clap::Parser;
-use echo_nlms::NlmsCanceller;
+use echo_nlms::{
+    ComfortNoise, DoubleTalkScaling, EchoCanceller, FdafCanceller, GeigelDetector, NlmsCanceller,
+    NonlinearCanceller, ResidualSuppressor, SpectralDenoiser,
+};
+use log::{debug, info, warn};
+use myjammer::audio::{
+    self, AlsaCapture, AlsaIo, AudioIo, EndOfInput, PcmFormat, WavIo, WavRecorder, XrunCounts,
+};
+use myjammer::dc_blocker::DcBlocker;
+use myjammer::fade::FadeIn;
+use myjammer::level::{LevelMeter, rms_dbfs};
+use myjammer::load::LoadMeter;
+use myjammer::{calibration, logging, signal};
 
 const SAMPLE_RATE: u32 = 48_000;
 const CHUNK_SIZE: usize = 4096;
-const DELAY_MS: u32 = 150;
+const DELAY_MS: f32 = 150.0;
+const MAX_FEEDBACK: f32 = 0.95;
 const AEC_TAPS: usize = 2048;
+const FDAF_PARTITION_LEN: usize = 256;
 const NLMS_STEP_SIZE: f32 = 0.1;
-const MIN_RENDER_LEVEL: f32 = 0.002;
+const NONLINEAR_ORDER: usize = 3;
+const WHITENING_ORDER: usize = 8;
+const MIN_RENDER_LEVEL_DB: f32 = -54.0;
 const DOUBLE_TALK_RATIO: f32 = 2.5;
+// Geigel compares single capture samples against the render peak, so it
+// cannot share the RMS ratio above: a peak is well above the RMS level for
+// speech and noise alike. 0.5 is the classic Geigel setting, which assumes
+// the speaker-to-mic path loses at least 6 dB; anything louder than half the
+// recent render peak must then contain near-end sound.
+const GEIGEL_THRESHOLD: f32 = 0.5;
+const DOUBLE_TALK_HOLD_BLOCKS: usize = 2;
+const DOUBLE_TALK_ONSET: f32 = 1.0;
+const SUPPRESSOR_MIN_GAIN: f32 = 0.1;
+const SUPPRESSOR_OVERDRIVE: f32 = 1.5;
+const LOAD_WARNING: f32 = 0.8;
+const METER_INTERVAL_MS: f32 = 500.0;
+const FADE_IN_MS: f32 = 20.0;
+const DC_BLOCKER_CUTOFF_HZ: f32 = 10.0;
+const DENOISE_FRAME: usize = 512;
+const DENOISE_OVER_SUBTRACTION: f32 = 2.0;
+const DENOISE_FLOOR: f32 = 0.1;
 
 #[derive(Parser, Debug)]
 #[command(name = "delay-jammer")]
 struct Args {
+    /// Log more detail: `-v` for debug output, `-vv` for trace. `RUST_LOG`
+    /// overrides this.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log the RMS and peak levels of the input and output twice a second,
+    /// to help set the microphone gain and spot clipping.
+    #[arg(long)]
+    meter: bool,
+
+    /// Fade playback in over this many milliseconds after starting, so it
+    /// does not open with a click (0 disables the fade).
+    #[arg(long, default_value_t = FADE_IN_MS)]
+    fade_in_ms: f32,
+
     /// Disable adaptive echo suppression (use when monitoring via headphones).
     #[arg(long)]
     disable_echo: bool,
+
+    /// Use the frequency-domain block filter instead of sample-wise NLMS.
+    #[arg(long)]
+    fdaf: bool,
+
+    /// Scale the NLMS step size down automatically as the filter converges
+    /// (VSS-NLMS), for a lower residual once settled.
+    #[arg(long, conflicts_with = "fdaf")]
+    variable_step: bool,
+
+    /// Model loudspeaker distortion by also adapting filters on the squared
+    /// and cubed render signal, at about three times the cost.
+    #[arg(long, conflicts_with_all = ["fdaf", "variable_step"])]
+    nonlinear: bool,
+
+    /// Adapt the NLMS filter on prewhitened render and capture, converging
+    /// much faster on speech at about twice the cost.
+    #[arg(long, conflicts_with_all = ["fdaf", "nonlinear"])]
+    whiten: bool,
+
+    /// Instead of freezing adaptation while both ends talk, scale the NLMS
+    /// step size down smoothly as the input grows louder than the played
+    /// signal, for quicker reconvergence after double-talk.
+    #[arg(long, conflicts_with_all = ["fdaf", "nonlinear"])]
+    soft_double_talk: bool,
+
+    /// Input-to-playback level ratio above which `--soft-double-talk` starts
+    /// reducing the step size.
+    #[arg(long, default_value_t = DOUBLE_TALK_ONSET)]
+    double_talk_onset: f32,
+
+    /// Input-to-playback level ratio at which `--soft-double-talk` stops
+    /// adaptation.
+    #[arg(long, default_value_t = DOUBLE_TALK_RATIO)]
+    double_talk_cutoff: f32,
+
+    /// Add TPDF dither when the echo-cancelled signal is rounded to 16 bits,
+    /// trading quantization distortion on quiet passages for a faint noise
+    /// floor. Applies to the sample-wise NLMS canceller only.
+    #[arg(long, conflicts_with_all = ["fdaf", "nonlinear"])]
+    dither: bool,
+
+    /// Attenuate the echo left over after cancellation, filling the gaps with
+    /// comfort noise.
+    #[arg(long)]
+    residual_suppression: bool,
+
+    /// Reduce stationary background noise in the captured signal by spectral
+    /// subtraction before any other processing.
+    #[arg(long)]
+    denoise: bool,
+
+    /// Multiple of the estimated noise spectrum removed by `--denoise`.
+    #[arg(long, default_value_t = DENOISE_OVER_SUBTRACTION)]
+    denoise_over_subtraction: f32,
+
+    /// Smallest fraction of each frequency's magnitude `--denoise` keeps
+    /// (0.0 to 1.0).
+    #[arg(long, default_value_t = DENOISE_FLOOR)]
+    denoise_floor: f32,
+
+    /// ALSA device to record from.
+    #[arg(long, default_value = "default")]
+    capture_device: String,
+
+    /// ALSA device to play back on.
+    #[arg(long, default_value = "default")]
+    playback_device: String,
+
+    /// ALSA device capturing what the loudspeaker actually plays, such as a
+    /// hardware loopback, to use as the echo canceller's reference instead
+    /// of the output sent to the playback device.
+    #[arg(long, conflicts_with = "input")]
+    render_loopback: Option<String>,
+
+    /// Print the available ALSA PCM devices and their capabilities, then
+    /// exit.
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Open the playback device in stereo and send the mono output to both
+    /// channels.
+    #[arg(long)]
+    mono_to_stereo: bool,
+
+    /// Sample format to open the ALSA devices with (s16, s32 or float).
+    #[arg(long, default_value_t = PcmFormat::S16)]
+    format: PcmFormat,
+
+    /// Echo canceller filter length in taps (at most one second of audio).
+    #[arg(long, default_value_t = AEC_TAPS)]
+    aec_taps: usize,
+
+    /// Echo canceller step size; larger values converge faster but are
+    /// noisier. NLMS is only stable for values below 2.
+    #[arg(long, default_value_t = NLMS_STEP_SIZE)]
+    nlms_mu: f32,
+
+    /// Delay between the captured and the played signal, in milliseconds.
+    /// Fractional delays are interpolated.
+    #[arg(long, default_value_t = DELAY_MS)]
+    delay_ms: f32,
+
+    /// Balance between the cleaned input (0.0) and the delayed signal (1.0).
+    #[arg(long, default_value_t = 1.0)]
+    mix: f32,
+
+    /// Portion of the delayed signal fed back into the delay line, producing
+    /// repeats that decay by this factor each time (0.0 to 0.95).
+    #[arg(long, default_value_t = 0.0)]
+    feedback: f32,
+
+    /// Measure the round-trip latency with a test sweep at startup and use it
+    /// as the delay length instead of `--delay-ms`.
+    #[arg(long, conflicts_with = "input")]
+    calibrate: bool,
+
+    /// Process a mono 16-bit WAV file instead of capturing from ALSA.
+    #[arg(long, requires = "output")]
+    input: Option<PathBuf>,
+
+    /// Write the processed audio to a WAV file instead of playing it back.
+    #[arg(long, requires = "input")]
+    output: Option<PathBuf>,
+
+    /// Record the echo canceller's render reference, the raw captured input
+    /// and the canceller's residual as the three channels of this WAV file,
+    /// for reproducing cancellation problems offline.
+    #[arg(long)]
+    wav_record_debug: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    run(args.disable_echo)
+    logging::init(args.verbose);
+    signal::install_interrupt_handler()?;
+    run(&args)
 }
 
-fn run(disable_echo: bool) -> Result<()> {
-    let capture = open_pcm(Direction::Capture).context("failed to open capture PCM")?;
-    let playback = open_pcm(Direction::Playback).context("failed to open playback PCM")?;
+fn run(args: &Args) -> Result<()> {
+    if args.list_devices {
+        for device in audio::list_devices()? {
+            println!("{device}");
+        }
+        return Ok(());
+    }
+
+    ensure!(
+        args.delay_ms.is_finite() && args.delay_ms > 0.0,
+        "delay must be positive"
+    );
+    ensure!(
+        (0.0..=1.0).contains(&args.mix),
+        "mix must be between 0.0 and 1.0"
+    );
+    ensure!(
+        (0.0..=MAX_FEEDBACK).contains(&args.feedback),
+        "feedback must be between 0.0 and {}",
+        MAX_FEEDBACK
+    );
+    ensure!(
+        (1..=SAMPLE_RATE as usize).contains(&args.aec_taps),
+        "AEC taps must be between 1 and {}",
+        SAMPLE_RATE
+    );
+    ensure!(
+        args.nlms_mu > 0.0 && args.nlms_mu < 2.0,
+        "NLMS step size must be between 0 and 2 (exclusive)"
+    );
+    ensure!(
+        args.double_talk_onset.is_finite() && args.double_talk_onset > 0.0,
+        "double-talk onset must be positive"
+    );
+    ensure!(
+        args.double_talk_cutoff.is_finite() && args.double_talk_cutoff > args.double_talk_onset,
+        "double-talk cutoff must be greater than the onset"
+    );
+    ensure!(
+        args.denoise_over_subtraction.is_finite() && args.denoise_over_subtraction >= 0.0,
+        "denoise over-subtraction must be non-negative"
+    );
+    ensure!(
+        (0.0..=1.0).contains(&args.denoise_floor),
+        "denoise floor must be between 0.0 and 1.0"
+    );
+    ensure!(
+        args.fade_in_ms.is_finite() && args.fade_in_ms >= 0.0,
+        "fade-in time must be non-negative"
+    );
+    let mut delay_frames = (args.delay_ms * SAMPLE_RATE as f32 / 1000.0).max(1.0);
+
+    if let (Some(input), Some(output)) = (&args.input, &args.output) {
+        let mut io = WavIo::open(input, output, SAMPLE_RATE, 1)?;
+        process(&mut io, None, args, CHUNK_SIZE, delay_frames)?;
+        return io.finish();
+    }
 
-    let capture_io = capture.io_i16().context("capture IO handle")?;
-    let playback_io = playback.io_i16().context("playback IO handle")?;
+    let mut io = AlsaIo::open(
+        &args.capture_device,
+        &args.playback_device,
+        args.format,
+        SAMPLE_RATE,
+        CHUNK_SIZE,
+        1,
+        args.mono_to_stereo,
+    )?;
+    let block_len = io.period_frames();
+    // Opened before calibrating, so the link starts both captures together.
+    // The loopback overruns while the sweep plays, and recovering from that
+    // restarts both in step.
+    let mut loopback = args
+        .render_loopback
+        .as_deref()
+        .map(|device| AlsaCapture::open(device, &io, SAMPLE_RATE))
+        .transpose()?;
+    if args.calibrate {
+        match calibration::measure_latency(&mut io, block_len, 1, SAMPLE_RATE)? {
+            Some(latency) => {
+                info!(
+                    "Measured round-trip latency: {:.1} ms ({} samples)",
+                    latency as f32 * 1000.0 / SAMPLE_RATE as f32,
+                    latency
+                );
+                delay_frames = latency.max(1) as f32;
+            }
+            None => warn!(
+                "Calibration sweep not heard; keeping the {} ms delay",
+                args.delay_ms
+            ),
+        }
+    }
+    process(&mut io, loopback.as_mut(), args, block_len, delay_frames)?;
+    if let Some(loopback) = loopback {
+        loopback.finish()?;
+    }
+    io.finish()
+}
 
-    let mut input = [0i16; CHUNK_SIZE];
-    let mut cleaned = [0i16; CHUNK_SIZE];
-    let mut output = [0i16; CHUNK_SIZE];
-    let mut render_history = [0i16; CHUNK_SIZE];
+/// Runs the delay loop in blocks of `block_len` frames until the input runs
+/// out or Ctrl-C is pressed. The echo canceller's reference is read from
+/// `loopback` if given, or else is the previous block of output.
+fn process(
+    io: &mut impl AudioIo,
+    mut loopback: Option<&mut AlsaCapture>,
+    args: &Args,
+    block_len: usize,
+    delay_frames: f32,
+) -> Result<()> {
+    let mut input = vec![0i16; block_len];
+    let mut cleaned = vec![0i16; block_len];
+    let mut output = vec![0i16; block_len];
+    let mut render_history = vec![0i16; block_len];
 
-    let delay_frames = ((SAMPLE_RATE as u64 * DELAY_MS as u64) / 1000).max(1) as usize;
-    let mut delay_line = vec![0i16; delay_frames];
-    let mut delay_pos = 0usize;
+    let mut delay_line = DelayLine::new(delay_frames);
 
-    let mut canceller = if disable_echo {
+    let mut denoiser = args.denoise.then(|| {
+        SpectralDenoiser::new(
+            DENOISE_FRAME,
+            args.denoise_over_subtraction,
+            args.denoise_floor,
+        )
+    });
+    let mut canceller: Option<Box<dyn EchoCanceller>> = if args.disable_echo {
         None
+    } else if args.fdaf {
+        Some(Box::new(FdafCanceller::new(
+            args.aec_taps,
+            FDAF_PARTITION_LEN,
+            args.nlms_mu,
+        )))
+    } else if args.nonlinear {
+        Some(Box::new(NonlinearCanceller::new(
+            args.aec_taps,
+            NONLINEAR_ORDER,
+            args.nlms_mu,
+        )))
     } else {
-        Some(NlmsCanceller::new(AEC_TAPS, NLMS_STEP_SIZE))
+        let mut nlms = NlmsCanceller::new(args.aec_taps, args.nlms_mu);
+        nlms.set_variable_step(args.variable_step);
+        nlms.set_dither(args.dither);
+        if args.whiten {
+            nlms.set_whitening(WHITENING_ORDER);
+        }
+        if args.soft_double_talk {
+            nlms.set_double_talk_scaling(Some(DoubleTalkScaling::new(
+                args.double_talk_onset,
+                args.double_talk_cutoff,
+            )));
+        }
+        Some(Box::new(nlms))
     };
+    let mut suppressor = (args.residual_suppression && !args.disable_echo).then(|| {
+        (
+            ResidualSuppressor::new(SUPPRESSOR_MIN_GAIN, SUPPRESSOR_OVERDRIVE),
+            ComfortNoise::new(),
+        )
+    });
+    let mut detector =
+        GeigelDetector::new(args.aec_taps, GEIGEL_THRESHOLD, DOUBLE_TALK_HOLD_BLOCKS);
+
+    let mut reported_xruns = XrunCounts::default();
+    let mut reported_loopback_overruns = 0;
 
-    loop {
-        read_chunk(&capture_io, &capture, &mut input)?;
+    let mut blocks = 0u64;
+    let mut meter = LoadMeter::new(block_len, SAMPLE_RATE);
+    let mut level_meters = args.meter.then(|| {
+        (
+            LevelMeter::new(METER_INTERVAL_MS, SAMPLE_RATE, 1),
+            LevelMeter::new(METER_INTERVAL_MS, SAMPLE_RATE, 1),
+        )
+    });
+    let mut fade_in = FadeIn::from_ms(args.fade_in_ms, SAMPLE_RATE, 1);
+    let mut dc_blocker = DcBlocker::new(DC_BLOCKER_CUTOFF_HZ, SAMPLE_RATE);
+    // The raw input is only kept while recording.
+    let mut debug_recording = args
+        .wav_record_debug
+        .as_deref()
+        .map(|path| WavRecorder::create(path, 3, SAMPLE_RATE))
+        .transpose()?
+        .map(|recorder| (recorder, vec![0i16; block_len]));
+
+    while !signal::interrupted() {
+        match io.read(&mut input) {
+            Err(err) if err.is::<EndOfInput>() => break,
+            result => result?,
+        }
+        if let Some(loopback) = loopback.as_mut() {
+            loopback.read(&mut render_history)?;
+        }
+        meter.start();
+        let xruns = io.xruns();
+        if xruns != reported_xruns {
+            warn!("xruns: {}", xruns);
+            reported_xruns = xruns;
+        }
+        if let Some(loopback) = loopback.as_ref()
+            && loopback.overruns() != reported_loopback_overruns
+        {
+            reported_loopback_overruns = loopback.overruns();
+            warn!("loopback capture overruns: {}", reported_loopback_overruns);
+        }
+        let input_levels = level_meters
+            .as_mut()
+            .and_then(|(input_meter, _)| input_meter.push(&input));
+        if let Some((_, raw)) = debug_recording.as_mut() {
+            raw.copy_from_slice(&input);
+        }
+        // Metered before, so the meter still shows what the ADC delivered.
+        dc_blocker.process(&mut input);
+        if let Some(denoiser) = denoiser.as_mut() {
+            denoiser.process(&mut input);
+        }
 
         if let Some(canceller) = canceller.as_mut() {
-            let render_level = rms_level(&render_history);
-            let capture_level = rms_level(&input);
-            let adapt = render_level > MIN_RENDER_LEVEL
-                && capture_level <= render_level * DOUBLE_TALK_RATIO;
+            let render_level = rms_dbfs(&render_history);
+            // With soft double-talk handling the canceller slows itself down
+            // instead.
+            let double_talk =
+                !args.soft_double_talk && detector.is_double_talk(&render_history, &input);
+            let adapt = render_level > MIN_RENDER_LEVEL_DB && !double_talk;
+            debug!(
+                "render level {:.1} dBFS, double-talk {}, adapting {}",
+                render_level, double_talk, adapt
+            );
             canceller.process_block(&render_history, &input, &mut cleaned, adapt);
         } else {
             cleaned.copy_from_slice(&input);
         }
+        if let Some((recorder, raw)) = debug_recording.as_mut() {
+            recorder.write(&[&render_history, raw, &cleaned])?;
+        }
+        if let Some((suppressor, comfort_noise)) = suppressor.as_mut() {
+            comfort_noise.update(&cleaned);
+            suppressor.suppress(&render_history, &mut cleaned);
+            comfort_noise.fill(&mut cleaned, 1.0 - suppressor.gain());
+            debug!("suppressor gain {:.3}", suppressor.gain());
+        }
 
-        process_delay(&cleaned, &mut output, &mut delay_line, &mut delay_pos);
-        write_chunk(&playback_io, &playback, &output)?;
-        render_history.copy_from_slice(&output);
+        process_delay(
+            &cleaned,
+            &mut output,
+            &mut delay_line,
+            args.mix,
+            args.feedback,
+        );
+        let load = meter.stop();
+        if load > LOAD_WARNING {
+            warn!(
+                "processing took {:.0}% of the block duration; close to real-time limit",
+                load * 100.0
+            );
+        } else {
+            debug!("processing load {:.0}%", load * 100.0);
+        }
+        fade_in.apply(&mut output);
+        if let Some((_, output_meter)) = level_meters.as_mut()
+            && let Some(output_levels) = output_meter.push(&output)
+            && let Some(input_levels) = input_levels
+        {
+            info!("Input {}; output {}", input_levels, output_levels);
+        }
+        io.write(&output)?;
+        if loopback.is_none() {
+            render_history.copy_from_slice(&output);
+        }
+        blocks += 1;
     }
-}
 
-fn open_pcm(direction: Direction) -> Result<PCM> {
-    let pcm = PCM::new("default", direction, false)
-        .with_context(|| format!("open {:?} PCM", direction))?;
-
-    {
-        let hwp = HwParams::any(&pcm)?;
-        hwp.set_access(Access::RWInterleaved)?;
-        hwp.set_format(Format::s16())?;
-        hwp.set_channels(1)?;
-        hwp.set_rate(SAMPLE_RATE, ValueOr::Nearest)?;
-        hwp.set_period_size_near(CHUNK_SIZE as Frames, ValueOr::Nearest)?;
-        hwp.set_buffer_size_near((CHUNK_SIZE * 2) as Frames)?;
-        pcm.hw_params(&hwp)?;
+    if let Some((recorder, _)) = debug_recording {
+        recorder.finish()?;
     }
+    info!("Processed {} blocks; xruns: {}", blocks, io.xruns());
+    info!(
+        "Processing load: mean {:.0}%, peak {:.0}%",
+        meter.mean() * 100.0,
+        meter.peak() * 100.0
+    );
+    Ok(())
+}
 
-    pcm.prepare()?;
-    Ok(pcm)
+/// Circular buffer read at a fractional distance behind the write position,
+/// interpolating linearly between the two nearest samples.
+struct DelayLine {
+    buffer: Vec<f32>,
+    write_pos: usize,
+    delay: f32,
 }
 
-fn read_chunk(io: &IO<i16>, pcm: &PCM, buffer: &mut [i16]) -> Result<()> {
-    let mut offset = 0;
-    while offset < buffer.len() {
-        match io.readi(&mut buffer[offset..]) {
-            Ok(frames) => offset += frames,
-            Err(err) if err.errno() == Errno::EPIPE => {
-                pcm.prepare()?;
-            }
-            Err(err) if err.errno() == Errno::EAGAIN => continue,
-            Err(err) => return Err(err.into()),
+impl DelayLine {
+    /// Creates a silent line delaying by `delay` samples.
+    fn new(delay: f32) -> Self {
+        assert!(delay >= 1.0, "delay must be at least one sample");
+        Self {
+            buffer: vec![0.0; delay.ceil() as usize + 1],
+            write_pos: 0,
+            delay,
         }
     }
-    Ok(())
-}
 
-fn write_chunk(io: &IO<i16>, pcm: &PCM, buffer: &[i16]) -> Result<()> {
-    let mut offset = 0;
-    while offset < buffer.len() {
-        match io.writei(&buffer[offset..]) {
-            Ok(frames) => offset += frames,
-            Err(err) if err.errno() == Errno::EPIPE => {
-                pcm.prepare()?;
-            }
-            Err(err) if err.errno() == Errno::EAGAIN => continue,
-            Err(err) => return Err(err.into()),
-        }
+    /// Returns the sample written `delay` samples ago.
+    fn read(&self) -> f32 {
+        let len = self.buffer.len();
+        let pos = self.write_pos as f32 - self.delay;
+        let pos = if pos < 0.0 { pos + len as f32 } else { pos };
+        let idx = (pos as usize).min(len - 1);
+        let frac = pos - idx as f32;
+        let next = if idx + 1 == len { 0 } else { idx + 1 };
+        self.buffer[idx] + (self.buffer[next] - self.buffer[idx]) * frac
     }
-    Ok(())
-}
 
-fn process_delay(input: &[i16], output: &mut [i16], delay_line: &mut [i16], delay_pos: &mut usize) {
-    for (idx, &sample) in input.iter().enumerate() {
-        let delayed = delay_line[*delay_pos];
-        delay_line[*delay_pos] = sample;
-        *delay_pos += 1;
-        if *delay_pos == delay_line.len() {
-            *delay_pos = 0;
+    /// Appends the next sample.
+    fn write(&mut self, sample: f32) {
+        self.buffer[self.write_pos] = sample;
+        self.write_pos += 1;
+        if self.write_pos == self.buffer.len() {
+            self.write_pos = 0;
         }
-
-        output[idx] = delayed;
     }
 }
 
-fn rms_level(samples: &[i16]) -> f32 {
-    if samples.is_empty() {
-        return 0.0;
+/// Pushes `input` through the delay line, writing the dry/wet blend selected
+/// by `mix` into `output`. `feedback` of the delayed signal is added back into
+/// the line, saturating at full scale.
+fn process_delay(
+    input: &[i16],
+    output: &mut [i16],
+    delay_line: &mut DelayLine,
+    mix: f32,
+    feedback: f32,
+) {
+    for (out, &sample) in output.iter_mut().zip(input) {
+        let dry = sample as f32;
+        let delayed = delay_line.read();
+        delay_line.write((dry + delayed * feedback).clamp(i16::MIN as f32, i16::MAX as f32));
+        *out = (dry * (1.0 - mix) + delayed * mix) as i16;
     }
-
-    let sum = samples
-        .iter()
-        .map(|&s| {
-            let v = s as f32;
-            v * v
-        })
-        .sum::<f32>();
-    let rms = (sum / samples.len() as f32).sqrt();
-    (rms / i16::MAX as f32).min(1.0)
 }
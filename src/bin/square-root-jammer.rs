@@ -1,325 +1,1022 @@
-use std::cmp::Ordering;
-use std::f32::consts::{PI, SQRT_2};
+use std::f32::consts::SQRT_2;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
 
-use alsa::nix::errno::Errno;
-use alsa::pcm::{Access, Format, Frames, HwParams, IO, PCM};
-use alsa::{Direction, ValueOr};
-use anyhow::{Context, Result};
+use anyhow::{Result, ensure};
 use clap::Parser;
-use echo_nlms::NlmsCanceller;
+use echo_nlms::{NlmsCanceller, SpectralDenoiser};
+use log::{debug, info, trace, warn};
+use myjammer::agc::Agc;
+use myjammer::audio::{self, AlsaIo, AudioIo, EndOfInput, PcmFormat, WavIo, XrunCounts};
+use myjammer::dc_blocker::DcBlocker;
+use myjammer::fade::FadeIn;
+use myjammer::level::{LevelMeter, rms_dbfs, rms_level};
+use myjammer::load::LoadMeter;
+use myjammer::pitch_shift::PitchShifter;
+use myjammer::synth::{Synthesizer, Waveform};
+use myjammer::{logging, signal};
+use pitch_detect::{
+    HpsDetector, NoteName, OverlappingFrames, PitchAlgorithm, PitchDetector, PitchResult,
+    PitchSeparation, PitchTracker, Window, detect_pitches_yin, hz_to_midi,
+};
 
 const SAMPLE_RATE: u32 = 48_000;
 const CHUNK_SIZE: usize = 4096;
 const MIN_FREQ: f32 = 60.0;
 const MAX_FREQ: f32 = 1000.0;
 const MAX_OUTPUT_GAIN: f32 = 1.0;
-const GAIN_SMOOTHING: f32 = 0.15;
-const MIN_DETECTION_LEVEL: f32 = 0.01;
+const GATE_THRESHOLD_DB: f32 = -40.0;
+const GATE_HYSTERESIS_DB: f32 = 6.0;
+const GATE_ATTACK_MS: f32 = 5.0;
+const GATE_RELEASE_MS: f32 = 150.0;
 const MAX_VOICES: usize = 3;
 const MIN_CORRELATION: f32 = 0.35;
+const COARSE_SEARCH_FACTOR: usize = 4;
+const YIN_THRESHOLD: f32 = 0.15;
+const HPS_MIN_STRENGTH: f32 = 0.2;
+const PITCH_MEDIAN_FRAMES: usize = 3;
 const HOLD_FRAMES: usize = 6;
+const ENVELOPE_ATTACK_MS: f32 = 10.0;
+const ENVELOPE_DECAY_MS: f32 = 50.0;
+const ENVELOPE_SUSTAIN: f32 = 1.0;
+const ENVELOPE_RELEASE_MS: f32 = 100.0;
+const PAN_SPREAD: f32 = 0.7;
+const VIBRATO_RATE_HZ: f32 = 5.0;
+const COMPRESSOR_THRESHOLD_DB: f32 = -3.0;
+const COMPRESSOR_RATIO: f32 = 8.0;
 const AEC_TAPS: usize = 1024;
 const NLMS_STEP_SIZE: f32 = 0.25;
-
-#[derive(Parser, Debug)]
-#[command(name = "square-root-jammer")]
-struct Args {
-    /// Disable adaptive echo suppression (use when monitoring via headphones).
-    #[arg(long)]
-    disable_echo: bool,
+const LOAD_WARNING: f32 = 0.8;
+const METER_INTERVAL_MS: f32 = 500.0;
+const FADE_IN_MS: f32 = 20.0;
+const DC_BLOCKER_CUTOFF_HZ: f32 = 10.0;
+const DENOISE_FRAME: usize = 512;
+const DENOISE_OVER_SUBTRACTION: f32 = 2.0;
+const DENOISE_FLOOR: f32 = 0.1;
+const AGC_TARGET_DB: f32 = -20.0;
+const AGC_MAX_GAIN_DB: f32 = 20.0;
+const AGC_ATTACK_MS: f32 = 1000.0;
+const AGC_RELEASE_MS: f32 = 50.0;
+const PITCH_SHIFT_WINDOW_MS: f32 = 40.0;
+/// Change in Hz from the last report that `--report-mode hz` reports, and
+/// the movement between frames that `--report-mode glide` counts as sliding.
+const REPORT_THRESHOLD_HZ: f32 = 3.0;
+/// Distance in cents beyond the semitone boundary a voice has to cross for
+/// `--report-mode note` to report a new note, so a pitch hovering between
+/// two notes is not reported over and over.
+const NOTE_HYSTERESIS_CENTS: f32 = 15.0;
+
+/// Block-rate noise gate on the input level in dBFS.
+///
+/// The gate opens once the level reaches the threshold and closes only after
+/// it falls `hysteresis_db` below it, so levels hovering around the threshold
+/// do not chatter. The returned gain ramps towards fully open or fully closed
+/// with the attack and release times.
+struct NoiseGate {
+    open_db: f32,
+    close_db: f32,
+    attack: f32,
+    release: f32,
+    open: bool,
+    gain: f32,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    run(args.disable_echo)
-}
-
-fn run(disable_echo: bool) -> Result<()> {
-    let capture = open_pcm(Direction::Capture).context("failed to open capture PCM")?;
-    let playback = open_pcm(Direction::Playback).context("failed to open playback PCM")?;
-
-    let capture_io = capture.io_i16().context("capture IO handle")?;
-    let playback_io = playback.io_i16().context("playback IO handle")?;
-
-    let mut input = [0i16; CHUNK_SIZE];
-    let mut analysis = [0i16; CHUNK_SIZE];
-    let mut output = [0i16; CHUNK_SIZE];
-    let mut render_history = [0i16; CHUNK_SIZE];
-    let mut phases = [0.0f32; MAX_VOICES];
-    let mut last_reported = [0.0f32; MAX_VOICES];
-    let mut current_gain = 0.0f32;
-    let mut active_freqs = [0.0f32; MAX_VOICES];
-    let mut active_count = 0usize;
-    let mut frames_since_detection = HOLD_FRAMES;
-    let mut canceller = if disable_echo {
-        None
-    } else {
-        Some(NlmsCanceller::new(AEC_TAPS, NLMS_STEP_SIZE))
-    };
-
-    loop {
-        read_chunk(&capture_io, &capture, &mut input)?;
-        if let Some(canceller) = canceller.as_mut() {
-            canceller.process_block(&render_history, &input, &mut analysis, true);
-        } else {
-            analysis.copy_from_slice(&input);
+impl NoiseGate {
+    /// Creates a closed gate fed one level per block of `block_ms`.
+    fn new(
+        threshold_db: f32,
+        hysteresis_db: f32,
+        attack_ms: f32,
+        release_ms: f32,
+        block_ms: f32,
+    ) -> Self {
+        let coefficient = |ms: f32| {
+            if ms > 0.0 {
+                1.0 - (-block_ms / ms).exp()
+            } else {
+                1.0
+            }
+        };
+        Self {
+            open_db: threshold_db,
+            close_db: threshold_db - hysteresis_db,
+            attack: coefficient(attack_ms),
+            release: coefficient(release_ms),
+            open: false,
+            gain: 0.0,
         }
+    }
 
-        let level = rms_level(&analysis);
-        let mut pitches = if level >= MIN_DETECTION_LEVEL {
-            detect_pitches(
-                &analysis,
-                SAMPLE_RATE,
-                MIN_FREQ,
-                MAX_FREQ,
-                MAX_VOICES,
-                MIN_CORRELATION,
-            )
+    /// Updates the gate with the level of the next block and returns the gain
+    /// to apply to it.
+    fn process(&mut self, level_db: f32) -> f32 {
+        if self.open {
+            self.open = level_db >= self.close_db;
         } else {
-            Vec::new()
-        };
-
-        if !pitches.is_empty() {
-            if pitches.len() > MAX_VOICES {
-                pitches.truncate(MAX_VOICES);
-            }
-
-            active_count = pitches.len();
-            active_freqs[..active_count].copy_from_slice(&pitches);
-            for idx in active_count..MAX_VOICES {
-                active_freqs[idx] = 0.0;
-                last_reported[idx] = 0.0;
-            }
-
-            for (idx, &freq) in active_freqs.iter().take(active_count).enumerate() {
-                if (freq - last_reported[idx]).abs() > 3.0 {
-                    println!(
-                        "Voice {}: {:.1} Hz -> {:.1} Hz",
-                        idx + 1,
-                        freq,
-                        freq * SQRT_2
-                    );
-                    last_reported[idx] = freq;
-                }
-            }
-
-            frames_since_detection = 0;
-        } else if active_count > 0 {
-            if frames_since_detection < HOLD_FRAMES {
-                frames_since_detection += 1;
-            } else {
-                active_count = 0;
-                active_freqs.fill(0.0);
-                last_reported.fill(0.0);
-                phases.fill(0.0);
-                frames_since_detection = HOLD_FRAMES;
-            }
+            self.open = level_db >= self.open_db;
+        }
+        if self.open {
+            self.gain += (1.0 - self.gain) * self.attack;
         } else {
-            frames_since_detection = HOLD_FRAMES;
+            self.gain -= self.gain * self.release;
         }
+        self.gain
+    }
 
-        let target_gain = (level * MAX_OUTPUT_GAIN).min(MAX_OUTPUT_GAIN);
-        current_gain += (target_gain - current_gain) * GAIN_SMOOTHING;
+    fn is_open(&self) -> bool {
+        self.open
+    }
+}
 
-        let playback_freqs: Vec<f32> = active_freqs
-            .iter()
-            .take(active_count)
-            .map(|f| f * SQRT_2)
-            .collect();
-        synthesize_chunk(&mut output, &playback_freqs, &mut phases, current_gain);
-        write_chunk(&playback_io, &playback, &output)?;
-        render_history.copy_from_slice(&output);
+/// When a voice's pitch is reported on the console.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportMode {
+    /// Whenever it moves by more than a few Hz.
+    Hz,
+    /// When it settles on a different note, named with its cents deviation.
+    Note,
+    /// When a voice starts and when it settles after a slide, with the slide
+    /// reported as one change.
+    Glide,
+}
+
+impl fmt::Display for ReportMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReportMode::Hz => "hz",
+            ReportMode::Note => "note",
+            ReportMode::Glide => "glide",
+        })
     }
 }
 
-fn open_pcm(direction: Direction) -> Result<PCM> {
-    let pcm = PCM::new("default", direction, false)
-        .with_context(|| format!("open {:?} PCM", direction))?;
-
-    {
-        let hwp = HwParams::any(&pcm)?;
-        hwp.set_access(Access::RWInterleaved)?;
-        hwp.set_format(Format::s16())?;
-        hwp.set_channels(1)?;
-        hwp.set_rate(SAMPLE_RATE, ValueOr::Nearest)?;
-        hwp.set_period_size_near(CHUNK_SIZE as Frames, ValueOr::Nearest)?;
-        hwp.set_buffer_size_near((CHUNK_SIZE * 2) as Frames)?;
-        pcm.hw_params(&hwp)?;
+impl FromStr for ReportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hz" => Ok(ReportMode::Hz),
+            "note" => Ok(ReportMode::Note),
+            "glide" => Ok(ReportMode::Glide),
+            _ => Err(format!(
+                "unknown report mode `{s}` (expected hz, note or glide)"
+            )),
+        }
     }
+}
 
-    pcm.prepare()?;
-    Ok(pcm)
+/// Logs the detected voices according to a [`ReportMode`].
+struct VoiceReporter {
+    mode: ReportMode,
+    note_names: bool,
+    shift_ratio: f32,
+    /// Frequency each voice was last reported at, 0.0 while silent.
+    reported: Vec<f32>,
+    /// Frequency of each voice in the previous frame, 0.0 while silent.
+    previous: Vec<f32>,
 }
 
-fn read_chunk(io: &IO<i16>, pcm: &PCM, buffer: &mut [i16]) -> Result<()> {
-    let mut offset = 0;
-    while offset < buffer.len() {
-        match io.readi(&mut buffer[offset..]) {
-            Ok(frames) => offset += frames,
-            Err(err) if err.errno() == Errno::EPIPE => {
-                pcm.prepare()?;
-            }
-            Err(err) if err.errno() == Errno::EAGAIN => continue,
-            Err(err) => return Err(err.into()),
+impl VoiceReporter {
+    fn new(mode: ReportMode, voices: usize, note_names: bool, shift_ratio: f32) -> Self {
+        Self {
+            mode,
+            note_names: note_names || mode == ReportMode::Note,
+            shift_ratio,
+            reported: vec![0.0; voices],
+            previous: vec![0.0; voices],
         }
     }
-    Ok(())
-}
 
-fn write_chunk(io: &IO<i16>, pcm: &PCM, buffer: &[i16]) -> Result<()> {
-    let mut offset = 0;
-    while offset < buffer.len() {
-        match io.writei(&buffer[offset..]) {
-            Ok(frames) => offset += frames,
-            Err(err) if err.errno() == Errno::EPIPE => {
-                pcm.prepare()?;
+    /// Takes the frequency voice `idx` has in the current frame, 0.0 if it
+    /// is silent.
+    fn update(&mut self, idx: usize, freq: f32) {
+        if freq <= 0.0 {
+            self.release(idx);
+            return;
+        }
+        let reported = self.reported[idx];
+        let previous = self.previous[idx];
+        self.previous[idx] = freq;
+        let changed = match self.mode {
+            _ if reported == 0.0 => true,
+            ReportMode::Hz => (freq - reported).abs() > REPORT_THRESHOLD_HZ,
+            ReportMode::Note => {
+                let (note, cents) = hz_to_midi(freq);
+                let exact = note as f32 + cents / 100.0;
+                let (reported_note, _) = hz_to_midi(reported);
+                (exact - reported_note as f32).abs() > 0.5 + NOTE_HYSTERESIS_CENTS / 100.0
+            }
+            ReportMode::Glide => {
+                (freq - previous).abs() <= REPORT_THRESHOLD_HZ
+                    && (freq - reported).abs() > REPORT_THRESHOLD_HZ
             }
-            Err(err) if err.errno() == Errno::EAGAIN => continue,
-            Err(err) => return Err(err.into()),
+        };
+        if changed {
+            let from = (self.mode == ReportMode::Glide && reported > 0.0).then_some(reported);
+            self.report(idx, freq, from);
         }
     }
-    Ok(())
-}
 
-fn synthesize_chunk(buffer: &mut [i16], freqs: &[f32], phases: &mut [f32], gain: f32) {
-    if freqs.is_empty() {
-        buffer.fill(0);
-        phases.fill(0.0);
-        return;
+    /// Marks voice `idx` as silent, first reporting where a slide still in
+    /// progress ended.
+    fn release(&mut self, idx: usize) {
+        let (reported, previous) = (self.reported[idx], self.previous[idx]);
+        if self.mode == ReportMode::Glide
+            && reported > 0.0
+            && (previous - reported).abs() > REPORT_THRESHOLD_HZ
+        {
+            self.report(idx, previous, Some(reported));
+        }
+        self.reported[idx] = 0.0;
+        self.previous[idx] = 0.0;
     }
 
-    let normalized_gain = gain.clamp(0.0, 1.0);
-    let amplitude = i16::MAX as f32 * (normalized_gain / freqs.len() as f32);
-
-    for sample in buffer.iter_mut() {
-        let mut acc = 0.0f32;
-        for (idx, freq) in freqs.iter().enumerate() {
-            let phase = &mut phases[idx];
-            acc += (*phase).sin();
-            let phase_step = 2.0 * PI * freq / SAMPLE_RATE as f32;
-            *phase += phase_step;
-            if *phase > 2.0 * PI {
-                *phase -= 2.0 * PI;
-            }
+    fn reset(&mut self) {
+        for idx in 0..self.reported.len() {
+            self.release(idx);
         }
-        let value = (acc * amplitude).clamp(i16::MIN as f32, i16::MAX as f32);
-        *sample = value as i16;
     }
 
-    for idx in freqs.len()..phases.len() {
-        phases[idx] = 0.0;
+    fn report(&mut self, idx: usize, freq: f32, glide_from: Option<f32>) {
+        let shifted = freq * self.shift_ratio;
+        let glide = glide_from.map_or(String::new(), |from| format!(" (slid from {:.1} Hz)", from));
+        if self.note_names {
+            info!(
+                "Voice {}: {:.1} Hz ({}) -> {:.1} Hz ({}){}",
+                idx + 1,
+                freq,
+                NoteName(freq),
+                shifted,
+                NoteName(shifted),
+                glide
+            );
+        } else {
+            info!(
+                "Voice {}: {:.1} Hz -> {:.1} Hz{}",
+                idx + 1,
+                freq,
+                shifted,
+                glide
+            );
+        }
+        self.reported[idx] = freq;
     }
 }
 
-fn detect_pitches(
-    samples: &[i16],
-    sample_rate: u32,
-    min_hz: f32,
-    max_hz: f32,
-    max_results: usize,
-    min_correlation: f32,
-) -> Vec<f32> {
-    if samples.is_empty() || max_results == 0 {
-        return Vec::new();
-    }
+#[derive(Parser, Debug)]
+#[command(name = "square-root-jammer")]
+struct Args {
+    /// Log more detail: `-v` for debug output, `-vv` for trace. `RUST_LOG`
+    /// overrides this.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
 
-    let mut floated: Vec<f32> = samples.iter().map(|&s| s as f32).collect();
-    let mean = floated.iter().sum::<f32>() / floated.len() as f32;
+    /// Log the RMS and peak levels of the input and output twice a second,
+    /// to help set the microphone gain and spot clipping.
+    #[arg(long)]
+    meter: bool,
 
-    for sample in &mut floated {
-        *sample -= mean;
-    }
+    /// Fade playback in over this many milliseconds after starting, so it
+    /// does not open with a click (0 disables the fade).
+    #[arg(long, default_value_t = FADE_IN_MS)]
+    fade_in_ms: f32,
 
-    apply_hann_window(&mut floated);
+    /// Copy the capture straight to playback without detection, synthesis
+    /// or echo cancellation, to check the audio setup on its own.
+    #[arg(long)]
+    passthrough: bool,
 
-    let len = floated.len();
-    if len < 2 {
-        return Vec::new();
-    }
+    /// Play the captured voice itself shifted by the shift ratio, keeping
+    /// its character, instead of synthesizing tones at the detected pitches.
+    /// Detection and the noise gate still run; the synthesis options do not
+    /// apply.
+    #[arg(long, conflicts_with_all = ["passthrough", "harmony"])]
+    pitch_shift: bool,
 
-    let min_period = ((sample_rate as f32) / max_hz).floor() as usize;
-    let max_period = ((sample_rate as f32) / min_hz).ceil() as usize;
+    /// Window in milliseconds `--pitch-shift` crossfades over: longer windows
+    /// warble less on sustained notes, shorter ones smear attacks less.
+    #[arg(long, default_value_t = PITCH_SHIFT_WINDOW_MS)]
+    pitch_shift_window_ms: f32,
 
-    if min_period < 2 || max_period >= len || min_period >= max_period {
-        return Vec::new();
-    }
+    /// Disable adaptive echo suppression (use when monitoring via headphones).
+    #[arg(long)]
+    disable_echo: bool,
 
-    let mut energy_prefix = vec![0.0f32; len + 1];
-    for (idx, sample) in floated.iter().enumerate() {
-        energy_prefix[idx + 1] = energy_prefix[idx] + sample * sample;
-    }
+    /// Lowest frequency in Hz searched for a pitch.
+    #[arg(long, default_value_t = MIN_FREQ)]
+    min_freq: f32,
 
-    let mut correlations: Vec<(usize, f32)> = Vec::with_capacity(max_period - min_period + 1);
+    /// Highest frequency in Hz searched for a pitch.
+    #[arg(long, default_value_t = MAX_FREQ)]
+    max_freq: f32,
 
-    for lag in min_period..=max_period {
-        let segment_len = len - lag;
-        if segment_len < 2 {
-            continue;
-        }
+    /// Reduce stationary background noise in the captured signal by spectral
+    /// subtraction before any other processing.
+    #[arg(long)]
+    denoise: bool,
 
-        let energy_a = energy_prefix[segment_len] - energy_prefix[0];
-        let energy_b = energy_prefix[len] - energy_prefix[lag];
-        let denom = (energy_a * energy_b).sqrt();
-        if denom <= 1e-9 {
-            continue;
-        }
+    /// Multiple of the estimated noise spectrum removed by `--denoise`.
+    #[arg(long, default_value_t = DENOISE_OVER_SUBTRACTION)]
+    denoise_over_subtraction: f32,
 
-        let mut sum = 0.0;
-        for i in 0..segment_len {
-            sum += floated[i] * floated[i + lag];
-        }
-        let normalized = (sum / denom).clamp(-1.0, 1.0);
-        correlations.push((lag, normalized));
+    /// Smallest fraction of each frequency's magnitude `--denoise` keeps
+    /// (0.0 to 1.0).
+    #[arg(long, default_value_t = DENOISE_FLOOR)]
+    denoise_floor: f32,
+
+    /// Normalize the level of the captured signal before pitch detection and
+    /// the noise gate, so they behave the same whatever the input gain. The
+    /// output level still follows the unnormalized input.
+    #[arg(long)]
+    agc: bool,
+
+    /// RMS level in dBFS `--agc` steers the analyzed signal towards.
+    #[arg(long, default_value_t = AGC_TARGET_DB, allow_negative_numbers = true)]
+    agc_target_db: f32,
+
+    /// Most boost in dB `--agc` applies, keeping background noise in pauses
+    /// below the gate threshold.
+    #[arg(long, default_value_t = AGC_MAX_GAIN_DB)]
+    agc_max_gain_db: f32,
+
+    /// Time constant in milliseconds over which `--agc` raises its gain.
+    #[arg(long, default_value_t = AGC_ATTACK_MS)]
+    agc_attack_ms: f32,
+
+    /// Time constant in milliseconds over which `--agc` lowers its gain.
+    #[arg(long, default_value_t = AGC_RELEASE_MS)]
+    agc_release_ms: f32,
+
+    /// Track a single voice with YIN instead of multi-voice autocorrelation.
+    #[arg(long)]
+    yin: bool,
+
+    /// Detect chord notes with the harmonic product spectrum instead of
+    /// autocorrelation.
+    #[arg(long, conflicts_with = "yin")]
+    hps: bool,
+
+    /// Samples between the starts of consecutive analysis frames. Values below
+    /// the 4096-sample frame overlap the frames and shorten the audio blocks,
+    /// reacting faster to note changes at the cost of more detection work.
+    #[arg(long, default_value_t = CHUNK_SIZE)]
+    hop: usize,
+
+    /// Number of frames each voice's pitch is median-filtered over (1 disables
+    /// smoothing).
+    #[arg(long, default_value_t = PITCH_MEDIAN_FRAMES)]
+    median_frames: usize,
+
+    /// Most pitches detected and tracked at once: 1 for clean monophonic
+    /// tracking, more for chords.
+    #[arg(long, default_value_t = MAX_VOICES)]
+    max_voices: usize,
+
+    /// Report voices as note names with cents deviation alongside Hz.
+    #[arg(long)]
+    note_names: bool,
+
+    /// When to report voices: `hz` whenever a voice moves by more than
+    /// 3 Hz, `note` only when it settles on a different note, or `glide`
+    /// when a voice starts and once a slide has come to rest.
+    #[arg(long, default_value_t = ReportMode::Hz)]
+    report_mode: ReportMode,
+
+    /// Minimum spacing between detected voices, in Hz (`5hz`) or cents
+    /// (`50cents`); closer pitches merge into one voice.
+    #[arg(long, default_value_t = PitchSeparation::default())]
+    voice_separation: PitchSeparation,
+
+    /// Analysis window for autocorrelation and HPS pitch detection (hann,
+    /// hamming, blackman or rectangular).
+    #[arg(long, default_value_t = Window::Hann)]
+    window: Window,
+
+    /// Band-limit the analysis signal to the detection range before
+    /// autocorrelation, suppressing low-frequency rumble.
+    #[arg(long)]
+    prefilter: bool,
+
+    /// Locate autocorrelation peaks on a decimated copy of each block and
+    /// refine them at full rate, cutting the detection cost.
+    #[arg(long, conflicts_with_all = ["yin", "hps"])]
+    coarse_search: bool,
+
+    /// Move autocorrelation pitches up an octave when half their period
+    /// correlates nearly as well, fixing notes detected an octave low. Best
+    /// for single voices, as it merges chord notes an octave apart.
+    #[arg(long, conflicts_with_all = ["yin", "hps"])]
+    octave_correction: bool,
+
+    /// Best autocorrelation a frame needs to count as voiced; below it no
+    /// pitches are reported at all. Raise it to keep breath and noise from
+    /// producing stray tones.
+    #[arg(long, default_value_t = MIN_CORRELATION)]
+    clarity_threshold: f32,
+
+    /// Master output gain in dB, applied on top of the level tracking.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    gain: f32,
+
+    /// Play at this fixed level (0.0 to 1.0 of full scale) instead of
+    /// following the input level.
+    #[arg(long)]
+    fixed_level: Option<f32>,
+
+    /// Add TPDF dither when the synthesized and echo-cancelled signals are
+    /// rounded to 16 bits, trading quantization distortion on quiet passages
+    /// for a faint noise floor.
+    #[arg(long)]
+    dither: bool,
+
+    /// Input level in dBFS, measured after `--agc` if enabled, below which
+    /// detection stops and the output is muted.
+    #[arg(long, default_value_t = GATE_THRESHOLD_DB, allow_negative_numbers = true)]
+    gate_threshold_db: f32,
+
+    /// Frequency ratio applied to each detected voice (2.0 is an octave up,
+    /// 1.0 passes pitches through unchanged).
+    #[arg(long, default_value_t = SQRT_2)]
+    shift_ratio: f32,
+
+    /// Shift interval in cents, overriding --shift-ratio.
+    #[arg(long, conflicts_with = "shift_ratio", allow_negative_numbers = true)]
+    shift_cents: Option<f32>,
+
+    /// Comma-separated intervals in semitones (e.g. `3,7`) added as extra
+    /// voices above or below each shifted voice, forming chords. Without it
+    /// each detected voice is played in unison.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        allow_negative_numbers = true,
+        value_name = "SEMITONES"
+    )]
+    harmony: Vec<f32>,
+
+    /// Oscillator waveform for the synthesized voices (sine, saw, square or
+    /// triangle).
+    #[arg(long, default_value_t = Waveform::Sine)]
+    waveform: Waveform,
+
+    /// Time for a new voice to fade in, in milliseconds.
+    #[arg(long, default_value_t = ENVELOPE_ATTACK_MS)]
+    attack_ms: f32,
+
+    /// Time to settle from full level to the sustain level, in milliseconds.
+    #[arg(long, default_value_t = ENVELOPE_DECAY_MS)]
+    decay_ms: f32,
+
+    /// Level held while a voice stays detected, between 0.0 and 1.0.
+    #[arg(long, default_value_t = ENVELOPE_SUSTAIN)]
+    sustain: f32,
+
+    /// Time for a lost voice to fade out from full level, in milliseconds.
+    #[arg(long, default_value_t = ENVELOPE_RELEASE_MS)]
+    release_ms: f32,
+
+    /// Scale each voice by how confidently it was detected, so spurious
+    /// voices play quieter.
+    #[arg(long)]
+    confidence_weighting: bool,
+
+    /// Play back in stereo, spreading the voices across the stereo field.
+    #[arg(long)]
+    stereo: bool,
+
+    /// Stereo width of the voice spread, from 0.0 (all centered) to 1.0
+    /// (outermost voices hard left and right).
+    #[arg(long, default_value_t = PAN_SPREAD)]
+    pan_spread: f32,
+
+    /// Vibrato rate in Hz.
+    #[arg(long, default_value_t = VIBRATO_RATE_HZ)]
+    vibrato_rate: f32,
+
+    /// Vibrato depth in cents either side of the pitch (0 disables vibrato).
+    #[arg(long, default_value_t = 0.0)]
+    vibrato_depth: f32,
+
+    /// Portamento time constant in milliseconds for gliding between
+    /// successive pitches of a voice (0 jumps immediately).
+    #[arg(long, default_value_t = 0.0)]
+    glide_ms: f32,
+
+    /// Output peak level in dBFS above which the compressor starts reducing
+    /// the gain.
+    #[arg(long, default_value_t = COMPRESSOR_THRESHOLD_DB, allow_negative_numbers = true)]
+    compressor_threshold_db: f32,
+
+    /// Compression ratio above the threshold (1 disables compression; large
+    /// values such as 20 turn the compressor into a limiter).
+    #[arg(long, default_value_t = COMPRESSOR_RATIO)]
+    compressor_ratio: f32,
+
+    /// ALSA device to record from.
+    #[arg(long, default_value = "default")]
+    capture_device: String,
+
+    /// ALSA device to play back on.
+    #[arg(long, default_value = "default")]
+    playback_device: String,
+
+    /// Print the available ALSA PCM devices and their capabilities, then
+    /// exit.
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Open the playback device in stereo and send the mono output to both
+    /// channels.
+    #[arg(long, conflicts_with = "stereo")]
+    mono_to_stereo: bool,
+
+    /// Sample format to open the ALSA devices with (s16, s32 or float).
+    #[arg(long, default_value_t = PcmFormat::S16)]
+    format: PcmFormat,
+
+    /// Process a mono 16-bit WAV file instead of capturing from ALSA.
+    #[arg(long, requires = "output")]
+    input: Option<PathBuf>,
+
+    /// Write the synthesized audio to a WAV file instead of playing it back.
+    #[arg(long, requires = "input")]
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    fn playback_channels(&self) -> usize {
+        if self.stereo { 2 } else { 1 }
     }
+}
 
-    correlations.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+fn main() -> Result<()> {
+    let args = Args::parse();
+    logging::init(args.verbose);
+    signal::install_interrupt_handler()?;
+    run(&args)
+}
 
-    let mut results: Vec<f32> = Vec::new();
-    for (lag, corr) in correlations {
-        if corr < min_correlation {
-            continue;
+fn run(args: &Args) -> Result<()> {
+    if args.list_devices {
+        for device in audio::list_devices()? {
+            println!("{device}");
         }
+        return Ok(());
+    }
+
+    ensure!(
+        (1..=CHUNK_SIZE).contains(&args.hop),
+        "hop must be between 1 and {} frames",
+        CHUNK_SIZE
+    );
+    ensure!(
+        args.fade_in_ms.is_finite() && args.fade_in_ms >= 0.0,
+        "fade-in time must be non-negative"
+    );
+    let channels = args.playback_channels();
+    if let (Some(input), Some(output)) = (&args.input, &args.output) {
+        let mut io = WavIo::open(input, output, SAMPLE_RATE, channels)?;
+        process(&mut io, args, args.hop)?;
+        return io.finish();
+    }
 
-        let freq = sample_rate as f32 / lag as f32;
-        let is_distinct = results
+    let mut io = AlsaIo::open(
+        &args.capture_device,
+        &args.playback_device,
+        args.format,
+        SAMPLE_RATE,
+        args.hop,
+        channels,
+        args.mono_to_stereo,
+    )?;
+    let block_len = io.period_frames();
+    process(&mut io, args, block_len)?;
+    io.finish()
+}
+
+/// Runs the detection and synthesis loop in blocks of `block_len` frames
+/// until the input runs out or Ctrl-C is pressed. Pitches are detected on
+/// `CHUNK_SIZE`-frame analysis frames advancing by `--hop`.
+fn process(io: &mut impl AudioIo, args: &Args, block_len: usize) -> Result<()> {
+    if args.passthrough {
+        return passthrough(io, args, block_len);
+    }
+    let algorithm = if args.yin {
+        PitchAlgorithm::Yin
+    } else if args.hps {
+        PitchAlgorithm::Hps
+    } else {
+        PitchAlgorithm::Autocorrelation
+    };
+    let shift_ratio = args
+        .shift_cents
+        .map_or(args.shift_ratio, |cents| 2.0f32.powf(cents / 1200.0));
+    ensure!(
+        shift_ratio.is_finite() && shift_ratio > 0.0,
+        "shift ratio must be positive and finite"
+    );
+    ensure!(
+        [args.attack_ms, args.decay_ms, args.release_ms]
             .iter()
-            .all(|&existing| (existing - freq).abs() > 5.0f32);
-        if is_distinct {
-            results.push(freq);
+            .all(|ms| ms.is_finite() && *ms >= 0.0),
+        "envelope times must be non-negative"
+    );
+    ensure!(
+        (0.0..=1.0).contains(&args.sustain),
+        "sustain level must be between 0.0 and 1.0"
+    );
+    ensure!(
+        (0.0..=1.0).contains(&args.pan_spread),
+        "pan spread must be between 0.0 and 1.0"
+    );
+    ensure!(
+        args.vibrato_rate.is_finite()
+            && args.vibrato_rate >= 0.0
+            && args.vibrato_depth.is_finite()
+            && args.vibrato_depth >= 0.0,
+        "vibrato rate and depth must be non-negative"
+    );
+    ensure!(
+        args.glide_ms.is_finite() && args.glide_ms >= 0.0,
+        "glide time must be non-negative"
+    );
+    ensure!(
+        args.compressor_threshold_db.is_finite() && args.compressor_threshold_db <= 0.0,
+        "compressor threshold must be at most 0 dBFS"
+    );
+    ensure!(
+        args.compressor_ratio >= 1.0,
+        "compressor ratio must be at least 1"
+    );
+    ensure!(
+        args.gate_threshold_db.is_finite() && args.gate_threshold_db <= 0.0,
+        "gate threshold must be at most 0 dBFS"
+    );
+    ensure!(args.gain.is_finite(), "gain must be finite");
+    ensure!(args.max_voices >= 1, "max voices must be at least 1");
+    ensure!(args.median_frames >= 1, "median frames must be at least 1");
+    ensure!(
+        args.min_freq > 0.0 && (SAMPLE_RATE as f32 / args.min_freq).ceil() < CHUNK_SIZE as f32,
+        "minimum frequency must be high enough for its period to fit in {} samples",
+        CHUNK_SIZE
+    );
+    ensure!(
+        args.max_freq > args.min_freq && args.max_freq <= SAMPLE_RATE as f32 / 4.0,
+        "maximum frequency must be above the minimum and at most {} Hz",
+        SAMPLE_RATE / 4
+    );
+    ensure!(
+        (0.0..=1.0).contains(&args.clarity_threshold),
+        "clarity threshold must be between 0.0 and 1.0"
+    );
+    ensure!(
+        args.pitch_shift_window_ms.is_finite() && args.pitch_shift_window_ms >= 1.0,
+        "pitch shift window must be at least 1 ms"
+    );
+    ensure!(
+        args.fixed_level
+            .is_none_or(|level| (0.0..=1.0).contains(&level)),
+        "fixed level must be between 0.0 and 1.0"
+    );
+    ensure!(
+        args.denoise_over_subtraction.is_finite() && args.denoise_over_subtraction >= 0.0,
+        "denoise over-subtraction must be non-negative"
+    );
+    ensure!(
+        (0.0..=1.0).contains(&args.denoise_floor),
+        "denoise floor must be between 0.0 and 1.0"
+    );
+    ensure!(
+        args.agc_target_db.is_finite() && args.agc_target_db <= 0.0,
+        "AGC target must be at most 0 dBFS"
+    );
+    ensure!(
+        args.agc_max_gain_db.is_finite() && args.agc_max_gain_db >= 0.0,
+        "AGC maximum gain must be non-negative"
+    );
+    ensure!(
+        args.agc_attack_ms.is_finite() && args.agc_attack_ms >= 0.0,
+        "AGC attack time must be non-negative"
+    );
+    ensure!(
+        args.agc_release_ms.is_finite() && args.agc_release_ms >= 0.0,
+        "AGC release time must be non-negative"
+    );
+    let channels = args.playback_channels();
+
+    let mut input = vec![0i16; block_len];
+    let mut analysis = vec![0i16; block_len];
+    let mut cleaned = vec![0i16; block_len];
+    let mut output = vec![0i16; block_len * channels];
+    let mut render_history = vec![0i16; block_len];
+    // Each detected voice drives one synthesized voice per interval, the
+    // unison first, laid out consecutively.
+    let intervals: Vec<f32> = std::iter::once(1.0)
+        .chain(
+            args.harmony
+                .iter()
+                .map(|semitones| (semitones / 12.0).exp2()),
+        )
+        .collect();
+    let mut synth = Synthesizer::new(args.max_voices * intervals.len(), channels, SAMPLE_RATE);
+    synth.set_waveform(args.waveform);
+    synth.set_envelope(args.attack_ms, args.decay_ms, args.sustain, args.release_ms);
+    synth.set_pan_spread(args.pan_spread);
+    synth.set_glide_ms(args.glide_ms);
+    synth.set_vibrato(args.vibrato_rate, args.vibrato_depth);
+    synth.set_compressor(args.compressor_threshold_db, args.compressor_ratio);
+    synth.set_master_gain_db(args.gain);
+    synth.set_dither(args.dither);
+    let mut voice_freqs = vec![0.0f32; synth.voices()];
+    let mut reporter = VoiceReporter::new(
+        args.report_mode,
+        args.max_voices,
+        args.note_names,
+        shift_ratio,
+    );
+    let mut gate = NoiseGate::new(
+        args.gate_threshold_db,
+        GATE_HYSTERESIS_DB,
+        GATE_ATTACK_MS,
+        GATE_RELEASE_MS,
+        block_len as f32 * 1000.0 / SAMPLE_RATE as f32,
+    );
+    let mut active_freqs = vec![0.0f32; args.max_voices];
+    let mut frames_since_detection = HOLD_FRAMES;
+    let mut frames = OverlappingFrames::new(CHUNK_SIZE, args.hop);
+    let mut detector = PitchDetector::new(CHUNK_SIZE, SAMPLE_RATE, args.min_freq, args.max_freq);
+    detector.set_max_results(args.max_voices);
+    detector.set_min_correlation(MIN_CORRELATION);
+    detector.set_min_clarity(args.clarity_threshold);
+    detector.set_separation(args.voice_separation);
+    detector.set_window(args.window);
+    detector.set_prefilter(args.prefilter.then_some((args.min_freq, args.max_freq)));
+    detector.set_coarse_search(args.coarse_search.then_some(COARSE_SEARCH_FACTOR));
+    detector.set_octave_correction(args.octave_correction);
+    debug!("searching periods of {:?} samples", detector.period_range());
+    let mut hps = HpsDetector::new(CHUNK_SIZE, SAMPLE_RATE, args.min_freq, args.max_freq);
+    hps.set_max_results(args.max_voices);
+    hps.set_min_strength(HPS_MIN_STRENGTH);
+    hps.set_separation(args.voice_separation);
+    hps.set_window(args.window);
+    let mut tracker = PitchTracker::new(args.max_voices, args.median_frames);
+    let mut denoiser = args.denoise.then(|| {
+        SpectralDenoiser::new(
+            DENOISE_FRAME,
+            args.denoise_over_subtraction,
+            args.denoise_floor,
+        )
+    });
+    let mut shifter = args.pitch_shift.then(|| {
+        PitchShifter::new(
+            shift_ratio,
+            args.pitch_shift_window_ms,
+            channels,
+            SAMPLE_RATE,
+        )
+    });
+    let master_gain = 10.0f32.powf(args.gain / 20.0);
+    let mut agc = args.agc.then(|| {
+        Agc::new(
+            args.agc_target_db,
+            args.agc_max_gain_db,
+            args.agc_attack_ms,
+            args.agc_release_ms,
+            SAMPLE_RATE,
+        )
+    });
+    let mut canceller = if args.disable_echo {
+        None
+    } else {
+        let mut nlms = NlmsCanceller::new(AEC_TAPS, NLMS_STEP_SIZE);
+        nlms.set_dither(args.dither);
+        Some(nlms)
+    };
+
+    let mut reported_xruns = XrunCounts::default();
+
+    let mut blocks = 0u64;
+    let mut meter = LoadMeter::new(block_len, SAMPLE_RATE);
+    let mut level_meters = args.meter.then(|| {
+        (
+            LevelMeter::new(METER_INTERVAL_MS, SAMPLE_RATE, 1),
+            LevelMeter::new(METER_INTERVAL_MS, SAMPLE_RATE, channels),
+        )
+    });
+    let mut fade_in = FadeIn::from_ms(args.fade_in_ms, SAMPLE_RATE, channels);
+    let mut dc_blocker = DcBlocker::new(DC_BLOCKER_CUTOFF_HZ, SAMPLE_RATE);
+
+    while !signal::interrupted() {
+        match io.read(&mut input) {
+            Err(err) if err.is::<EndOfInput>() => break,
+            result => result?,
+        }
+        meter.start();
+        let xruns = io.xruns();
+        if xruns != reported_xruns {
+            warn!("xruns: {}", xruns);
+            reported_xruns = xruns;
+        }
+        let input_levels = level_meters
+            .as_mut()
+            .and_then(|(input_meter, _)| input_meter.push(&input));
+        // Metered before, so the meter still shows what the ADC delivered.
+        dc_blocker.process(&mut input);
+        if let Some(denoiser) = denoiser.as_mut() {
+            denoiser.process(&mut input);
+        }
+        if let Some(canceller) = canceller.as_mut() {
+            canceller.process_block(&render_history, &input, &mut analysis, true);
+            debug!("ERLE {:.1} dB", canceller.erle_db());
+        } else {
+            analysis.copy_from_slice(&input);
         }
 
-        if results.len() == max_results {
-            break;
+        let input_level = rms_level(&analysis);
+        if shifter.is_some() {
+            // Shift the signal as captured, not as levelled for detection.
+            cleaned.copy_from_slice(&analysis);
         }
-    }
+        if let Some(agc) = agc.as_mut() {
+            agc.process(&mut analysis);
+            debug!("AGC gain {:.1} dB", 20.0 * agc.gain().log10());
+        }
+        let level = rms_dbfs(&analysis);
+        let gate_gain = gate.process(level);
+        frames.push(&analysis);
+        while let Some(frame) = frames.next_frame() {
+            let yin_pitch;
+            let pitches: &[PitchResult] = if !gate.is_open() {
+                &[]
+            } else {
+                match algorithm {
+                    PitchAlgorithm::Autocorrelation => detector.detect(frame),
+                    PitchAlgorithm::Hps => hps.detect(frame),
+                    PitchAlgorithm::Yin => {
+                        yin_pitch = detect_pitches_yin(
+                            frame,
+                            SAMPLE_RATE,
+                            args.min_freq,
+                            args.max_freq,
+                            YIN_THRESHOLD,
+                        );
+                        yin_pitch.as_slice()
+                    }
+                }
+            };
+            debug!(
+                "input level {:.1} dBFS, {} pitches detected",
+                level,
+                pitches.len()
+            );
+            for pitch in pitches {
+                trace!(
+                    "  {:.1} Hz (correlation {:.2}, period {:.2} samples)",
+                    pitch.freq, pitch.correlation, pitch.period
+                );
+            }
+            if !pitches.is_empty() {
+                for (idx, voice) in tracker.update(pitches).iter().enumerate() {
+                    if let Some(voice) = voice {
+                        active_freqs[idx] = voice.freq;
+                        if args.confidence_weighting {
+                            let gain = voice.correlation.clamp(0.0, 1.0);
+                            for voice in idx * intervals.len()..(idx + 1) * intervals.len() {
+                                synth.set_voice_gain(voice, gain);
+                            }
+                        }
+                    } else {
+                        // Keep the last gain so a released voice fades from where
+                        // it was rather than dropping out.
+                        active_freqs[idx] = 0.0;
+                    }
+                }
 
-    results
-}
+                for (idx, &freq) in active_freqs.iter().enumerate() {
+                    reporter.update(idx, freq);
+                }
+
+                frames_since_detection = 0;
+            } else if active_freqs.iter().any(|&freq| freq > 0.0) {
+                if frames_since_detection < HOLD_FRAMES {
+                    frames_since_detection += 1;
+                } else {
+                    active_freqs.fill(0.0);
+                    reporter.reset();
+                    tracker.reset();
+                    frames_since_detection = HOLD_FRAMES;
+                }
+            } else {
+                frames_since_detection = HOLD_FRAMES;
+            }
+        }
 
-fn apply_hann_window(samples: &mut [f32]) {
-    if samples.len() < 2 {
-        return;
+        if let Some(shifter) = shifter.as_mut() {
+            shifter.set_gain(gate_gain * master_gain);
+            shifter.process(&cleaned, &mut output);
+        } else {
+            let output_level = args
+                .fixed_level
+                .unwrap_or((input_level * MAX_OUTPUT_GAIN).min(MAX_OUTPUT_GAIN));
+            synth.set_target_gain(output_level * gate_gain);
+            for (chord, &freq) in voice_freqs
+                .chunks_exact_mut(intervals.len())
+                .zip(&active_freqs)
+            {
+                for (voice_freq, &interval) in chord.iter_mut().zip(&intervals) {
+                    *voice_freq = if freq > 0.0 {
+                        freq * shift_ratio * interval
+                    } else {
+                        0.0
+                    };
+                }
+            }
+            synth.render(&voice_freqs, &mut output);
+            debug!("output gain {:.3}", synth.gain());
+        }
+        let load = meter.stop();
+        if load > LOAD_WARNING {
+            warn!(
+                "processing took {:.0}% of the block duration; close to real-time limit",
+                load * 100.0
+            );
+        } else {
+            debug!("processing load {:.0}%", load * 100.0);
+        }
+        fade_in.apply(&mut output);
+        if let Some((_, output_meter)) = level_meters.as_mut()
+            && let Some(output_levels) = output_meter.push(&output)
+            && let Some(input_levels) = input_levels
+        {
+            info!("Input {}; output {}", input_levels, output_levels);
+        }
+        io.write(&output)?;
+        // The canceller models a single echo path, so it is fed the mono
+        // downmix of what was played.
+        for (mono, frame) in render_history.iter_mut().zip(output.chunks_exact(channels)) {
+            let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+            *mono = (sum / channels as i32) as i16;
+        }
+        blocks += 1;
     }
 
-    let denom = (samples.len() - 1) as f32;
-    for (n, sample) in samples.iter_mut().enumerate() {
-        let weight = 0.5 - 0.5 * (2.0 * PI * n as f32 / denom).cos();
-        *sample *= weight;
+    info!("Processed {} blocks; xruns: {}", blocks, io.xruns());
+    info!(
+        "Processing load: mean {:.0}%, peak {:.0}%",
+        meter.mean() * 100.0,
+        meter.peak() * 100.0
+    );
+    if let Some(canceller) = canceller.as_ref() {
+        info!("Final ERLE: {:.1} dB", canceller.erle_db());
     }
+    Ok(())
 }
 
-fn rms_level(samples: &[i16]) -> f32 {
-    if samples.is_empty() {
-        return 0.0;
+/// Plays the capture back unchanged, duplicated to every playback channel,
+/// in blocks of `block_len` frames until the input runs out or Ctrl-C is
+/// pressed.
+fn passthrough(io: &mut impl AudioIo, args: &Args, block_len: usize) -> Result<()> {
+    let channels = args.playback_channels();
+    let mut input = vec![0i16; block_len];
+    let mut output = vec![0i16; block_len * channels];
+    let mut reported_xruns = XrunCounts::default();
+    let mut blocks = 0u64;
+    let mut level_meters = args.meter.then(|| {
+        (
+            LevelMeter::new(METER_INTERVAL_MS, SAMPLE_RATE, 1),
+            LevelMeter::new(METER_INTERVAL_MS, SAMPLE_RATE, channels),
+        )
+    });
+    let mut fade_in = FadeIn::from_ms(args.fade_in_ms, SAMPLE_RATE, channels);
+    info!("Passing capture through to playback");
+
+    while !signal::interrupted() {
+        match io.read(&mut input) {
+            Err(err) if err.is::<EndOfInput>() => break,
+            result => result?,
+        }
+        let xruns = io.xruns();
+        if xruns != reported_xruns {
+            warn!("xruns: {}", xruns);
+            reported_xruns = xruns;
+        }
+        for (frame, &sample) in output.chunks_exact_mut(channels).zip(&input) {
+            frame.fill(sample);
+        }
+        fade_in.apply(&mut output);
+        if let Some((input_meter, output_meter)) = level_meters.as_mut() {
+            let input_levels = input_meter.push(&input);
+            if let Some(output_levels) = output_meter.push(&output)
+                && let Some(input_levels) = input_levels
+            {
+                info!("Input {}; output {}", input_levels, output_levels);
+            }
+        }
+        io.write(&output)?;
+        blocks += 1;
     }
 
-    let sum = samples
-        .iter()
-        .map(|&s| {
-            let v = s as f32;
-            v * v
-        })
-        .sum::<f32>();
-    let rms = (sum / samples.len() as f32).sqrt();
-    (rms / i16::MAX as f32).min(1.0)
+    info!("Processed {} blocks; xruns: {}", blocks, io.xruns());
+    Ok(())
 }
@@ -0,0 +1,182 @@
+//! Round-trip latency measurement.
+
+use std::f32::consts::PI;
+
+use anyhow::Result;
+
+use crate::audio::AudioIo;
+
+/// Length of the measurement sweep.
+const SWEEP_MS: u32 = 100;
+/// Start and end frequencies of the sweep.
+const SWEEP_START_HZ: f32 = 200.0;
+const SWEEP_END_HZ: f32 = 8000.0;
+/// Peak sweep level relative to full scale.
+const SWEEP_LEVEL: f32 = 0.25;
+/// Length of the raised-cosine fades at either end of the sweep.
+const SWEEP_FADE: usize = 64;
+/// Longest round trip searched for.
+const MAX_LATENCY_MS: u32 = 1000;
+/// Normalized correlation below which the sweep counts as not found.
+const MIN_CORRELATION: f32 = 0.2;
+
+/// Plays an exponential sine sweep through `io`, records the capture side and
+/// cross-correlates the two to find the loop delay.
+///
+/// Returns the number of samples between a sample being written and it
+/// reappearing in the capture stream, or `None` if the sweep could not be
+/// found (e.g. the microphone does not hear the speaker). `io` is driven in
+/// blocks of `block_len` frames with `channels` playback channels, the same
+/// read-then-write order the processing loops use, so the result includes
+/// all device buffering.
+pub fn measure_latency(
+    io: &mut impl AudioIo,
+    block_len: usize,
+    channels: usize,
+    sample_rate: u32,
+) -> Result<Option<usize>> {
+    assert!(block_len > 0, "block length must be positive");
+    assert!(channels > 0, "channel count must be positive");
+
+    let sweep = sweep(sample_rate);
+    let max_latency = (sample_rate as u64 * MAX_LATENCY_MS as u64 / 1000) as usize;
+    let blocks = (sweep.len() + max_latency).div_ceil(block_len) + 1;
+
+    let mut playback = vec![0.0f32; blocks * block_len];
+    playback[..sweep.len()].copy_from_slice(&sweep);
+
+    let mut capture = vec![0.0f32; blocks * block_len];
+    let mut input = vec![0i16; block_len];
+    let mut output = vec![0i16; block_len * channels];
+    for (played, recorded) in playback
+        .chunks_exact(block_len)
+        .zip(capture.chunks_exact_mut(block_len))
+    {
+        io.read(&mut input)?;
+        for (dst, &src) in recorded.iter_mut().zip(&input) {
+            *dst = src as f32 / 32768.0;
+        }
+        for (frame, &sample) in output.chunks_exact_mut(channels).zip(played) {
+            frame.fill((sample * 32767.0) as i16);
+        }
+        io.write(&output)?;
+    }
+
+    Ok(find_sweep(&capture, &sweep))
+}
+
+/// Generates the faded exponential sweep.
+fn sweep(sample_rate: u32) -> Vec<f32> {
+    let len = (sample_rate as u64 * SWEEP_MS as u64 / 1000) as usize;
+    let duration = len as f32 / sample_rate as f32;
+    let rate = (SWEEP_END_HZ / SWEEP_START_HZ).ln();
+    let fade = SWEEP_FADE.min(len / 2);
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / sample_rate as f32;
+            let phase =
+                2.0 * PI * SWEEP_START_HZ * duration / rate * ((t * rate / duration).exp() - 1.0);
+            let edge = n.min(len - 1 - n);
+            let gain = if edge < fade {
+                0.5 - 0.5 * (PI * edge as f32 / fade as f32).cos()
+            } else {
+                1.0
+            };
+            SWEEP_LEVEL * gain * phase.sin()
+        })
+        .collect()
+}
+
+/// Returns the offset in `capture` where `sweep` correlates best, if the
+/// normalized correlation there is convincing.
+fn find_sweep(capture: &[f32], sweep: &[f32]) -> Option<usize> {
+    if capture.len() < sweep.len() {
+        return None;
+    }
+    let sweep_energy: f32 = sweep.iter().map(|s| s * s).sum();
+    let mut window_energy: f32 = capture[..sweep.len()].iter().map(|s| s * s).sum();
+
+    let mut best = None;
+    let mut best_score = MIN_CORRELATION;
+    for lag in 0..=capture.len() - sweep.len() {
+        if lag > 0 {
+            let old = capture[lag - 1];
+            let new = capture[lag + sweep.len() - 1];
+            window_energy = (window_energy - old * old + new * new).max(0.0);
+        }
+        let norm = (sweep_energy * window_energy).sqrt();
+        if norm <= f32::EPSILON {
+            continue;
+        }
+        let dot: f32 = capture[lag..].iter().zip(sweep).map(|(c, s)| c * s).sum();
+        let score = dot.abs() / norm;
+        if score > best_score {
+            best_score = score;
+            best = Some(lag);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::audio::{EndOfInput, NullIo};
+
+    const SAMPLE_RATE: u32 = 48_000;
+    const BLOCK: usize = 256;
+
+    /// Mono backend feeding everything written back to the capture side
+    /// behind a primed block plus `delay` samples, like a device whose
+    /// playback buffer starts one block full.
+    struct Loopback {
+        pending: VecDeque<i16>,
+    }
+
+    impl Loopback {
+        fn new(delay: usize) -> Self {
+            Self {
+                pending: vec![0; BLOCK + delay].into(),
+            }
+        }
+    }
+
+    impl AudioIo for Loopback {
+        fn read(&mut self, buf: &mut [i16]) -> Result<()> {
+            for sample in buf {
+                *sample = self.pending.pop_front().expect("loopback underrun");
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, buf: &[i16]) -> Result<()> {
+            self.pending.extend(buf);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn silence_gives_no_latency() {
+        let latency = measure_latency(&mut NullIo::new(), BLOCK, 2, SAMPLE_RATE).unwrap();
+        assert_eq!(latency, None);
+    }
+
+    #[test]
+    fn exhausted_input_is_an_error() {
+        let err = measure_latency(&mut NullIo::with_blocks(3), BLOCK, 2, SAMPLE_RATE).unwrap_err();
+        assert!(err.is::<EndOfInput>(), "{err}");
+    }
+
+    #[test]
+    fn loopback_delay_is_found() {
+        // The sweep is written after the first read has drained the primed
+        // block, so it lands `delay` samples into the second capture block.
+        for delay in [0, 100, 2_000] {
+            let latency =
+                measure_latency(&mut Loopback::new(delay), BLOCK, 1, SAMPLE_RATE).unwrap();
+            assert_eq!(latency, Some(BLOCK + delay));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! DC offset removal for the capture path.
+
+use std::f32::consts::TAU;
+
+/// One-pole high-pass filter `y[n] = x[n] - x[n-1] + r * y[n-1]` removing
+/// the DC offset some ADCs add to the capture.
+///
+/// Left in, the offset inflates every energy estimate downstream, from level
+/// measurements to the render normalization of an echo canceller. The
+/// response is 3 dB down at the cutoff and flat well above it.
+#[derive(Clone, Debug)]
+pub struct DcBlocker {
+    pole: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl DcBlocker {
+    /// Creates a filter with a cutoff of `cutoff_hz` at `sample_rate`.
+    pub fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        assert!(
+            cutoff_hz.is_finite() && cutoff_hz > 0.0 && cutoff_hz < sample_rate as f32 / 2.0,
+            "cutoff must be between 0 Hz and the Nyquist frequency"
+        );
+        Self {
+            pole: (-TAU * cutoff_hz / sample_rate as f32).exp(),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    /// Filters `block` in place.
+    pub fn process(&mut self, block: &mut [i16]) {
+        for sample in block {
+            let input = *sample as f32;
+            let output = input - self.previous_input + self.pole * self.previous_output;
+            self.previous_input = input;
+            self.previous_output = output;
+            *sample = output.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::rms_level;
+
+    const SAMPLE_RATE: u32 = 48_000;
+    const CUTOFF_HZ: f32 = 10.0;
+
+    fn tone(freq: f32, amplitude: f32, offset: f32) -> Vec<i16> {
+        (0..SAMPLE_RATE as usize)
+            .map(|idx| {
+                let t = idx as f32 / SAMPLE_RATE as f32;
+                (offset + amplitude * (TAU * freq * t).sin()).round() as i16
+            })
+            .collect()
+    }
+
+    /// Filters a second of `samples` and returns the last tenth of it.
+    fn settled(mut samples: Vec<i16>) -> Vec<i16> {
+        DcBlocker::new(CUTOFF_HZ, SAMPLE_RATE).process(&mut samples);
+        samples.split_off(samples.len() - SAMPLE_RATE as usize / 10)
+    }
+
+    #[test]
+    fn constant_offset_decays_to_zero() {
+        let output = settled(vec![1000; SAMPLE_RATE as usize]);
+        assert!(output.iter().all(|&sample| sample.abs() <= 1), "{output:?}");
+    }
+
+    #[test]
+    fn tone_above_the_cutoff_passes_unchanged() {
+        let input = tone(1000.0, 8000.0, 0.0);
+        let output = settled(tone(1000.0, 8000.0, 2000.0));
+        let expected = rms_level(&input[input.len() - output.len()..]);
+        let gain = rms_level(&output) / expected;
+        assert!((gain - 1.0).abs() < 0.01, "gain {gain}");
+
+        let mean = output.iter().map(|&s| f32::from(s)).sum::<f32>() / output.len() as f32;
+        assert!(mean.abs() < 5.0, "offset {mean} left");
+    }
+
+    #[test]
+    fn cutoff_is_three_db_down() {
+        let output = settled(tone(CUTOFF_HZ, 8000.0, 0.0));
+        let gain = rms_level(&output) / (8000.0 / i16::MAX as f32 / 2.0f32.sqrt());
+        assert!((gain - 0.5f32.sqrt()).abs() < 0.02, "gain {gain}");
+    }
+}
@@ -0,0 +1,63 @@
+//! Start-up fade-in for the playback path.
+
+/// Linear fade-in over the first frames written to a playback device.
+///
+/// Opening the device and starting playback mid-signal otherwise produces an
+/// audible click. Frames are interleaved with `channels` samples each; every
+/// sample of a frame gets the same gain, and once the ramp has run out blocks
+/// pass unchanged.
+#[derive(Clone, Debug)]
+pub struct FadeIn {
+    frames: usize,
+    channels: usize,
+    position: usize,
+}
+
+impl FadeIn {
+    /// Creates a ramp rising from silence to full level over `frames` frames.
+    /// Zero frames disables it.
+    pub fn new(frames: usize, channels: usize) -> Self {
+        assert!(channels > 0, "channels must be positive");
+        Self {
+            frames,
+            channels,
+            position: 0,
+        }
+    }
+
+    /// Creates a ramp lasting `ms` milliseconds at `sample_rate`.
+    pub fn from_ms(ms: f32, sample_rate: u32, channels: usize) -> Self {
+        assert!(
+            ms.is_finite() && ms >= 0.0,
+            "fade-in time must be non-negative"
+        );
+        Self::new(
+            (ms * sample_rate as f32 / 1000.0).round() as usize,
+            channels,
+        )
+    }
+
+    /// Returns whether the ramp has reached full level.
+    pub fn is_done(&self) -> bool {
+        self.position >= self.frames
+    }
+
+    /// Applies the next stretch of the ramp to `block` in place.
+    pub fn apply(&mut self, block: &mut [i16]) {
+        assert_eq!(
+            block.len() % self.channels,
+            0,
+            "block must hold whole frames"
+        );
+        for frame in block.chunks_exact_mut(self.channels) {
+            if self.is_done() {
+                return;
+            }
+            let gain = self.position as f32 / self.frames as f32;
+            for sample in frame {
+                *sample = (*sample as f32 * gain).round() as i16;
+            }
+            self.position += 1;
+        }
+    }
+}
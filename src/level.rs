@@ -0,0 +1,197 @@
+//! Signal level measurements.
+
+use std::fmt;
+
+/// Returns the RMS level of `samples` relative to full scale, between 0.0 and
+/// 1.0. An empty slice is silent.
+pub fn rms_level(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum = samples
+        .iter()
+        .map(|&s| {
+            let v = s as f32;
+            v * v
+        })
+        .sum::<f32>();
+    let rms = (sum / samples.len() as f32).sqrt();
+    (rms / i16::MAX as f32).min(1.0)
+}
+
+/// Returns the RMS level of `samples` in dBFS, at most 0.0. Silence, including
+/// an empty slice, reads as -120 dBFS.
+pub fn rms_dbfs(samples: &[i16]) -> f32 {
+    to_db(rms_level(samples))
+}
+
+/// Returns the largest absolute sample of `samples` relative to full scale,
+/// between 0.0 and 1.0. An empty slice is silent.
+pub fn peak_level(samples: &[i16]) -> f32 {
+    let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+    (peak as f32 / i16::MAX as f32).min(1.0)
+}
+
+/// Lowest level reported by [`Levels`] and [`rms_dbfs`], standing in for
+/// silence.
+const METER_FLOOR_DB: f32 = -120.0;
+
+/// RMS and peak level of a signal over one metering interval, relative to
+/// full scale.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Levels {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+impl Levels {
+    /// Returns whether any sample reached full scale.
+    pub fn is_clipping(&self) -> bool {
+        self.peak >= 1.0
+    }
+}
+
+impl fmt::Display for Levels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RMS {:.1} dBFS, peak {:.1} dBFS",
+            to_db(self.rms),
+            to_db(self.peak)
+        )?;
+        if self.is_clipping() {
+            write!(f, " (clipping)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates the RMS and peak level of a stream and reports them once per
+/// interval, for a periodic level meter.
+#[derive(Debug)]
+pub struct LevelMeter {
+    interval: usize,
+    samples: usize,
+    sum_squares: f64,
+    peak: f32,
+}
+
+impl LevelMeter {
+    /// Creates a meter reporting every `interval_ms` of audio with
+    /// `channels` interleaved channels at `sample_rate`.
+    pub fn new(interval_ms: f32, sample_rate: u32, channels: usize) -> Self {
+        assert!(
+            interval_ms.is_finite() && interval_ms > 0.0,
+            "interval must be positive"
+        );
+        let frames = (interval_ms * sample_rate as f32 / 1000.0).round() as usize;
+        Self {
+            interval: frames.max(1) * channels,
+            samples: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    /// Adds a block of samples, returning the levels since the last report
+    /// once a full interval has been collected.
+    pub fn push(&mut self, samples: &[i16]) -> Option<Levels> {
+        self.sum_squares += samples
+            .iter()
+            .map(|&s| {
+                let v = s as f64;
+                v * v
+            })
+            .sum::<f64>();
+        self.peak = self.peak.max(peak_level(samples));
+        self.samples += samples.len();
+        if self.samples < self.interval {
+            return None;
+        }
+
+        let rms = (self.sum_squares / self.samples as f64).sqrt() as f32;
+        let levels = Levels {
+            rms: (rms / i16::MAX as f32).min(1.0),
+            peak: self.peak,
+        };
+        self.samples = 0;
+        self.sum_squares = 0.0;
+        self.peak = 0.0;
+        Some(levels)
+    }
+}
+
+fn to_db(level: f32) -> f32 {
+    if level > 0.0 {
+        (20.0 * level.log10()).max(METER_FLOOR_DB)
+    } else {
+        METER_FLOOR_DB
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_input_is_silent() {
+        assert_eq!(rms_level(&[]), 0.0);
+        assert_eq!(peak_level(&[]), 0.0);
+        assert_eq!(rms_dbfs(&[]), METER_FLOOR_DB);
+    }
+
+    #[test]
+    fn full_scale_reads_as_one() {
+        let square = [i16::MAX, i16::MIN, i16::MAX, i16::MIN];
+        assert_eq!(rms_level(&square), 1.0);
+        assert_eq!(peak_level(&square), 1.0);
+        assert_eq!(rms_dbfs(&square), 0.0);
+        // The negative rail is one step further out than the positive one.
+        assert_eq!(peak_level(&[i16::MIN]), 1.0);
+    }
+
+    #[test]
+    fn quiet_levels_bottom_out_at_the_floor() {
+        assert_eq!(rms_dbfs(&[0; 64]), METER_FLOOR_DB);
+        // A single LSB in a million samples sits below -120 dBFS.
+        let mut samples = vec![0; 1_000_000];
+        samples[0] = 1;
+        assert_eq!(rms_dbfs(&samples), METER_FLOOR_DB);
+        // Half scale is about -6 dBFS.
+        assert!((rms_dbfs(&[16_384, -16_384]) + 6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn meter_reports_once_per_interval() {
+        // 1 ms of stereo at 48 kHz is 96 samples.
+        let mut meter = LevelMeter::new(1.0, 48_000, 2);
+        assert_eq!(meter.push(&[0; 95]), None);
+        let levels = meter.push(&[i16::MAX; 1]).unwrap();
+        assert_eq!(levels.peak, 1.0);
+        assert!(levels.is_clipping());
+        assert!((levels.rms - (1.0 / 96.0f32).sqrt()).abs() < 1e-6);
+
+        // The next interval starts from scratch.
+        let levels = meter.push(&[1_000; 96]).unwrap();
+        assert!(!levels.is_clipping());
+        assert!((levels.rms - 1_000.0 / i16::MAX as f32).abs() < 1e-6);
+        assert_eq!(levels.peak, levels.rms);
+    }
+
+    #[test]
+    fn silent_levels_display_at_the_floor() {
+        assert_eq!(
+            Levels::default().to_string(),
+            "RMS -120.0 dBFS, peak -120.0 dBFS"
+        );
+        let clipping = Levels {
+            rms: 1.0,
+            peak: 1.0,
+        };
+        assert_eq!(
+            clipping.to_string(),
+            "RMS 0.0 dBFS, peak 0.0 dBFS (clipping)"
+        );
+    }
+}
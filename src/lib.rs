@@ -0,0 +1,13 @@
+//! Audio plumbing shared by the jammer binaries.
+
+pub mod agc;
+pub mod audio;
+pub mod calibration;
+pub mod dc_blocker;
+pub mod fade;
+pub mod level;
+pub mod load;
+pub mod logging;
+pub mod pitch_shift;
+pub mod signal;
+pub mod synth;
@@ -0,0 +1,59 @@
+//! Real-time headroom measurement for the processing loops.
+
+use std::time::{Duration, Instant};
+
+/// Measures how much of each block's real-time duration the processing
+/// takes.
+///
+/// A load of 1.0 means processing a block took exactly as long as playing
+/// it; above that the loop cannot keep up and xruns follow.
+#[derive(Debug)]
+pub struct LoadMeter {
+    budget: Duration,
+    started: Instant,
+    total: f64,
+    peak: f32,
+    blocks: u64,
+}
+
+impl LoadMeter {
+    /// Creates a meter for blocks of `block_frames` frames at `sample_rate`.
+    pub fn new(block_frames: usize, sample_rate: u32) -> Self {
+        assert!(sample_rate > 0, "sample rate must be positive");
+        Self {
+            budget: Duration::from_secs_f64(block_frames as f64 / sample_rate as f64),
+            started: Instant::now(),
+            total: 0.0,
+            peak: 0.0,
+            blocks: 0,
+        }
+    }
+
+    /// Marks the start of a block's processing.
+    pub fn start(&mut self) {
+        self.started = Instant::now();
+    }
+
+    /// Marks the end of a block's processing and returns its load.
+    pub fn stop(&mut self) -> f32 {
+        let load = (self.started.elapsed().as_secs_f64() / self.budget.as_secs_f64()) as f32;
+        self.total += load as f64;
+        self.peak = self.peak.max(load);
+        self.blocks += 1;
+        load
+    }
+
+    /// Returns the average load over all measured blocks.
+    pub fn mean(&self) -> f32 {
+        if self.blocks == 0 {
+            0.0
+        } else {
+            (self.total / self.blocks as f64) as f32
+        }
+    }
+
+    /// Returns the highest load of any measured block.
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+}
@@ -0,0 +1,19 @@
+//! Logger setup for the jammer binaries.
+
+use log::LevelFilter;
+
+/// Initializes the global logger writing to stderr.
+///
+/// `verbosity` counts `-v` flags: none logs at info level, one adds debug and
+/// two or more add trace. `RUST_LOG`, when set, takes precedence.
+pub fn init(verbosity: u8) {
+    let level = match verbosity {
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .init();
+}
@@ -0,0 +1,192 @@
+//! Time-domain pitch shifting of the captured signal.
+
+use std::f32::consts::PI;
+
+/// Pitch shifter reading a delay line through two crossfaded taps.
+///
+/// Each tap's delay sweeps across the window at a rate set by the shift
+/// ratio, so the audio under it plays back faster or slower, and jumps back
+/// when it reaches the end of the window. The two taps are half a window
+/// apart and weighted by complementary raised-cosine fades, each fully faded
+/// out at its jump, so the output is continuous and the input's timbre is
+/// carried over instead of being replaced by synthetic tones. The jumps are
+/// not aligned to the pitch period, so steady tones pick up sidebands at the
+/// jump rate, `|ratio - 1|` per window. Longer windows move those closer
+/// together and warble less on sustained notes but smear transients; the
+/// average latency is half a window.
+///
+/// Mono input is written to every channel of the interleaved output.
+#[derive(Clone, Debug)]
+pub struct PitchShifter {
+    ratio: f32,
+    window: f32,
+    channels: usize,
+    /// Recent input samples, written circularly at `write_pos`.
+    buffer: Vec<f32>,
+    write_pos: usize,
+    /// Position of the first tap within the window, in `[0, 1)`.
+    phase: f32,
+    gain: f32,
+    target_gain: f32,
+}
+
+impl PitchShifter {
+    /// Creates a shifter multiplying frequencies by `ratio`, with taps
+    /// sweeping a window of `window_ms` milliseconds at `sample_rate`.
+    pub fn new(ratio: f32, window_ms: f32, channels: usize, sample_rate: u32) -> Self {
+        assert!(channels > 0, "channels must be positive");
+        let window = window_ms * sample_rate as f32 / 1000.0;
+        assert!(
+            window.is_finite() && window >= 2.0,
+            "window must span at least two samples"
+        );
+        let mut shifter = Self {
+            ratio: 1.0,
+            window,
+            channels,
+            // Room for the longest delay plus the interpolation neighbour.
+            buffer: vec![0.0; window.ceil() as usize + 2],
+            write_pos: 0,
+            phase: 0.0,
+            gain: 1.0,
+            target_gain: 1.0,
+        };
+        shifter.set_ratio(ratio);
+        shifter
+    }
+
+    /// Sets the frequency ratio, 2.0 being an octave up.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        assert!(
+            ratio.is_finite() && ratio > 0.0,
+            "ratio must be positive and finite"
+        );
+        self.ratio = ratio;
+    }
+
+    /// Sets the output gain, reached by a linear ramp over the next
+    /// processed block.
+    pub fn set_gain(&mut self, gain: f32) {
+        assert!(gain.is_finite() && gain >= 0.0, "gain must be non-negative");
+        self.target_gain = gain;
+    }
+
+    /// Shifts `input` into `output`, which holds one interleaved frame per
+    /// input sample.
+    pub fn process(&mut self, input: &[i16], output: &mut [i16]) {
+        assert_eq!(
+            output.len(),
+            input.len() * self.channels,
+            "output must hold one frame per input sample"
+        );
+        let gain_step = (self.target_gain - self.gain) / input.len().max(1) as f32;
+        // A tap's delay shrinks by `ratio - 1` samples per sample to play
+        // its audio `ratio` times as fast.
+        let phase_step = (1.0 - self.ratio) / self.window;
+
+        for (&sample, frame) in input.iter().zip(output.chunks_exact_mut(self.channels)) {
+            self.buffer[self.write_pos] = sample as f32;
+            let second = (self.phase + 0.5).fract();
+            let shifted = self.tap(self.phase) + self.tap(second);
+
+            self.gain += gain_step;
+            let value = (shifted * self.gain)
+                .round()
+                .clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            frame.fill(value);
+
+            self.phase = (self.phase + phase_step).rem_euclid(1.0);
+            self.write_pos = (self.write_pos + 1) % self.buffer.len();
+        }
+        self.gain = self.target_gain;
+    }
+
+    /// Reads the delay line at the delay `phase` of a window behind the
+    /// newest sample, weighted by the fade for that position.
+    fn tap(&self, phase: f32) -> f32 {
+        let delay = phase * self.window;
+        let whole = delay as usize;
+        let frac = delay - whole as f32;
+        let len = self.buffer.len();
+        let newer = self.buffer[(self.write_pos + len - whole) % len];
+        let older = self.buffer[(self.write_pos + len - whole - 1) % len];
+        let fade = (PI * phase).sin();
+        (newer + (older - newer) * frac) * fade * fade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+    const WINDOW_MS: f32 = 30.0;
+    /// Half of the 1440-sample window.
+    const LATENCY: usize = 720;
+    const FREQ: f32 = 200.0;
+    const AMPLITUDE: f32 = 8000.0;
+
+    fn sine(len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|idx| {
+                let t = idx as f32 / SAMPLE_RATE as f32;
+                (AMPLITUDE * (2.0 * PI * FREQ * t).sin()).round() as i16
+            })
+            .collect()
+    }
+
+    fn shift(ratio: f32, input: &[i16]) -> Vec<i16> {
+        let mut shifter = PitchShifter::new(ratio, WINDOW_MS, 1, SAMPLE_RATE);
+        let mut output = vec![0; input.len()];
+        shifter.process(input, &mut output);
+        output
+    }
+
+    fn zero_crossings(samples: &[i16]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0) != (pair[1] < 0))
+            .count()
+    }
+
+    #[test]
+    fn octave_up_doubles_the_frequency() {
+        let input = sine(SAMPLE_RATE as usize);
+        let output = shift(2.0, &input);
+        // Skip the window that fills the delay line.
+        let settled = 2 * LATENCY..;
+        let ratio = zero_crossings(&output[settled.clone()]) as f32
+            / zero_crossings(&input[settled]) as f32;
+        assert!((ratio - 2.0).abs() < 0.05, "frequency ratio {ratio}");
+    }
+
+    #[test]
+    fn unity_ratio_delays_by_half_a_window() {
+        let input = sine(4 * LATENCY);
+        let mut shifter = PitchShifter::new(1.0, WINDOW_MS, 2, SAMPLE_RATE);
+        let mut output = vec![0; 2 * input.len()];
+        shifter.process(&input, &mut output);
+
+        for (idx, frame) in output.chunks_exact(2).enumerate() {
+            let expected = idx.checked_sub(LATENCY).map_or(0, |src| input[src]);
+            assert_eq!(frame, [expected, expected], "frame {idx}");
+        }
+    }
+
+    #[test]
+    fn crossfade_keeps_the_output_continuous() {
+        // The steepest step a clean sine of this frequency takes.
+        let max_slope = 2.0 * PI * FREQ * AMPLITUDE / SAMPLE_RATE as f32;
+        for ratio in [0.5, 0.8, 1.5, 2.0] {
+            let output = shift(ratio, &sine(SAMPLE_RATE as usize / 2));
+            let max_step = output
+                .windows(2)
+                .map(|pair| (pair[1] as f32 - pair[0] as f32).abs())
+                .fold(0.0, f32::max);
+            assert!(
+                max_step < 2.5 * ratio.max(1.0) * max_slope,
+                "ratio {ratio} stepped by {max_step}"
+            );
+        }
+    }
+}
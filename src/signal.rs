@@ -0,0 +1,20 @@
+//! Ctrl-C handling for the processing loops.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a SIGINT handler that makes [`interrupted`] return `true` instead
+/// of killing the process, so loops can stop at a block boundary and close
+/// their devices cleanly.
+pub fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::Relaxed))
+        .context("install Ctrl-C handler")
+}
+
+/// Returns whether Ctrl-C has been pressed since the handler was installed.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
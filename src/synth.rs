@@ -0,0 +1,735 @@
+//! Polyphonic oscillator bank used to voice detected pitches.
+
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Fraction of the distance to the target gain covered per rendered block.
+const GAIN_SMOOTHING: f32 = 0.15;
+const COMPRESSOR_KNEE_DB: f32 = 6.0;
+const COMPRESSOR_ATTACK_MS: f32 = 1.0;
+const COMPRESSOR_RELEASE_MS: f32 = 100.0;
+
+/// Oscillator shape used for each synthesized voice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Saw,
+    Square,
+    Triangle,
+}
+
+impl Waveform {
+    /// Maps an oscillator phase in `[0, 2π)` to a sample in roughly `[-1, 1]`.
+    ///
+    /// The discontinuities of the saw and square are smoothed with PolyBLEP
+    /// residuals and the corners of the triangle with PolyBLAMP residuals,
+    /// both spanning one `phase_step` either side of the edge, so harmonics
+    /// beyond Nyquist are strongly attenuated instead of folding back.
+    pub fn sample(self, phase: f32, phase_step: f32) -> f32 {
+        let t = phase / (2.0 * PI);
+        let dt = (phase_step / (2.0 * PI)).min(0.5);
+        let half = (t + 0.5).fract();
+        match self {
+            Waveform::Sine => phase.sin(),
+            Waveform::Saw => 2.0 * t - 1.0 - poly_blep(t, dt),
+            Waveform::Square => {
+                let naive = if t < 0.5 { 1.0 } else { -1.0 };
+                naive + poly_blep(t, dt) - poly_blep(half, dt)
+            }
+            Waveform::Triangle => {
+                let naive = 1.0 - 2.0 * (2.0 * t - 1.0).abs();
+                naive + 4.0 * dt * (poly_blamp(t, dt) - poly_blamp(half, dt))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Waveform::Sine => "sine",
+            Waveform::Saw => "saw",
+            Waveform::Square => "square",
+            Waveform::Triangle => "triangle",
+        })
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sine" => Ok(Waveform::Sine),
+            "saw" => Ok(Waveform::Saw),
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            _ => Err(format!(
+                "unknown waveform `{s}` (expected sine, saw, square or triangle)"
+            )),
+        }
+    }
+}
+
+/// Polynomial band-limited step residual for a unit phase `t` with a
+/// discontinuity at zero, `dt` being the phase increment per sample.
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = t / dt;
+        x + x - x * x - 1.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt;
+        x * x + x + x + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Integrated [`poly_blep`], smoothing a slope discontinuity at zero.
+fn poly_blamp(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = t / dt - 1.0;
+        -x * x * x / 3.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt + 1.0;
+        x * x * x / 3.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EnvelopeStage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Linear attack/decay/sustain/release envelope for one voice.
+///
+/// Attack and decay times are measured over their full travel; the release
+/// time is the time to fall from full scale, so releasing from a lower
+/// sustain level finishes proportionally sooner.
+#[derive(Clone, Debug)]
+struct Adsr {
+    attack_step: f32,
+    decay_step: f32,
+    sustain: f32,
+    release_step: f32,
+    stage: EnvelopeStage,
+    level: f32,
+}
+
+impl Adsr {
+    fn new(sample_rate: u32, attack_ms: f32, decay_ms: f32, sustain: f32, release_ms: f32) -> Self {
+        let step = |ms: f32, span: f32| {
+            let samples = ms * sample_rate as f32 / 1000.0;
+            if samples >= 1.0 { span / samples } else { span }
+        };
+        Self {
+            attack_step: step(attack_ms, 1.0),
+            decay_step: step(decay_ms, 1.0 - sustain),
+            sustain,
+            release_step: step(release_ms, 1.0),
+            stage: EnvelopeStage::Idle,
+            level: 0.0,
+        }
+    }
+
+    /// Opens or closes the gate. Re-opening during the release restarts the
+    /// attack from the current level rather than from silence.
+    fn gate(&mut self, on: bool) {
+        match (on, self.stage) {
+            (true, EnvelopeStage::Idle | EnvelopeStage::Release) => {
+                self.stage = EnvelopeStage::Attack;
+            }
+            (false, EnvelopeStage::Attack | EnvelopeStage::Decay | EnvelopeStage::Sustain) => {
+                self.stage = EnvelopeStage::Release;
+            }
+            _ => {}
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.stage == EnvelopeStage::Idle
+    }
+
+    /// Advances one sample and returns the envelope gain.
+    fn next(&mut self) -> f32 {
+        match self.stage {
+            EnvelopeStage::Idle => {}
+            EnvelopeStage::Attack => {
+                self.level += self.attack_step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = EnvelopeStage::Decay;
+                }
+            }
+            EnvelopeStage::Decay => {
+                self.level -= self.decay_step;
+                if self.level <= self.sustain {
+                    self.level = self.sustain;
+                    self.stage = EnvelopeStage::Sustain;
+                }
+            }
+            EnvelopeStage::Sustain => {}
+            EnvelopeStage::Release => {
+                self.level -= self.release_step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = EnvelopeStage::Idle;
+                }
+            }
+        }
+        self.level
+    }
+}
+
+/// Playback state of one voice slot.
+///
+/// `freq` glides towards `target_freq` by `glide` of the remaining interval
+/// (measured in log frequency) each sample; a `glide` of one jumps straight
+/// to the target.
+#[derive(Clone, Debug)]
+struct Voice {
+    freq: f32,
+    target_freq: f32,
+    glide: f32,
+    phase: f32,
+    gain: f32,
+    pan: f32,
+    envelope: Adsr,
+}
+
+impl Voice {
+    fn advance_glide(&mut self) {
+        if self.glide >= 1.0 {
+            self.freq = self.target_freq;
+        } else if self.freq != self.target_freq {
+            self.freq *= (self.target_freq / self.freq).powf(self.glide);
+        }
+    }
+
+    /// Constant-power left/right gains for the voice's pan position.
+    fn pan_gains(&self) -> (f32, f32) {
+        let angle = (self.pan + 1.0) * PI / 4.0;
+        (angle.cos(), angle.sin())
+    }
+}
+
+/// Low-frequency oscillator shared by all voices for pitch vibrato.
+#[derive(Clone, Debug)]
+struct Vibrato {
+    step: f32,
+    depth_cents: f32,
+    phase: f32,
+}
+
+impl Vibrato {
+    /// Advances one sample and returns the frequency multiplier to apply.
+    fn next(&mut self) -> f32 {
+        if self.depth_cents == 0.0 {
+            return 1.0;
+        }
+        let ratio = (self.depth_cents * self.phase.sin() / 1200.0).exp2();
+        self.phase += self.step;
+        if self.phase > 2.0 * PI {
+            self.phase -= 2.0 * PI;
+        }
+        ratio
+    }
+}
+
+/// Soft-knee peak compressor for the mixed synthesis output.
+///
+/// Levels more than half the knee below the threshold pass unchanged; above
+/// the knee the output rises by only `1 / ratio` dB per input dB. The gain
+/// reduction follows the peak of each frame (linked across channels) with
+/// the attack and release times, so peaks are turned down instead of
+/// clipped.
+#[derive(Clone, Debug)]
+struct Compressor {
+    threshold_db: f32,
+    ratio: f32,
+    knee_db: f32,
+    attack: f32,
+    release: f32,
+    reduction_db: f32,
+}
+
+impl Compressor {
+    fn new(
+        sample_rate: u32,
+        threshold_db: f32,
+        ratio: f32,
+        knee_db: f32,
+        attack_ms: f32,
+        release_ms: f32,
+    ) -> Self {
+        let coefficient = |ms: f32| 1.0 - (-1000.0 / (ms * sample_rate as f32)).exp();
+        Self {
+            threshold_db,
+            ratio,
+            knee_db,
+            attack: coefficient(attack_ms),
+            release: coefficient(release_ms),
+            reduction_db: 0.0,
+        }
+    }
+
+    /// Returns the static gain change in dB (never positive) for a peak
+    /// level in dBFS.
+    fn curve(&self, level_db: f32) -> f32 {
+        let over = level_db - self.threshold_db;
+        let slope = 1.0 / self.ratio - 1.0;
+        if 2.0 * over < -self.knee_db {
+            0.0
+        } else if 2.0 * over.abs() <= self.knee_db {
+            let into_knee = over + self.knee_db / 2.0;
+            slope * into_knee * into_knee / (2.0 * self.knee_db)
+        } else {
+            slope * over
+        }
+    }
+
+    /// Returns the linear gain for a frame whose largest magnitude relative
+    /// to full scale is `peak`.
+    fn gain(&mut self, peak: f32) -> f32 {
+        let target = self.curve(20.0 * peak.max(1e-6).log10());
+        let coefficient = if target < self.reduction_db {
+            self.attack
+        } else {
+            self.release
+        };
+        self.reduction_db += (target - self.reduction_db) * coefficient;
+        10.0f32.powf(self.reduction_db / 20.0)
+    }
+}
+
+/// Triangular-PDF dither spanning ±1 LSB, added before the final rounding to
+/// `i16` so quiet passages decay into a flat noise floor instead of
+/// quantization distortion.
+#[derive(Clone, Debug)]
+struct Dither {
+    state: u32,
+}
+
+impl Dither {
+    fn new() -> Self {
+        Self { state: 0x2545_F491 }
+    }
+
+    /// Returns the next dither value in `(-1.0, 1.0)`, in LSBs.
+    fn next(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+
+    /// Uniform value in `[0.0, 1.0)` from a xorshift generator.
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Bank of enveloped oscillators rendering interleaved `i16` blocks.
+///
+/// Each call to [`render`](Self::render) takes one frequency per voice: a
+/// positive frequency holds the voice's gate open and becomes its pitch
+/// target, zero releases it. Released voices keep sounding at their last
+/// frequency until their envelope runs out, and oscillator phases carry over
+/// between blocks. The overall output gain eases towards
+/// [`set_target_gain`](Self::set_target_gain) once per block, is scaled by the
+/// fixed [master gain](Self::set_master_gain_db), and the mix passes through
+/// a soft-knee compressor before being quantized, with optional
+/// [dither](Self::set_dither).
+///
+/// The envelope, glide and vibrato default to none: voices start and stop
+/// instantly on a sine wave, centred.
+#[derive(Clone, Debug)]
+pub struct Synthesizer {
+    sample_rate: u32,
+    channels: usize,
+    waveform: Waveform,
+    voices: Vec<Voice>,
+    vibrato: Vibrato,
+    compressor: Compressor,
+    gain: f32,
+    target_gain: f32,
+    master_gain: f32,
+    dither: Option<Dither>,
+}
+
+impl Synthesizer {
+    /// Creates a silent synthesizer with `voices` voices rendering `channels`
+    /// (1 or 2) interleaved channels at `sample_rate`.
+    pub fn new(voices: usize, channels: usize, sample_rate: u32) -> Self {
+        assert!(
+            channels == 1 || channels == 2,
+            "only mono and stereo are supported"
+        );
+        assert!(sample_rate > 0, "sample rate must be positive");
+        let voice = Voice {
+            freq: 0.0,
+            target_freq: 0.0,
+            glide: 1.0,
+            phase: 0.0,
+            gain: 1.0,
+            pan: 0.0,
+            envelope: Adsr::new(sample_rate, 0.0, 0.0, 1.0, 0.0),
+        };
+        Self {
+            sample_rate,
+            channels,
+            waveform: Waveform::default(),
+            voices: vec![voice; voices],
+            vibrato: Vibrato {
+                step: 0.0,
+                depth_cents: 0.0,
+                phase: 0.0,
+            },
+            compressor: Compressor::new(
+                sample_rate,
+                0.0,
+                1.0,
+                COMPRESSOR_KNEE_DB,
+                COMPRESSOR_ATTACK_MS,
+                COMPRESSOR_RELEASE_MS,
+            ),
+            gain: 0.0,
+            target_gain: 0.0,
+            master_gain: 1.0,
+            dither: None,
+        }
+    }
+
+    /// Returns the number of voices.
+    pub fn voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Returns the number of interleaved output channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Selects the oscillator shape of all voices.
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
+    /// Replaces the envelope of every voice, silencing any that are
+    /// sounding. Times are in milliseconds and `sustain` is a level in
+    /// `[0, 1]`.
+    pub fn set_envelope(&mut self, attack_ms: f32, decay_ms: f32, sustain: f32, release_ms: f32) {
+        assert!(
+            [attack_ms, decay_ms, release_ms]
+                .iter()
+                .all(|ms| ms.is_finite() && *ms >= 0.0),
+            "envelope times must be non-negative"
+        );
+        assert!(
+            (0.0..=1.0).contains(&sustain),
+            "sustain level must be between 0.0 and 1.0"
+        );
+        let envelope = Adsr::new(self.sample_rate, attack_ms, decay_ms, sustain, release_ms);
+        for voice in &mut self.voices {
+            voice.envelope = envelope.clone();
+        }
+    }
+
+    /// Spreads the voices evenly across the stereo field, the first hard
+    /// left and the last hard right at a `spread` of one. Has no audible
+    /// effect in mono.
+    pub fn set_pan_spread(&mut self, spread: f32) {
+        assert!(
+            (0.0..=1.0).contains(&spread),
+            "pan spread must be between 0.0 and 1.0"
+        );
+        let count = self.voices.len();
+        for (idx, voice) in self.voices.iter_mut().enumerate() {
+            voice.pan = if count > 1 {
+                spread * (2.0 * idx as f32 / (count - 1) as f32 - 1.0)
+            } else {
+                0.0
+            };
+        }
+    }
+
+    /// Sets the portamento time constant in milliseconds used when a
+    /// sounding voice changes pitch (0 jumps immediately).
+    pub fn set_glide_ms(&mut self, glide_ms: f32) {
+        assert!(
+            glide_ms.is_finite() && glide_ms >= 0.0,
+            "glide time must be non-negative"
+        );
+        let glide_samples = glide_ms * self.sample_rate as f32 / 1000.0;
+        let glide = if glide_samples > 0.0 {
+            1.0 - (-1.0 / glide_samples).exp()
+        } else {
+            1.0
+        };
+        for voice in &mut self.voices {
+            voice.glide = glide;
+        }
+    }
+
+    /// Sets the shared vibrato LFO rate and depth (0 cents disables it).
+    pub fn set_vibrato(&mut self, rate_hz: f32, depth_cents: f32) {
+        assert!(
+            rate_hz.is_finite() && rate_hz >= 0.0 && depth_cents.is_finite() && depth_cents >= 0.0,
+            "vibrato rate and depth must be non-negative"
+        );
+        self.vibrato.step = 2.0 * PI * rate_hz / self.sample_rate as f32;
+        self.vibrato.depth_cents = depth_cents;
+    }
+
+    /// Configures the output compressor. A `ratio` of one disables it.
+    pub fn set_compressor(&mut self, threshold_db: f32, ratio: f32) {
+        assert!(
+            threshold_db.is_finite() && threshold_db <= 0.0,
+            "compressor threshold must be at most 0 dBFS"
+        );
+        assert!(ratio >= 1.0, "compressor ratio must be at least 1");
+        self.compressor.threshold_db = threshold_db;
+        self.compressor.ratio = ratio;
+    }
+
+    /// Sets the overall output gain the synthesizer eases towards, relative
+    /// to full scale.
+    pub fn set_target_gain(&mut self, gain: f32) {
+        self.target_gain = gain.clamp(0.0, 1.0);
+    }
+
+    /// Sets a fixed gain in dB applied on top of the target gain. Boosts
+    /// beyond full scale are held back by the compressor, and clipped if it
+    /// is disabled.
+    pub fn set_master_gain_db(&mut self, gain_db: f32) {
+        assert!(gain_db.is_finite(), "master gain must be finite");
+        self.master_gain = 10.0f32.powf(gain_db / 20.0);
+    }
+
+    /// Enables adding TPDF dither before samples are rounded to `i16`.
+    /// Without it samples are truncated towards zero.
+    pub fn set_dither(&mut self, enabled: bool) {
+        self.dither = enabled.then(Dither::new);
+    }
+
+    /// Returns the current smoothed output gain.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Scales a single voice, e.g. by detection confidence. Released voices
+    /// fade from whatever gain they were last given.
+    pub fn set_voice_gain(&mut self, voice: usize, gain: f32) {
+        self.voices[voice].gain = gain.clamp(0.0, 1.0);
+    }
+
+    /// Renders the next block into `out`, which holds interleaved frames of
+    /// [`channels`](Self::channels) samples, with `freqs` holding one
+    /// frequency in Hz per voice.
+    pub fn render(&mut self, freqs: &[f32], out: &mut [i16]) {
+        assert_eq!(freqs.len(), self.voices.len(), "one frequency per voice");
+        assert_eq!(
+            out.len() % self.channels,
+            0,
+            "output must hold whole frames"
+        );
+
+        for (voice, &freq) in self.voices.iter_mut().zip(freqs) {
+            let sounding = freq > 0.0;
+            if sounding {
+                voice.target_freq = freq;
+                if voice.envelope.is_idle() {
+                    // A fresh note starts on pitch rather than sliding in.
+                    voice.freq = voice.target_freq;
+                }
+            }
+            voice.envelope.gate(sounding);
+        }
+        self.gain += (self.target_gain - self.gain) * GAIN_SMOOTHING;
+
+        let sounding = self
+            .voices
+            .iter()
+            .filter(|voice| !voice.envelope.is_idle())
+            .count();
+        if sounding == 0 {
+            out.fill(0);
+            for voice in &mut self.voices {
+                voice.phase = 0.0;
+            }
+            return;
+        }
+
+        let scale = self.gain * self.master_gain / sounding as f32;
+        for frame in out.chunks_exact_mut(self.channels) {
+            let mut left = 0.0f32;
+            let mut right = 0.0f32;
+            let modulation = self.vibrato.next();
+            for voice in &mut self.voices {
+                if voice.envelope.is_idle() {
+                    voice.phase = 0.0;
+                    continue;
+                }
+                voice.advance_glide();
+                let level = voice.envelope.next() * voice.gain;
+                let phase_step = 2.0 * PI * voice.freq * modulation / self.sample_rate as f32;
+                let value = level * self.waveform.sample(voice.phase, phase_step);
+                if self.channels == 2 {
+                    let (left_gain, right_gain) = voice.pan_gains();
+                    left += value * left_gain;
+                    right += value * right_gain;
+                } else {
+                    left += value;
+                }
+                voice.phase += phase_step;
+                if voice.phase > 2.0 * PI {
+                    voice.phase -= 2.0 * PI;
+                }
+            }
+            left *= scale;
+            right *= scale;
+            let amplitude = i16::MAX as f32 * self.compressor.gain(left.abs().max(right.abs()));
+            let mut quantize = |value: f32| {
+                let value = match self.dither.as_mut() {
+                    Some(dither) => (value + dither.next()).round(),
+                    None => value,
+                };
+                value.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            };
+            frame[0] = quantize(left * amplitude);
+            if self.channels == 2 {
+                frame[1] = quantize(right * amplitude);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+    /// A fundamental whose harmonics above Nyquist fold back between them.
+    const HIGH_FREQ: f32 = 5_000.0;
+
+    /// Renders 100 ms of `waveform` at [`HIGH_FREQ`] and returns the energy
+    /// folded back onto frequencies that are not harmonics, relative to that
+    /// of the harmonics, in dB. Without `band_limited` the waveform is given
+    /// a zero phase step, which disables its residuals.
+    fn aliasing_db(waveform: Waveform, band_limited: bool) -> f32 {
+        let step = 2.0 * PI * HIGH_FREQ / SAMPLE_RATE as f32;
+        let mut phase = 0.0;
+        let samples: Vec<f32> = (0..SAMPLE_RATE as usize / 10)
+            .map(|_| {
+                let sample = waveform.sample(phase, if band_limited { step } else { 0.0 });
+                phase += step;
+                if phase > 2.0 * PI {
+                    phase -= 2.0 * PI;
+                }
+                sample
+            })
+            .collect();
+
+        // Every harmonic and every alias of one lands on a multiple of 1 kHz.
+        let (mut harmonics, mut aliases) = (0.0, 0.0);
+        for freq in (1_000..SAMPLE_RATE / 2).step_by(1_000) {
+            let power = power_at(&samples, freq as f64);
+            if freq as f32 % HIGH_FREQ == 0.0 {
+                harmonics += power;
+            } else {
+                aliases += power;
+            }
+        }
+        10.0 * (aliases / harmonics).log10() as f32
+    }
+
+    /// Returns the power of the component of `samples` at `freq` Hz.
+    fn power_at(samples: &[f32], freq: f64) -> f64 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (idx, &sample) in samples.iter().enumerate() {
+            let angle = std::f64::consts::TAU * freq * idx as f64 / f64::from(SAMPLE_RATE);
+            re += f64::from(sample) * angle.cos();
+            im += f64::from(sample) * angle.sin();
+        }
+        (re * re + im * im) / (samples.len() * samples.len()) as f64
+    }
+
+    /// Renders `blocks` consecutive blocks of `frames` mono frames each,
+    /// holding every voice at `freqs`.
+    fn render_blocks(
+        synth: &mut Synthesizer,
+        freqs: &[f32],
+        blocks: usize,
+        frames: usize,
+    ) -> Vec<i16> {
+        let mut out = vec![0; blocks * frames];
+        for block in out.chunks_mut(frames) {
+            synth.render(freqs, block);
+        }
+        out
+    }
+
+    #[test]
+    fn edges_are_band_limited() {
+        for (waveform, limit_db) in [
+            (Waveform::Saw, -20.0),
+            (Waveform::Square, -20.0),
+            (Waveform::Triangle, -33.0),
+        ] {
+            let naive = aliasing_db(waveform, false);
+            let band_limited = aliasing_db(waveform, true);
+            assert!(
+                band_limited < limit_db && band_limited < naive - 10.0,
+                "{waveform}: {band_limited:.1} dB aliasing, {naive:.1} dB naive"
+            );
+        }
+    }
+
+    #[test]
+    fn phase_carries_over_between_blocks() {
+        let freqs = [440.0, 660.0];
+        let mut synth = Synthesizer::new(freqs.len(), 1, SAMPLE_RATE);
+        synth.set_waveform(Waveform::Saw);
+        synth.set_target_gain(1.0);
+        // Let the per-block gain smoothing settle so only the phases matter.
+        render_blocks(&mut synth, &freqs, 200, 37);
+
+        // Block lengths that are not multiples of either period.
+        let mut split = synth.clone();
+        let whole = render_blocks(&mut synth, &freqs, 1, 3 * 37);
+        let pieces = render_blocks(&mut split, &freqs, 3, 37);
+        assert_eq!(whole, pieces);
+    }
+
+    #[test]
+    fn silence_outputs_zeros_and_restarts_phases() {
+        let mut synth = Synthesizer::new(2, 2, SAMPLE_RATE);
+        synth.set_target_gain(1.0);
+        synth.set_dither(true);
+        render_blocks(&mut synth, &[440.0, 550.0], 20, 64);
+
+        // The block closing the gates still runs the instant release; once
+        // every envelope is idle, silence overwrites the whole block without
+        // dither noise.
+        render_blocks(&mut synth, &[0.0, 0.0], 1, 64);
+        let mut out = vec![123; 128];
+        synth.render(&[0.0, 0.0], &mut out);
+        assert!(out.iter().all(|&sample| sample == 0), "{out:?}");
+
+        // The next note starts a sine from zero phase.
+        synth.set_dither(false);
+        let mut out = vec![0; 2];
+        synth.render(&[440.0, 0.0], &mut out);
+        assert_eq!(out, [0, 0]);
+        synth.render(&[440.0, 0.0], &mut out);
+        assert!(out.iter().all(|&sample| sample > 0), "{out:?}");
+    }
+}